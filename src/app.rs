@@ -1,12 +1,18 @@
 use crate::config::{AppConfig, ColorMode};
+use crate::drive_lock;
+use crate::tray;
 use crate::plugins::PluginManager;
-use crate::ui::{PluginsMarketPage, PluginsManagePage, SettingsPage};
+use crate::ui::{show_download_queue, PluginsMarketPage, PluginsManagePage, SettingsPage};
 use crate::utils::BootDriveManager;
 use crate::mode::PluginMode;
 use eframe::egui;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
-use parking_lot::RwLock;
+use std::time::{Duration, Instant};
+use parking_lot::{Mutex, RwLock};
 use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
 
 #[cfg(target_os = "windows")]
 use winapi::um::dwmapi::DwmSetWindowAttribute;
@@ -15,6 +21,93 @@ use winapi::um::winuser::GetActiveWindow;
 #[cfg(target_os = "windows")]
 use std::mem;
 
+const SYSTEM_THEME_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const MIN_FONT_SCALE: f32 = 0.8;
+pub const MAX_FONT_SCALE: f32 = 2.0;
+
+// 导航栏宽度，以逻辑点为单位，随显示缩放自动换算
+const NAV_PANEL_WIDTH: f32 = 155.0;
+// 收起后只显示图标的导航栏宽度
+const NAV_RAIL_WIDTH: f32 = 36.0;
+// 所有窗口和插件源合计同时进行的下载数，超出的任务排队等待
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+// 窗口宽度（逻辑点）低于此值时导航栏自动收起为图标栏，界面缩放较大时为内容留出空间
+const NARROW_WINDOW_WIDTH: f32 = 560.0;
+
+// 后台初始化所处的阶段，供加载界面显示
+pub const INIT_STAGE_SCANNING: u8 = 0;
+pub const INIT_STAGE_LOADING_PLUGINS: u8 = 1;
+
+// 扫描启动盘、读取本地插件等需要访问文件系统的初始化结果。
+// 驱动器较多或 U 盘较慢时耗时明显，可在后台线程中准备好再创建界面
+pub struct AppInit {
+    config: Arc<RwLock<AppConfig>>,
+    boot_drive_manager: BootDriveManager,
+    plugin_manager: PluginManager,
+    is_first_launch: bool,
+}
+
+impl AppInit {
+    pub fn prepare(mode: PluginMode, config: Arc<RwLock<AppConfig>>, stage: &AtomicU8) -> Self {
+        stage.store(INIT_STAGE_SCANNING, Ordering::Relaxed);
+        let mut boot_drive_manager = BootDriveManager::new(mode);
+        let mut plugin_manager = PluginManager::new(mode);
+        
+        let boot_drives = boot_drive_manager.get_all_drives();
+        
+        // 清理上次异常退出后残留的写入锁
+        for drive in &boot_drives {
+            drive_lock::remove_stale(&drive.letter);
+        }
+        
+        stage.store(INIT_STAGE_LOADING_PLUGINS, Ordering::Relaxed);
+        let mut is_first_launch = boot_drives.len() > 1 && config.read().default_boot_drive.is_none();
+        
+        // 只有一个原生启动盘时，其余兼容模式的启动盘通常不是目标，直接选择原生启动盘
+        if is_first_launch && config.read().prefer_native_drive {
+            if let Some(drive) = boot_drive_manager.only_native_drive() {
+                boot_drive_manager.set_current_drive(drive.letter.clone());
+                let _ = plugin_manager.load_local_plugins(&drive.letter);
+                is_first_launch = false;
+            }
+        }
+        
+        if is_first_launch && config.read().auto_select_recent_drive {
+            if let Some(drive) = boot_drive_manager.most_recent_drive() {
+                boot_drive_manager.set_current_drive(drive.letter.clone());
+                let _ = plugin_manager.load_local_plugins(&drive.letter);
+                is_first_launch = false;
+            }
+        }
+        
+        if !is_first_launch && boot_drive_manager.get_current_drive().is_none() {
+            // 配置文件可能被手动修改，盘符统一为 "X:" 后再使用
+            let default_drive = config.read().default_boot_drive.as_deref().map(|drive| {
+                crate::utils::normalize_drive_letter(drive).unwrap_or_else(|| drive.to_string())
+            });
+            if let Some(default) = &default_drive {
+                boot_drive_manager.set_current_drive(default.clone());
+                let _ = plugin_manager.load_local_plugins(default);
+            } else if boot_drives.len() == 1 {
+                boot_drive_manager.set_current_drive(boot_drives[0].letter.clone());
+                let mut config = config.write();
+                config.default_boot_drive = Some(boot_drives[0].letter.clone());
+                config.save().ok();
+                let _ = plugin_manager.load_local_plugins(&boot_drives[0].letter);
+            }
+        }
+        
+        Self {
+            config,
+            boot_drive_manager,
+            plugin_manager,
+            is_first_launch,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum Page {
     PluginMarket,
@@ -22,12 +115,28 @@ pub enum Page {
     Settings,
 }
 
+struct ResidueDialog {
+    drive: String,
+    files: Vec<PathBuf>,
+    // 清理完成后删除的文件数和失败数
+    cleaned: Option<(usize, usize)>,
+}
+
+// 首次启动选择启动盘后、应用之前显示的确认摘要
+struct BootDriveSummary {
+    drive: String,
+    display_name: String,
+    save_as_default: bool,
+    // 已启用和已禁用的插件数，读取失败时为错误信息
+    plugin_count: Result<(usize, usize), String>,
+}
+
 pub struct CloudPEApp {
     config: Arc<RwLock<AppConfig>>,
     current_page: Page,
     plugin_manager: Arc<RwLock<PluginManager>>,
     boot_drive_manager: Arc<RwLock<BootDriveManager>>,
-    _runtime: Arc<Runtime>,
+    runtime: Arc<Runtime>,
     mode: PluginMode,
     
     market_page: PluginsMarketPage,
@@ -37,39 +146,66 @@ pub struct CloudPEApp {
     show_boot_drive_dialog: bool,
     selected_boot_drive: Option<String>,
     save_as_default: bool,
+    boot_drive_summary: Option<BootDriveSummary>,
     _is_first_launch: bool,
+    
+    // 在同一进程中以独立窗口打开的其他插件源
+    secondary_apps: Vec<CloudPEApp>,
+    // 是否为独立窗口中的插件源，独立窗口不提供切换插件源和打开新窗口
+    is_secondary: bool,
+    // 切换插件源后仍有下载任务的旧插件源，任务继续在后台进行并显示在下载队列中，全部结束后释放
+    background_apps: Vec<CloudPEApp>,
+    // 正在后台初始化、准备切换到或在新窗口中打开的插件源
+    pending_sources: Vec<PendingSource>,
+    // 同一进程中所有窗口共用的下载名额
+    download_slots: Arc<Semaphore>,
+    
+    system_dark: bool,
+    last_system_theme_check: Instant,
+    // 最近一次成功应用到标题栏的深色状态
+    applied_dark: Option<bool>,
+    // 已检查过残留文件的启动盘，切换启动盘后重新检查
+    residue_checked_drive: Option<String>,
+    residue_dialog: Option<ResidueDialog>,
+    // 已检查过写入锁的启动盘，以及启动盘正被其他进程使用时的提示
+    lock_checked_drive: Option<String>,
+    lock_warning: Option<String>,
+    // 上一帧的显示缩放，窗口移到其他显示器后可能变化
+    last_pixels_per_point: Option<f32>,
+}
+
+// 与启动时相同，扫描启动盘等初始化在后台线程中进行，完成后在界面线程中创建应用
+struct PendingSource {
+    mode: PluginMode,
+    // 完成后在新窗口中打开，否则切换当前窗口的插件源
+    open_in_window: bool,
+    init: Arc<Mutex<Option<AppInit>>>,
 }
 
 impl CloudPEApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, runtime: Arc<Runtime>, mode: PluginMode) -> Self {
-        let mut config = AppConfig::load().unwrap_or_default();
-        
-        apply_theme(&cc.egui_ctx, &config.color_mode);
-        
-        let boot_drive_manager = Arc::new(RwLock::new(BootDriveManager::new(mode)));
-        let plugin_manager = Arc::new(RwLock::new(PluginManager::new(mode)));
+    pub fn from_init(
+        ctx: &egui::Context,
+        runtime: Arc<Runtime>,
+        download_slots: Arc<Semaphore>,
+        mode: PluginMode,
+        init: AppInit,
+    ) -> Self {
+        let AppInit { config, boot_drive_manager, plugin_manager, is_first_launch } = init;
         
-        let boot_drives = boot_drive_manager.read().scan_boot_drives();
-        let is_first_launch = boot_drives.len() > 1 && config.default_boot_drive.is_none();
-        
-        if !is_first_launch {
-            if let Some(default) = &config.default_boot_drive {
-                boot_drive_manager.write().set_current_drive(default.clone());
-                let _ = plugin_manager.write().load_local_plugins(default);
-            } else if boot_drives.len() == 1 {
-                boot_drive_manager.write().set_current_drive(boot_drives[0].letter.clone());
-                config.default_boot_drive = Some(boot_drives[0].letter.clone());
-                config.save().ok();
-                let _ = plugin_manager.write().load_local_plugins(&boot_drives[0].letter);
-            }
+        let system_dark = detect_system_dark();
+        {
+            let config = config.read();
+            apply_theme(ctx, &config, resolve_is_dark(&config.color_mode, system_dark));
         }
         
-        let config = Arc::new(RwLock::new(config));
+        let boot_drive_manager = Arc::new(RwLock::new(boot_drive_manager));
+        let plugin_manager = Arc::new(RwLock::new(plugin_manager));
         
         let market_page = PluginsMarketPage::new(
             plugin_manager.clone(),
             config.clone(),
             runtime.clone(),
+            download_slots.clone(),
             boot_drive_manager.clone(),
             mode,
         );
@@ -78,6 +214,7 @@ impl CloudPEApp {
             boot_drive_manager.clone(),
             mode,
             runtime.clone(),
+            download_slots.clone(),
             config.clone(),
         );
         let settings_page = SettingsPage::new(
@@ -91,7 +228,7 @@ impl CloudPEApp {
             current_page: Page::PluginMarket,
             plugin_manager,
             boot_drive_manager,
-            _runtime: runtime,
+            runtime,
             mode,
             market_page,
             manage_page,
@@ -99,56 +236,666 @@ impl CloudPEApp {
             show_boot_drive_dialog: is_first_launch,
             selected_boot_drive: None,
             save_as_default: false,
+            boot_drive_summary: None,
             _is_first_launch: is_first_launch,
+            secondary_apps: Vec::new(),
+            is_secondary: false,
+            background_apps: Vec::new(),
+            pending_sources: Vec::new(),
+            download_slots,
+            system_dark,
+            last_system_theme_check: Instant::now(),
+            applied_dark: None,
+            residue_checked_drive: None,
+            residue_dialog: None,
+            lock_checked_drive: None,
+            lock_warning: None,
+            last_pixels_per_point: None,
         }
     }
-}
-
-impl eframe::App for CloudPEApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    
+    // 在新窗口中打开另一个插件源，共享运行时、配置和下载名额。
+    // 同一插件源只保留一份：已在独立窗口中打开时不重复打开，在后台时直接移到新窗口，不会丢失其任务
+    fn open_secondary(&mut self, mode: PluginMode) {
+        if mode == self.mode || self.secondary_apps.iter().any(|app| app.mode == mode) {
+            return;
+        }
+        
+        if let Some(index) = self.background_apps.iter().position(|app| app.mode == mode) {
+            let mut app = self.background_apps.remove(index);
+            app.is_secondary = true;
+            self.secondary_apps.push(app);
+            return;
+        }
+        
+        self.prepare_source(mode, true);
+    }
+    
+    fn has_source(&self, mode: PluginMode) -> bool {
+        mode == self.mode
+            || self.secondary_apps.iter().any(|app| app.mode == mode)
+            || self.background_apps.iter().any(|app| app.mode == mode)
+    }
+    
+    // 在后台线程中初始化插件源，同一插件源正在准备时只更新完成后的去向
+    fn prepare_source(&mut self, mode: PluginMode, open_in_window: bool) {
+        if let Some(pending) = self.pending_sources.iter_mut().find(|pending| pending.mode == mode) {
+            pending.open_in_window = open_in_window;
+            return;
+        }
+        
+        let init = Arc::new(Mutex::new(None));
+        let init_clone = init.clone();
+        let config = self.config.clone();
+        self.runtime.spawn_blocking(move || {
+            let prepared = AppInit::prepare(mode, config, &AtomicU8::new(INIT_STAGE_SCANNING));
+            *init_clone.lock() = Some(prepared);
+        });
+        
+        self.pending_sources.push(PendingSource { mode, open_in_window, init });
+    }
+    
+    // 取出已初始化完成的插件源，按请求切换或在新窗口中打开
+    fn poll_pending_sources(&mut self, ctx: &egui::Context) {
+        if self.pending_sources.is_empty() {
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_millis(100));
+        
+        let mut index = 0;
+        while index < self.pending_sources.len() {
+            let Some(init) = self.pending_sources[index].init.lock().take() else {
+                index += 1;
+                continue;
+            };
+            
+            let pending = self.pending_sources.remove(index);
+            
+            // 初始化期间该插件源可能已在其他窗口或后台打开，直接使用已有的，避免同一插件源出现两份
+            if self.has_source(pending.mode) {
+                if pending.open_in_window {
+                    self.open_secondary(pending.mode);
+                } else {
+                    self.switch_mode(ctx, pending.mode);
+                }
+                continue;
+            }
+            
+            let mut app = Self::from_init(ctx, self.runtime.clone(), self.download_slots.clone(), pending.mode, init);
+            if pending.open_in_window {
+                app.is_secondary = true;
+                self.secondary_apps.push(app);
+            } else {
+                self.install_mode(ctx, app);
+            }
+        }
+    }
+    
+    // 在当前窗口中切换插件源，不重新启动程序。
+    // 旧插件源仍有下载任务时转到后台继续；目标插件源已在后台或独立窗口中时直接接管，不会丢失其任务，
+    // 否则在后台初始化完成后再切换，初始化期间界面保持可用
+    fn switch_mode(&mut self, ctx: &egui::Context, mode: PluginMode) {
+        if mode == self.mode {
+            return;
+        }
+        
+        if let Some(index) = self.background_apps.iter().position(|app| app.mode == mode) {
+            let next = self.background_apps.remove(index);
+            self.install_mode(ctx, next);
+        } else if let Some(index) = self.secondary_apps.iter().position(|app| app.mode == mode) {
+            let mut next = self.secondary_apps.remove(index);
+            next.is_secondary = false;
+            self.install_mode(ctx, next);
+        } else {
+            self.prepare_source(mode, false);
+        }
+    }
+    
+    // 用 next 替换当前窗口中的插件源
+    fn install_mode(&mut self, ctx: &egui::Context, next: CloudPEApp) {
+        let mode = next.mode;
+        if mode == self.mode {
+            return;
+        }
+        
+        let secondary_apps = std::mem::take(&mut self.secondary_apps);
+        let background_apps = std::mem::take(&mut self.background_apps);
+        let pending_sources = std::mem::take(&mut self.pending_sources);
+        let current_page = self.current_page;
+        
+        let previous = std::mem::replace(self, next);
+        self.secondary_apps = secondary_apps;
+        self.background_apps = background_apps;
+        self.pending_sources = pending_sources;
+        self.current_page = current_page;
+        
+        if previous.has_active_tasks() {
+            self.background_apps.push(previous);
+        }
+        
+        crate::app_log::log(format!("切换插件源: {}", mode.get_title()));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(mode.get_title().to_string()));
+    }
+    
+    // 本窗口、独立窗口及后台插件源中进行中的任务数
+    fn task_counter(&self) -> Box<dyn Fn() -> usize> {
+        let counters: Vec<_> = std::iter::once(self)
+            .chain(&self.secondary_apps)
+            .chain(&self.background_apps)
+            .flat_map(|app| [app.market_page.task_counter(), app.manage_page.task_counter()])
+            .collect();
+        Box::new(move || counters.iter().map(|count| count()).sum())
+    }
+    
+    fn has_active_tasks(&self) -> bool {
+        !self.market_page.queue_entries().is_empty() || !self.manage_page.queue_entries().is_empty()
+    }
+    
+    fn show_secondary_windows(&mut self, ctx: &egui::Context) {
+        let mut closed = Vec::new();
+        
+        for (index, app) in self.secondary_apps.iter_mut().enumerate() {
+            let viewport_id = egui::ViewportId::from_hash_of(("secondary_source", app.mode));
+            let builder = egui::ViewportBuilder::default()
+                .with_title(app.mode.get_title())
+                .with_inner_size([1024.0, 630.0])
+                .with_min_inner_size([800.0, 600.0]);
+            
+            ctx.show_viewport_immediate(viewport_id, builder, |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // 不支持多窗口的后端无法单独显示，直接关闭
+                    closed.push(index);
+                    return;
+                }
+                
+                app.show_ui(ctx);
+                
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    closed.push(index);
+                }
+            });
+        }
+        
+        // 关闭的窗口仍有下载任务时与切换插件源相同，转到后台继续
+        for index in closed.into_iter().rev() {
+            let mut app = self.secondary_apps.remove(index);
+            if app.has_active_tasks() {
+                app.is_secondary = false;
+                self.background_apps.push(app);
+            }
+        }
+    }
+    
+    // 布局均以逻辑点计算，缩放变化时只需让居中的对话框重新定位并立即重绘
+    fn handle_scale_change(&mut self, ctx: &egui::Context) {
+        let pixels_per_point = ctx.pixels_per_point();
+        
+        if let Some(last) = self.last_pixels_per_point {
+            if (last - pixels_per_point).abs() > f32::EPSILON {
+                ctx.memory_mut(|memory| memory.reset_areas());
+                ctx.request_repaint();
+            }
+        }
+        
+        self.last_pixels_per_point = Some(pixels_per_point);
+    }
+    
+    // Ctrl+1/2/3 切换页面，输入框获得焦点时不处理，避免干扰输入
+    fn handle_page_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        
+        let shortcuts = [
+            (egui::Key::Num1, Page::PluginMarket),
+            (egui::Key::Num2, Page::PluginManage),
+            (egui::Key::Num3, Page::Settings),
+        ];
+        
+        for (key, page) in shortcuts {
+            let shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, key);
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.current_page = page;
+            }
+        }
+    }
+    
+    fn show_ui(&mut self, ctx: &egui::Context) {
         if self.show_boot_drive_dialog {
             self.show_boot_drive_selection_dialog(ctx);
             return;
         }
         
-        egui::SidePanel::left("nav_panel")
-            .resizable(false)
-            .default_width(150.0)
-            .show(ctx, |ui| {
-                ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                    ui.set_min_width(155.0);
-                    ui.label(egui::RichText::new(self.mode.get_title()).size(16.0));
-                    ui.separator();
-                    
-                    if ui.selectable_label(self.current_page == Page::PluginMarket, self.mode.get_plugin_market_name()).clicked() {
-                        self.current_page = Page::PluginMarket;
+        self.handle_scale_change(ctx);
+        self.handle_page_shortcuts(ctx);
+        self.check_drive_lock();
+        self.check_residue_files();
+        self.poll_pending_sources(ctx);
+        
+        let mut open_mode = None;
+        let mut switch_mode = None;
+        
+        // 自动收起不修改设置，窗口变宽后恢复原来的状态
+        let auto_collapsed = ctx.screen_rect().width() < NARROW_WINDOW_WIDTH;
+        let collapsed = self.config.read().nav_collapsed || auto_collapsed;
+        let mut toggle_collapsed = false;
+        let nav_items = [
+            (Page::PluginMarket, "🛒", self.mode.get_plugin_market_name().to_string(), "Ctrl+1"),
+            (Page::PluginManage, "📦", self.mode.get_plugin_manage_name().to_string(), "Ctrl+2"),
+            (Page::Settings, "⚙", "设置".to_string(), "Ctrl+3"),
+        ];
+        
+        let mut nav_panel = egui::SidePanel::left(egui::Id::new("nav_panel").with(self.mode))
+            .resizable(false);
+        nav_panel = if collapsed {
+            nav_panel.exact_width(NAV_RAIL_WIDTH)
+        } else {
+            nav_panel.default_width(NAV_PANEL_WIDTH)
+        };
+        
+        nav_panel.show(ctx, |ui| {
+            self.apply_panel_accent(ui);
+            ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                if collapsed {
+                    if ui.add_enabled(!auto_collapsed, egui::Button::new("▶"))
+                        .on_hover_text("展开导航栏")
+                        .on_disabled_hover_text("窗口较窄，导航栏已自动收起")
+                        .clicked()
+                    {
+                        toggle_collapsed = true;
                     }
+                } else {
+                    ui.set_min_width(NAV_PANEL_WIDTH);
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(self.mode.get_title()).size(16.0));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("◀").on_hover_text("收起导航栏").clicked() {
+                                toggle_collapsed = true;
+                            }
+                        });
+                    });
+                }
+                ui.separator();
+                
+                for (page, icon, name, shortcut) in &nav_items {
+                    let response = if collapsed {
+                        ui.selectable_label(self.current_page == *page, *icon)
+                            .on_hover_text(format!("{} ({})", name, shortcut))
+                    } else {
+                        ui.selectable_label(self.current_page == *page, name.as_str())
+                            .on_hover_text(*shortcut)
+                    };
                     
-                    if ui.selectable_label(self.current_page == Page::PluginManage, self.mode.get_plugin_manage_name()).clicked() {
-                        self.current_page = Page::PluginManage;
+                    if response.clicked() {
+                        self.current_page = *page;
                     }
+                }
+                
+                ui.separator();
+                if !self.is_secondary {
+                    let menu_title = if collapsed {
+                        "🔀".to_string()
+                    } else {
+                        format!("插件源: {}", self.mode.get_server_name())
+                    };
+                    ui.menu_button(menu_title, |ui| {
+                        for mode in [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless] {
+                            if ui.selectable_label(mode == self.mode, mode.get_server_name()).clicked() {
+                                switch_mode = Some(mode);
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("正在使用的插件源，切换后进行中的下载会在后台继续");
                     
-                    if ui.selectable_label(self.current_page == Page::Settings, "设置").clicked() {
-                        self.current_page = Page::Settings;
-                    }
-                });
+                    // 独立窗口由主窗口统一管理，只在主窗口中打开新窗口
+                    let menu_title = if collapsed { "🗗" } else { "在新窗口打开" };
+                    ui.menu_button(menu_title, |ui| {
+                        for mode in [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless] {
+                            if mode != self.mode && ui.button(mode.get_server_name()).clicked() {
+                                open_mode = Some(mode);
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("在新窗口打开");
+                }
+                
+                for pending in &self.pending_sources {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        if !collapsed {
+                            ui.label(format!("正在加载 {}", pending.mode.get_server_name()));
+                        }
+                    })
+                    .response
+                    .on_hover_text(format!("正在扫描启动盘并读取 {} 插件，完成后自动打开", pending.mode.get_server_name()));
+                }
+                
+                if crate::network::is_offline_mode() {
+                    ui.separator();
+                    let text = if collapsed { "✈" } else { "✈ 离线模式" };
+                    ui.label(egui::RichText::new(text).color(egui::Color32::from_rgb(255, 160, 0)))
+                        .on_hover_text("已禁止所有网络请求，可在设置中关闭");
+                }
             });
+        });
+        
+        if toggle_collapsed {
+            let mut config = self.config.write();
+            config.nav_collapsed = !collapsed;
+            let _ = config.save();
+        }
+        
+        self.show_download_queue_panel(ctx);
         
         egui::CentralPanel::default().show(ctx, |ui| {
-            match self.current_page {
-                Page::PluginMarket => self.market_page.show(ui, ctx),
-                Page::PluginManage => self.manage_page.show(ui, ctx),
-                Page::Settings => self.settings_page.show(ui, ctx),
-            }
+            self.apply_panel_accent(ui);
+            // 多个窗口共用同一个 Context，按模式区分控件 ID
+            ui.push_id(self.mode, |ui| {
+                match self.current_page {
+                    Page::PluginMarket => self.market_page.show(ui, ctx),
+                    Page::PluginManage => self.manage_page.show(ui, ctx),
+                    Page::Settings => self.settings_page.show(ui, ctx),
+                }
+            });
         });
         
+        self.show_residue_dialog(ctx);
+        self.show_lock_warning(ctx);
+        
+        if self.market_page.take_settings_request() {
+            self.settings_page.focus_boot_drive_settings();
+            self.current_page = Page::Settings;
+        }
+        
+        if let Some(mode) = open_mode {
+            self.open_secondary(mode);
+        }
+        
+        if let Some(mode) = switch_mode {
+            self.switch_mode(ctx, mode);
+        }
+        
+        self.update_theme(ctx);
+    }
+    
+    // 底部的下载队列，汇总市场页和管理页的全部任务，没有任务时不显示
+    fn show_download_queue_panel(&mut self, ctx: &egui::Context) {
+        self.background_apps.retain(|app| app.has_active_tasks());
+        
+        let mut entries = self.market_page.queue_entries();
+        let market_count = entries.len();
+        entries.extend(self.manage_page.queue_entries());
+        let own_count = entries.len();
+        
+        // 后台插件源的任务排在后面，名称前标明所属插件源；记录每项所属的插件源以及是否来自市场页
+        let mut background_owners = Vec::new();
+        for (app_index, app) in self.background_apps.iter().enumerate() {
+            let market_entries = app.market_page.queue_entries().into_iter().map(|entry| (entry, true));
+            let manage_entries = app.manage_page.queue_entries().into_iter().map(|entry| (entry, false));
+            for (mut entry, from_market) in market_entries.chain(manage_entries) {
+                entry.plugin_name = format!("[{}] {}", app.mode.get_server_name(), entry.plugin_name);
+                entries.push(entry);
+                background_owners.push((app_index, from_market));
+            }
+        }
+        
+        if entries.is_empty() {
+            return;
+        }
+        
+        let cancelled = egui::TopBottomPanel::bottom(egui::Id::new("download_queue_panel").with(self.mode))
+            .show(ctx, |ui| {
+                self.apply_panel_accent(ui);
+                show_download_queue(ui, &entries)
+            })
+            .inner;
+        
+        if let Some(index) = cancelled {
+            let task_id = &entries[index].task_id;
+            if index < market_count {
+                self.market_page.cancel_task(task_id);
+            } else if index < own_count {
+                self.manage_page.cancel_task(task_id);
+            } else {
+                let (app_index, from_market) = background_owners[index - own_count];
+                let app = &mut self.background_apps[app_index];
+                if from_market {
+                    app.market_page.cancel_task(task_id);
+                } else {
+                    app.manage_page.cancel_task(task_id);
+                }
+            }
+        }
+        
+        // 进度在后台更新，需要持续重绘
+        ctx.request_repaint_after(Duration::from_millis(200));
+    }
+    
+    // 以本窗口插件源的主题色显示面板中的控件，不修改其他窗口共用的全局样式
+    fn apply_panel_accent(&self, ui: &mut egui::Ui) {
         let config = self.config.read();
-        apply_theme(ctx, &config.color_mode);
+        if config.mode_accent && !config.high_contrast {
+            let is_dark = ui.visuals().dark_mode;
+            apply_mode_accent(ui.visuals_mut(), self.mode, is_dark);
+        }
+    }
+    
+    // 跟随系统模式下定期重新检测系统主题，只在实际变化时更新标题栏
+    fn update_theme(&mut self, ctx: &egui::Context) {
+        if self.last_system_theme_check.elapsed() > SYSTEM_THEME_POLL_INTERVAL {
+            self.system_dark = detect_system_dark();
+            self.last_system_theme_check = Instant::now();
+        }
+        
+        let config = self.config.read();
+        let is_dark = resolve_is_dark(&config.color_mode, self.system_dark);
+        apply_theme(ctx, &config, is_dark);
+        
+        if self.applied_dark != Some(is_dark) {
+            #[cfg(target_os = "windows")]
+            let applied = unsafe { set_dwm_theme(is_dark) };
+            #[cfg(not(target_os = "windows"))]
+            let applied = true;
+            
+            if applied {
+                self.applied_dark = Some(is_dark);
+            }
+        }
+        
+        if config.color_mode == ColorMode::System {
+            ctx.request_repaint_after(SYSTEM_THEME_POLL_INTERVAL);
+        }
+    }
+}
+
+impl eframe::App for CloudPEApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        tray::update(ctx, self.config.read().minimize_to_tray, self.task_counter());
+        
+        // 隐藏到托盘后下载继续进行，从托盘菜单退出时才关闭窗口，退出前的清理照常进行
+        if tray::is_active() && !tray::quit_requested() && ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            tray::hide_window();
+        }
+        
+        self.show_ui(ctx);
+        self.show_secondary_windows(ctx);
+    }
+}
+
+// 切换后被替换、后台任务全部结束或独立窗口关闭时，释放该插件源选中的启动盘
+impl Drop for CloudPEApp {
+    fn drop(&mut self) {
+        if let Some(drive) = self.lock_checked_drive.take() {
+            drive_lock::deselect(&drive);
+        }
     }
 }
 
 impl CloudPEApp {
+    // 选定启动盘后获取写入锁，其他进程正在写入同一个启动盘时提示用户。
+    // 之后的写入操作会再次尝试获取锁，对方退出后即可正常写入。
+    // 改选其他启动盘时释放原启动盘的锁；仍有任务在写入时保留，程序退出时释放
+    fn check_drive_lock(&mut self) {
+        let current_drive = self.boot_drive_manager.read().get_current_drive();
+        if current_drive == self.lock_checked_drive {
+            return;
+        }
+        
+        if let Some(previous) = &self.lock_checked_drive {
+            if !self.has_active_tasks() {
+                drive_lock::deselect(previous);
+            }
+        }
+        self.lock_checked_drive = current_drive.clone();
+        
+        self.lock_warning = current_drive.and_then(|drive| {
+            drive_lock::select(&drive, self.mode)
+                .err()
+                .map(|holder| drive_lock::conflict_message(&drive, &holder))
+        });
+    }
+    
+    fn show_lock_warning(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.lock_warning else {
+            return;
+        };
+        
+        let mut close = false;
+        
+        egui::Window::new("启动盘正在使用中")
+            .id(egui::Id::new("drive_lock_warning").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 160, 0), message);
+                ui.label("在此期间仍可浏览插件，安装、启用、禁用等操作会被拒绝");
+                ui.separator();
+                if ui.button("知道了").clicked() {
+                    close = true;
+                }
+            });
+        
+        if close {
+            self.lock_warning = None;
+        }
+    }
+    
+    // 启动时及切换启动盘后检查上次异常中断留下的残留文件
+    fn check_residue_files(&mut self) {
+        let current_drive = self.boot_drive_manager.read().get_current_drive();
+        if current_drive == self.residue_checked_drive {
+            return;
+        }
+        self.residue_checked_drive = current_drive.clone();
+        
+        let Some(drive) = current_drive else {
+            return;
+        };
+        
+        let files = self.plugin_manager.read().find_residue_files(&drive);
+        self.residue_dialog = if files.is_empty() {
+            None
+        } else {
+            Some(ResidueDialog {
+                drive,
+                files,
+                cleaned: None,
+            })
+        };
+    }
+    
+    fn show_residue_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.residue_dialog else {
+            return;
+        };
+        
+        let mut clean = false;
+        let mut close = false;
+        
+        egui::Window::new("清理残留文件")
+            .id(egui::Id::new("residue_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match dialog.cleaned {
+                    None => {
+                        ui.label(format!(
+                            "在启动盘 {} 上发现 {} 个上次异常中断留下的文件：",
+                            dialog.drive,
+                            dialog.files.len(),
+                        ));
+                        
+                        egui::ScrollArea::vertical()
+                            .id_salt("residue_files")
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                for file in &dialog.files {
+                                    let name = file.file_name()
+                                        .map(|name| name.to_string_lossy().to_string())
+                                        .unwrap_or_default();
+                                    ui.label(format!("• {}", name));
+                                }
+                            });
+                        
+                        ui.label(egui::RichText::new("包括未下载完成的临时文件和空的插件文件，已禁用的插件不受影响").small());
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            if ui.button("清理").clicked() {
+                                clean = true;
+                            }
+                            if ui.button("忽略").clicked() {
+                                close = true;
+                            }
+                        });
+                    }
+                    Some((removed, failed)) => {
+                        ui.label(format!("已清理 {} 个文件", removed));
+                        if failed > 0 {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 100, 100),
+                                format!("{} 个文件无法删除", failed),
+                            );
+                        }
+                        ui.separator();
+                        if ui.button("关闭").clicked() {
+                            close = true;
+                        }
+                    }
+                }
+            });
+        
+        if clean {
+            if let Err(message) = drive_lock::acquire_for_write(&dialog.drive, self.mode) {
+                self.residue_dialog = None;
+                self.lock_warning = Some(message);
+                return;
+            }
+            
+            let removed = dialog.files.iter()
+                .filter(|file| std::fs::remove_file(file).is_ok())
+                .count();
+            dialog.cleaned = Some((removed, dialog.files.len() - removed));
+            
+            let drive = dialog.drive.clone();
+            let _ = self.plugin_manager.write().load_local_plugins(&drive);
+        } else if close {
+            self.residue_dialog = None;
+        }
+    }
+    
     fn show_boot_drive_selection_dialog(&mut self, ctx: &egui::Context) {
+        if self.boot_drive_summary.is_some() {
+            self.show_boot_drive_summary(ctx);
+            return;
+        }
+        
         egui::Window::new("选择启动盘")
             .collapsible(false)
             .resizable(false)
@@ -159,14 +906,22 @@ impl CloudPEApp {
                 
                 let boot_drives = self.boot_drive_manager.read().get_all_drives();
                 
+                let selected_text = match &self.selected_boot_drive {
+                    Some(letter) => boot_drives.iter()
+                        .find(|drive| &drive.letter == letter)
+                        .map(|drive| drive.display_name())
+                        .unwrap_or_else(|| letter.clone()),
+                    None => "请选择".to_string(),
+                };
+                
                 egui::ComboBox::from_label("启动盘")
-                    .selected_text(self.selected_boot_drive.as_deref().unwrap_or("请选择"))
+                    .selected_text(selected_text)
                     .show_ui(ui, |ui| {
                         for drive in &boot_drives {
                             ui.selectable_value(
                                 &mut self.selected_boot_drive,
                                 Some(drive.letter.clone()),
-                                &drive.letter,
+                                drive.display_name(),
                             );
                         }
                     });
@@ -175,66 +930,197 @@ impl CloudPEApp {
                 
                 ui.separator();
                 ui.horizontal(|ui| {
-                    if ui.button("确定").clicked() && self.selected_boot_drive.is_some() {
+                    let next = ui.add_enabled(self.selected_boot_drive.is_some(), egui::Button::new("下一步"));
+                    if next.clicked() {
                         if let Some(drive) = &self.selected_boot_drive {
-                            self.boot_drive_manager.write().set_current_drive(drive.clone());
-                            let _ = self.plugin_manager.write().load_local_plugins(drive);
+                            let display_name = boot_drives.iter()
+                                .find(|boot_drive| &boot_drive.letter == drive)
+                                .map(|boot_drive| boot_drive.display_name())
+                                .unwrap_or_else(|| drive.clone());
                             
-                            if self.save_as_default {
-                                let mut config = self.config.write();
-                                config.default_boot_drive = Some(drive.clone());
-                                config.save().ok();
-                            }
-                            
-                            self.show_boot_drive_dialog = false;
+                            // 插件目录中只有文件名需要解析，直接在界面线程中统计
+                            self.boot_drive_summary = Some(BootDriveSummary {
+                                drive: drive.clone(),
+                                display_name,
+                                save_as_default: self.save_as_default,
+                                plugin_count: PluginManager::count_local_plugins(self.mode, drive)
+                                    .map_err(|e| e.to_string()),
+                            });
                         }
                     }
                 });
             });
     }
+    
+    // 应用启动盘选择前确认摘要，返回后可以重新选择，对话框保持打开
+    fn show_boot_drive_summary(&mut self, ctx: &egui::Context) {
+        let Some(summary) = &self.boot_drive_summary else {
+            return;
+        };
+        
+        let mut confirm = false;
+        let mut back = false;
+        
+        egui::Window::new("确认启动盘")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("即将执行以下操作：");
+                ui.separator();
+                
+                ui.label(format!("• 使用启动盘 {}", summary.display_name));
+                ui.label(if summary.save_as_default {
+                    "• 设为默认启动盘，以后启动时自动选择"
+                } else {
+                    "• 仅本次使用，不设为默认启动盘"
+                });
+                match &summary.plugin_count {
+                    Ok((0, 0)) => {
+                        ui.label("• 该启动盘上目前没有插件");
+                    }
+                    Ok((enabled, disabled)) => {
+                        ui.label(format!("• 该启动盘上目前有 {} 个已启用、{} 个已禁用的插件", enabled, disabled));
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::from_rgb(255, 160, 0), format!("• 无法读取该启动盘上的插件: {}", e));
+                    }
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("确定").clicked() {
+                        confirm = true;
+                    }
+                    if ui.button("返回修改").clicked() {
+                        back = true;
+                    }
+                });
+            });
+        
+        if back {
+            self.boot_drive_summary = None;
+        } else if confirm {
+            let Some(summary) = self.boot_drive_summary.take() else {
+                return;
+            };
+            
+            self.boot_drive_manager.write().set_current_drive(summary.drive.clone());
+            let _ = self.plugin_manager.write().load_local_plugins(&summary.drive);
+            
+            if summary.save_as_default {
+                let mut config = self.config.write();
+                config.default_boot_drive = Some(summary.drive);
+                config.save().ok();
+            }
+            
+            self.show_boot_drive_dialog = false;
+        }
+    }
 }
 
-fn apply_theme(ctx: &egui::Context, mode: &ColorMode) {
-    let is_dark = match mode {
-        ColorMode::System => {
-            dark_light::detect() == dark_light::Mode::Dark
-        }
+fn resolve_is_dark(mode: &ColorMode, system_dark: bool) -> bool {
+    match mode {
+        ColorMode::System => system_dark,
         ColorMode::Light => false,
         ColorMode::Dark => true,
-    };
-    
-    let visuals = if is_dark {
+    }
+}
+
+fn detect_system_dark() -> bool {
+    dark_light::detect() == dark_light::Mode::Dark
+}
+
+// 按配置的颜色模式应用主题，供主界面创建前的加载界面使用
+pub fn apply_configured_theme(ctx: &egui::Context, config: &AppConfig) {
+    apply_theme(ctx, config, resolve_is_dark(&config.color_mode, detect_system_dark()));
+}
+
+// 样式由同一进程的所有窗口共用，这里只应用颜色模式和缩放，插件源主题色由各窗口的面板单独应用
+fn apply_theme(ctx: &egui::Context, config: &AppConfig, is_dark: bool) {
+    let mut visuals = if is_dark {
         egui::Visuals::dark()
     } else {
         egui::Visuals::light()
     };
     
+    if config.high_contrast {
+        apply_high_contrast(&mut visuals, is_dark);
+    }
+    
     ctx.set_visuals(visuals);
     
-    #[cfg(target_os = "windows")]
-    unsafe {
-        set_dwm_theme(mode, is_dark);
+    let font_scale = config.font_scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE);
+    if (ctx.zoom_factor() - font_scale).abs() > f32::EPSILON {
+        ctx.set_zoom_factor(font_scale);
+    }
+}
+
+// 以各插件源的主题色替换选中与悬停高亮，便于区分当前所在的插件源
+fn apply_mode_accent(visuals: &mut egui::Visuals, plugin_mode: PluginMode, is_dark: bool) {
+    let accent = plugin_mode.get_accent_color();
+    let selection_fill = if is_dark {
+        accent.gamma_multiply(0.6)
+    } else {
+        accent.gamma_multiply(0.35)
+    };
+    
+    visuals.selection.bg_fill = selection_fill;
+    visuals.selection.stroke = egui::Stroke::new(1.0, accent);
+    visuals.hyperlink_color = accent;
+    visuals.widgets.hovered.bg_stroke = egui::Stroke::new(1.0, accent);
+    visuals.widgets.active.bg_stroke = egui::Stroke::new(1.0, accent);
+}
+
+// 高对比度：纯色背景、纯色文字与加粗描边
+fn apply_high_contrast(visuals: &mut egui::Visuals, is_dark: bool) {
+    let (background, foreground, highlight) = if is_dark {
+        (egui::Color32::BLACK, egui::Color32::WHITE, egui::Color32::YELLOW)
+    } else {
+        (egui::Color32::WHITE, egui::Color32::BLACK, egui::Color32::from_rgb(0, 0, 200))
+    };
+    
+    visuals.override_text_color = Some(foreground);
+    visuals.panel_fill = background;
+    visuals.window_fill = background;
+    visuals.extreme_bg_color = background;
+    visuals.hyperlink_color = highlight;
+    visuals.selection.bg_fill = highlight;
+    visuals.selection.stroke = egui::Stroke::new(2.0, background);
+    visuals.window_stroke = egui::Stroke::new(2.0, foreground);
+    
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_stroke = egui::Stroke::new(2.0, foreground);
+        widget.fg_stroke = egui::Stroke::new(2.0, foreground);
     }
+    
+    visuals.widgets.inactive.weak_bg_fill = background;
+    visuals.widgets.inactive.bg_fill = background;
+    visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, highlight);
+    visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, highlight);
 }
 
 #[cfg(target_os = "windows")]
-unsafe fn set_dwm_theme(mode: &ColorMode, is_dark: bool) {
+unsafe fn set_dwm_theme(is_dark: bool) -> bool {
     let hwnd = GetActiveWindow();
-    if !hwnd.is_null() {
-        let dark_mode = match mode {
-            ColorMode::System => {
-                if dark_light::detect() == dark_light::Mode::Dark { 1i32 } else { 0i32 }
-            }
-            _ => {
-                if is_dark { 1i32 } else { 0i32 }
-            }
-        };
-        
-        DwmSetWindowAttribute(
-            hwnd as _,
-            20,
-            &dark_mode as *const _ as *mut _,
-            mem::size_of::<i32>() as u32,
-        );
+    if hwnd.is_null() {
+        return false;
     }
-}
+    
+    let dark_mode = if is_dark { 1i32 } else { 0i32 };
+    
+    DwmSetWindowAttribute(
+        hwnd as _,
+        20, // DWMWA_USE_IMMERSIVE_DARK_MODE
+        &dark_mode as *const _ as *mut _,
+        mem::size_of::<i32>() as u32,
+    );
+    
+    true
+}
\ No newline at end of file
@@ -0,0 +1,67 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+// 运行日志。最近的日志保留在内存中，同时追加写入配置目录下的日志文件。
+// PE 中配置目录可能位于内存盘，写入失败或重启后丢失时，打包诊断信息仍可使用内存中的日志
+
+// 内存中保留的日志行数
+const MAX_RECENT_LINES: usize = 500;
+// 日志文件超过此大小时改名为旧日志，重新开始记录
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+const LOG_FILE_NAME: &str = "cloud-pe.log";
+const OLD_LOG_FILE_NAME: &str = "cloud-pe.old.log";
+
+static RECENT_LINES: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+pub fn log(message: impl AsRef<str>) {
+    let line = format!("[{}] {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), message.as_ref());
+    
+    // 持有锁写入文件，多个线程同时记录时行不会交错
+    let mut recent = RECENT_LINES.lock();
+    append_to_file(&line);
+    
+    recent.push_back(line);
+    if recent.len() > MAX_RECENT_LINES {
+        recent.pop_front();
+    }
+}
+
+pub fn recent_lines() -> Vec<String> {
+    RECENT_LINES.lock().iter().cloned().collect()
+}
+
+// 已存在的日志文件，旧日志在前
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(dir) = crate::config::log_dir() else {
+        return Vec::new();
+    };
+    
+    [OLD_LOG_FILE_NAME, LOG_FILE_NAME]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn append_to_file(line: &str) {
+    let Some(dir) = crate::config::log_dir() else {
+        return;
+    };
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    
+    let path = dir.join(LOG_FILE_NAME);
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_FILE_SIZE) {
+        let _ = fs::rename(&path, dir.join(OLD_LOG_FILE_NAME));
+    }
+    
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// 只读查看插件压缩包中的文件列表，不解压任何内容。支持 7z（Cloud-PE、Edgeless 插件常用格式）和 ZIP
+
+const SEVEN_Z_SIGNATURE: &[u8] = &[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+const ZIP_END_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_END_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const ZIP64_LOCATOR_LEN: usize = 20;
+// ZIP64 结束记录中读取到目录位置为止的长度
+const ZIP64_END_LEN: u64 = 56;
+// 中央目录中每条记录最短 46 字节
+const ZIP_ENTRY_MIN_LEN: u64 = 46;
+// 中央目录结束记录最短 22 字节，之后最多是 65535 字节的注释
+const ZIP_END_MAX_SEARCH: u64 = 22 + 65535;
+
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+pub struct ArchiveContents {
+    pub format: &'static str,
+    pub entries: Vec<ArchiveEntry>,
+    // ZIP 的文件名不加密，内容加密时仍可列出文件，但需要提示
+    pub encrypted: bool,
+}
+
+pub fn list_entries(path: &Path) -> Result<ArchiveContents, String> {
+    let mut file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    
+    let mut signature = [0u8; 6];
+    let read = file.read(&mut signature).map_err(|e| format!("无法读取文件: {}", e))?;
+    let signature = &signature[..read];
+    
+    if signature.starts_with(SEVEN_Z_SIGNATURE) {
+        list_7z(path)
+    } else if signature.starts_with(ZIP_SIGNATURE) {
+        list_zip(file)
+    } else {
+        Err("不是 7z 或 ZIP 格式的压缩包，暂不支持查看内容".to_string())
+    }
+}
+
+fn list_7z(path: &Path) -> Result<ArchiveContents, String> {
+    let archive = sevenz_rust::Archive::open(path).map_err(|e| match e {
+        sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_) => {
+            "压缩包已加密，无法查看内容".to_string()
+        }
+        sevenz_rust::Error::UnsupportedCompressionMethod(method) => {
+            format!("压缩包的文件列表使用了不支持的压缩方式 {}", method)
+        }
+        e => format!("无法读取压缩包，文件可能已损坏: {}", e),
+    })?;
+    
+    // 部分压缩工具会把根目录本身记录为一个没有名称的条目
+    let entries = archive.files
+        .iter()
+        .filter(|entry| !entry.name().is_empty())
+        .map(|entry| ArchiveEntry {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            is_dir: entry.is_directory(),
+        })
+        .collect();
+    
+    Ok(ArchiveContents {
+        format: "7z",
+        entries,
+        encrypted: false,
+    })
+}
+
+// 用 zip 库读取中央目录，只列出文件，不解压内容
+fn list_zip(mut file: File) -> Result<ArchiveContents, String> {
+    check_zip_directory(&mut file)?;
+    
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| match e {
+        zip::result::ZipError::UnsupportedArchive(reason) => format!("暂不支持查看此压缩包: {}", reason),
+        _ => "无法读取压缩包，文件可能已损坏".to_string(),
+    })?;
+    
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut encrypted = false;
+    for index in 0..archive.len() {
+        // 不解密也不解压，只读取条目信息
+        let entry = archive.by_index_raw(index).map_err(|_| "压缩包的文件目录已损坏".to_string())?;
+        encrypted |= entry.encrypted();
+        entries.push(ArchiveEntry {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            is_dir: entry.is_dir(),
+        });
+    }
+    
+    Ok(ArchiveContents {
+        format: "ZIP",
+        entries,
+        encrypted,
+    })
+}
+
+// 交给 zip 库之前先核对中央目录结束记录（含 ZIP64 记录）：目录必须完整位于文件内，
+// 且按最短 46 字节的记录计算也能容纳声明的条目数。损坏的记录可能声明极大的条目数，
+// 读取时会按此预先分配内存
+fn check_zip_directory(file: &mut File) -> Result<(), String> {
+    let corrupt = |_| "无法读取压缩包，文件可能已损坏".to_string();
+    
+    let len = file.seek(SeekFrom::End(0)).map_err(corrupt)?;
+    let search_len = len.min(ZIP_END_MAX_SEARCH);
+    file.seek(SeekFrom::Start(len - search_len)).map_err(corrupt)?;
+    let mut tail = vec![0u8; search_len as usize];
+    file.read_exact(&mut tail).map_err(corrupt)?;
+    
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(&tail, i) == Some(ZIP_END_SIGNATURE))
+        .ok_or("找不到压缩包的文件目录，文件可能不完整")?;
+    
+    let mut count = read_u16(&tail, end + 10).unwrap_or(0) as u64;
+    let mut directory_size = read_u32(&tail, end + 12).unwrap_or(0) as u64;
+    let mut directory_offset = read_u32(&tail, end + 16).unwrap_or(0) as u64;
+    
+    // ZIP64 的条目数、目录大小和位置记录在另一条结束记录中，由紧挨着的定位记录给出其位置
+    if count == u16::MAX as u64 || directory_size == u32::MAX as u64 || directory_offset == u32::MAX as u64 {
+        let locator = end.checked_sub(ZIP64_LOCATOR_LEN)
+            .filter(|&locator| read_u32(&tail, locator) == Some(ZIP64_LOCATOR_SIGNATURE))
+            .ok_or("压缩包的文件目录已损坏")?;
+        let record_offset = read_u64(&tail, locator + 8).unwrap_or(u64::MAX);
+        if record_offset.checked_add(ZIP64_END_LEN).is_none_or(|record_end| record_end > len) {
+            return Err("压缩包的文件目录已损坏".to_string());
+        }
+        
+        let mut record = [0u8; ZIP64_END_LEN as usize];
+        file.seek(SeekFrom::Start(record_offset)).map_err(corrupt)?;
+        file.read_exact(&mut record).map_err(corrupt)?;
+        if read_u32(&record, 0) != Some(ZIP64_END_SIGNATURE) {
+            return Err("压缩包的文件目录已损坏".to_string());
+        }
+        count = read_u64(&record, 32).unwrap_or(u64::MAX);
+        directory_size = read_u64(&record, 40).unwrap_or(u64::MAX);
+        directory_offset = read_u64(&record, 48).unwrap_or(u64::MAX);
+    }
+    
+    let fits = directory_offset.checked_add(directory_size).is_some_and(|directory_end| directory_end <= len)
+        && count.checked_mul(ZIP_ENTRY_MIN_LEN).is_some_and(|min_size| min_size <= directory_size);
+    if !fits {
+        return Err("压缩包的文件目录已损坏".to_string());
+    }
+    
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+}
@@ -1,7 +1,22 @@
+use crate::mode::PluginMode;
+use crate::plugins::PluginCategory;
+use crate::utils::is_dir_writable;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use anyhow::Result;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("无法获取配置目录")]
+    NoConfigDir,
+    #[error("读写配置文件失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("解析配置文件失败: {0}")]
+    Parse(#[from] serde_json::Error),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ColorMode {
@@ -19,12 +34,187 @@ impl Default for ColorMode {
     }
 }
 
+// 单独下载插件完成后的操作
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum PostDownloadAction {
+    #[serde(rename = "none")]
+    None,
+    #[default]
+    #[serde(rename = "toast")]
+    Toast,
+    #[serde(rename = "open-folder")]
+    OpenFolder,
+    #[serde(rename = "open-file")]
+    OpenFile,
+}
+
+// 插件频道，测试版可以获取更新但未经充分验证的插件
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ReleaseChannel {
+    #[default]
+    #[serde(rename = "stable")]
+    Stable,
+    #[serde(rename = "testing")]
+    Testing,
+}
+
+// 检测插件源是否可用的方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ConnectTestStrategy {
+    // 只发送 HEAD 请求，不下载响应内容
+    #[default]
+    #[serde(rename = "head")]
+    Head,
+    // 完整请求测试地址并检查响应内容
+    #[serde(rename = "full")]
+    Full,
+}
+
+// 插件标注的适用平台与当前插件源不符时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum CompatibilityCheck {
+    // 安装前提示，确认后仍可安装
+    #[default]
+    #[serde(rename = "warn")]
+    Warn,
+    // 拒绝安装
+    #[serde(rename = "block")]
+    Block,
+    #[serde(rename = "off")]
+    Off,
+}
+
+impl ReleaseChannel {
+    // 测试版在接口地址后附加 channel 参数，正式版保持原地址
+    pub fn apply_to_url(&self, url: &str) -> String {
+        match self {
+            ReleaseChannel::Stable => url.to_string(),
+            ReleaseChannel::Testing => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!("{}{}channel=testing", url, separator)
+            }
+        }
+    }
+}
+
+// 访问需要认证的插件源或下载镜像时附加的请求头，按主机名匹配
+#[derive(Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SourceAuth {
+    pub host: String,
+    pub header_name: String,
+    pub header_value: String,
+}
+
+// 请求头的值通常是令牌，调试输出中隐去
+impl std::fmt::Debug for SourceAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SourceAuth")
+            .field("host", &self.host)
+            .field("header_name", &self.header_name)
+            .field("header_value", &"***")
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub color_mode: ColorMode,
     pub download_threads: u32,
+    // 下载出错后自动重试的次数，可续传的下载从中断处继续
+    #[serde(default = "default_download_retries")]
+    pub download_retries: u32,
+    // 下载时连接和等待数据的超时时间（秒）
+    #[serde(default = "default_download_timeout_secs")]
+    pub download_timeout_secs: u64,
     pub default_boot_drive: Option<String>,
-    pub default_download_path: Option<PathBuf>,
+    // 每个插件源各自的默认下载路径，通过 download_path 和 set_download_path 读写
+    #[serde(default)]
+    pub download_paths: HashMap<PluginMode, PathBuf>,
+    // 旧版本所有插件源共用的默认下载路径，只在读取时保留，由 migrate_download_path 迁移
+    #[serde(default, rename = "default_download_path", skip_serializing)]
+    legacy_download_path: Option<PathBuf>,
+    #[serde(default)]
+    pub auto_select_recent_drive: bool,
+    #[serde(default = "default_true")]
+    pub prefer_native_drive: bool,
+    #[serde(default = "default_true")]
+    pub mode_accent: bool,
+    #[serde(default = "default_font_scale")]
+    pub font_scale: f32,
+    #[serde(default)]
+    pub high_contrast: bool,
+    // 证书固定：启用后只信任 pinned_certificate_path 指定的证书
+    #[serde(default)]
+    pub tls_pinning: bool,
+    #[serde(default)]
+    pub pinned_certificate_path: Option<PathBuf>,
+    #[serde(default)]
+    pub safe_install: bool,
+    #[serde(default)]
+    pub post_download_action: PostDownloadAction,
+    #[serde(default = "default_true")]
+    pub show_new_badges: bool,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    // 禁用插件、恢复备份等操作前是否弹出确认
+    #[serde(default = "default_true")]
+    pub confirm_destructive: bool,
+    // 关闭窗口时隐藏到系统托盘，下载在后台继续
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    // 导航栏收起为图标栏
+    #[serde(default)]
+    pub nav_collapsed: bool,
+    // 离线模式：禁止所有网络请求，只管理启动盘上的本地插件
+    #[serde(default)]
+    pub offline_mode: bool,
+    // 所有网络请求经由的 HTTP/HTTPS 代理地址，PE 部署环境中常需通过企业代理访问外网
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    // 在后台预取其他插件源的插件列表并缓存，切换插件源时直接使用，无法联网时显示缓存的列表
+    #[serde(default)]
+    pub prefetch_plugin_lists: bool,
+    // 单独下载插件时优先使用服务器提供的文件名
+    #[serde(default)]
+    pub use_server_filename: bool,
+    // 单独下载插件时按所属分类建立子文件夹
+    #[serde(default)]
+    pub category_subfolder: bool,
+    #[serde(default)]
+    pub compatibility_check: CompatibilityCheck,
+    // 启动盘上的插件比市场中的版本新时，是否提供降级安装
+    #[serde(default)]
+    pub allow_downgrade: bool,
+    #[serde(default)]
+    pub source_auth: Vec<SourceAuth>,
+    #[serde(default)]
+    pub connect_test: ConnectTestStrategy,
+    // 搜索结果每次显示的数量，点击"显示更多"后继续显示下一批
+    #[serde(default = "default_search_page_size")]
+    pub search_page_size: usize,
+    // 最近的搜索关键词，最新的在前
+    #[serde(default)]
+    pub search_history: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_download_retries() -> u32 {
+    3
+}
+
+fn default_download_timeout_secs() -> u64 {
+    30
+}
+
+fn default_font_scale() -> f32 {
+    1.0
+}
+
+fn default_search_page_size() -> usize {
+    50
 }
 
 impl Default for AppConfig {
@@ -32,14 +222,42 @@ impl Default for AppConfig {
         Self {
             color_mode: ColorMode::System,
             download_threads: 8,
+            download_retries: default_download_retries(),
+            download_timeout_secs: default_download_timeout_secs(),
             default_boot_drive: None,
-            default_download_path: None,
+            download_paths: HashMap::new(),
+            legacy_download_path: None,
+            auto_select_recent_drive: false,
+            prefer_native_drive: true,
+            mode_accent: true,
+            font_scale: 1.0,
+            high_contrast: false,
+            tls_pinning: false,
+            pinned_certificate_path: None,
+            safe_install: false,
+            post_download_action: PostDownloadAction::Toast,
+            show_new_badges: true,
+            channel: ReleaseChannel::Stable,
+            confirm_destructive: true,
+            minimize_to_tray: false,
+            nav_collapsed: false,
+            offline_mode: false,
+            proxy_url: None,
+            prefetch_plugin_lists: false,
+            use_server_filename: false,
+            category_subfolder: false,
+            compatibility_check: CompatibilityCheck::Warn,
+            allow_downgrade: false,
+            source_auth: Vec::new(),
+            connect_test: ConnectTestStrategy::Head,
+            search_page_size: 50,
+            search_history: Vec::new(),
         }
     }
 }
 
 impl AppConfig {
-    pub fn load() -> Result<Self> {
+    pub fn load() -> Result<Self, ConfigError> {
         let config_path = Self::config_path()?;
         
         if config_path.exists() {
@@ -50,7 +268,24 @@ impl AppConfig {
         }
     }
     
-    pub fn save(&self) -> Result<()> {
+    pub fn download_path(&self, mode: PluginMode) -> Option<PathBuf> {
+        self.download_paths.get(&mode).cloned()
+    }
+    
+    pub fn set_download_path(&mut self, mode: PluginMode, path: PathBuf) {
+        self.download_paths.insert(mode, path);
+    }
+    
+    // 旧配置只有一个共用的默认下载路径，首次加载时归入当前插件源。返回 true 表示配置有变化需要保存
+    pub fn migrate_download_path(&mut self, mode: PluginMode) -> bool {
+        let Some(path) = self.legacy_download_path.take() else {
+            return false;
+        };
+        self.download_paths.entry(mode).or_insert(path);
+        true
+    }
+    
+    pub fn save(&self) -> Result<(), ConfigError> {
         let config_path = Self::config_path()?;
         
         if let Some(parent) = config_path.parent() {
@@ -63,9 +298,193 @@ impl AppConfig {
         Ok(())
     }
     
-    fn config_path() -> Result<PathBuf> {
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
-        Ok(config_dir.join("CloudPE").join("plugin_market.json"))
+    // 把网络相关的配置应用到全局客户端设置
+    pub fn apply_network_settings(&self) {
+        crate::network::set_pinned_certificate(self.tls_pinning, self.pinned_certificate_path.as_deref());
+        crate::network::set_offline_mode(self.offline_mode);
+        crate::network::set_proxy_url(self.proxy_url.clone());
+        crate::network::set_download_timeout(self.download_timeout_secs);
+        crate::network::set_source_auth(self.source_auth.clone());
+    }
+    
+    fn config_path() -> Result<PathBuf, ConfigError> {
+        Ok(data_dir()?.join("plugin_market.json"))
+    }
+}
+
+// 数据目录在首次使用时确定，返回目录以及是否为程序所在目录下的便携位置
+static DATA_DIR: Lazy<Option<(PathBuf, bool)>> = Lazy::new(resolve_data_dir);
+
+// 优先使用用户配置目录。PE 中用户配置目录可能只读，此时改用程序所在目录，
+// 否则每次保存都会静默失败，所有设置在重启后丢失
+fn resolve_data_dir() -> Option<(PathBuf, bool)> {
+    let usable = |dir: &PathBuf| fs::create_dir_all(dir).is_ok() && is_dir_writable(dir);
+    
+    if let Some(dir) = dirs::config_dir().map(|dir| dir.join("CloudPE")) {
+        if usable(&dir) {
+            return Some((dir, false));
+        }
     }
+    
+    let portable = std::env::current_exe().ok()?.parent()?.join("CloudPE-Data");
+    usable(&portable).then_some((portable, true))
+}
+
+fn data_dir() -> Result<PathBuf, ConfigError> {
+    DATA_DIR.as_ref()
+        .map(|(dir, _)| dir.clone())
+        .ok_or(ConfigError::NoConfigDir)
+}
+
+// 运行日志所在目录
+pub fn log_dir() -> Option<PathBuf> {
+    data_dir().ok().map(|dir| dir.join("logs"))
+}
+
+// 用户配置目录无法写入、设置改为保存在程序所在目录时返回该目录
+pub fn portable_data_dir() -> Option<PathBuf> {
+    DATA_DIR.as_ref()
+        .filter(|(_, portable)| *portable)
+        .map(|(dir, _)| dir.clone())
+}
+
+fn known_plugins_path(mode: PluginMode) -> Result<PathBuf, ConfigError> {
+    Ok(data_dir()?.join(format!("known_plugins_{}.json", mode.get_server_name())))
+}
+
+// 上次查看插件市场时的插件 ID，从未记录过时返回 None
+pub fn load_known_plugins(mode: PluginMode) -> Option<HashSet<String>> {
+    let content = fs::read_to_string(known_plugins_path(mode).ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_known_plugins(mode: PluginMode, plugin_ids: &HashSet<String>) -> Result<(), ConfigError> {
+    let path = known_plugins_path(mode)?;
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    fs::write(path, serde_json::to_string(plugin_ids)?)?;
+    Ok(())
+}
+
+// 控制接口的访问令牌，每次以 --serve 启动时重新生成，外部脚本从该文件读取
+pub fn save_server_token(token: &str) -> Result<PathBuf, ConfigError> {
+    let path = data_dir()?.join("server_token.txt");
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    fs::write(&path, token)?;
+    Ok(path)
+}
+
+// 以当前插件列表为基准重新计算新插件
+pub fn reset_known_plugins() {
+    for mode in [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless] {
+        if let Ok(path) = known_plugins_path(mode) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// 未完成的批量安装，每处理完一个插件更新一次，全部完成后删除。
+// PE 环境中途崩溃或重启后可据此继续安装
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingBatch {
+    pub drive: String,
+    pub plugin_ids: Vec<String>,
+    #[serde(default)]
+    pub force_reinstall: bool,
+}
+
+fn pending_batch_path(mode: PluginMode) -> Result<PathBuf, ConfigError> {
+    Ok(data_dir()?.join(format!("pending_batch_{}.json", mode.get_server_name())))
+}
+
+pub fn load_pending_batch(mode: PluginMode) -> Option<PendingBatch> {
+    let content = fs::read_to_string(pending_batch_path(mode).ok()?).ok()?;
+    serde_json::from_str::<PendingBatch>(&content)
+        .ok()
+        .filter(|batch| !batch.plugin_ids.is_empty())
+}
+
+pub fn save_pending_batch(mode: PluginMode, batch: &PendingBatch) -> Result<(), ConfigError> {
+    let path = pending_batch_path(mode)?;
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    fs::write(path, serde_json::to_string(batch)?)?;
+    Ok(())
+}
+
+pub fn clear_pending_batch(mode: PluginMode) {
+    if let Ok(path) = pending_batch_path(mode) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// 插件列表缓存的有效期，超过后切换插件源时重新获取，无法联网时仍可使用
+pub const PLUGIN_LIST_CACHE_FRESH_SECS: i64 = 30 * 60;
+
+// 某个插件源在某个频道下的插件列表缓存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginListCache {
+    // 获取时间，Unix 时间戳（秒）
+    pub fetched_at: i64,
+    pub channel: ReleaseChannel,
+    pub categories: Vec<PluginCategory>,
+}
+
+impl PluginListCache {
+    pub fn is_fresh(&self) -> bool {
+        chrono::Local::now().timestamp() - self.fetched_at < PLUGIN_LIST_CACHE_FRESH_SECS
+    }
+    
+    pub fn fetched_label(&self) -> String {
+        chrono::DateTime::from_timestamp(self.fetched_at, 0)
+            .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "未知时间".to_string())
+    }
+}
+
+fn plugin_list_cache_path(mode: PluginMode) -> Result<PathBuf, ConfigError> {
+    Ok(data_dir()?.join(format!("plugin_list_{}.json", mode.get_server_name())))
+}
+
+// 只返回指定频道的缓存，切换频道后不会显示另一个频道的插件
+pub fn load_plugin_list_cache(mode: PluginMode, channel: ReleaseChannel) -> Option<PluginListCache> {
+    let content = fs::read_to_string(plugin_list_cache_path(mode).ok()?).ok()?;
+    let mut cache = serde_json::from_str::<PluginListCache>(&content)
+        .ok()
+        .filter(|cache| cache.channel == channel)?;
+    
+    // 插件所属分类不写入文件，读取后按分类补上
+    for category in &mut cache.categories {
+        for plugin in &mut category.list {
+            plugin.category = category.class.clone();
+        }
+    }
+    
+    Some(cache)
+}
+
+pub fn save_plugin_list_cache(mode: PluginMode, channel: ReleaseChannel, categories: &[PluginCategory]) -> Result<(), ConfigError> {
+    let path = plugin_list_cache_path(mode)?;
+    
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    let cache = PluginListCache {
+        fetched_at: chrono::Local::now().timestamp(),
+        channel,
+        categories: categories.to_vec(),
+    };
+    fs::write(path, serde_json::to_string(&cache)?)?;
+    Ok(())
 }
\ No newline at end of file
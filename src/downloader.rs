@@ -1,66 +1,286 @@
 use std::sync::Arc;
-use parking_lot::RwLock;
-use std::path::PathBuf;
-use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::{Mutex, RwLock};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 use futures::StreamExt;
-use std::fs::File;
-use std::io::Write;
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Error)]
+pub enum DownloadError {
+    #[error("网络请求失败: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("写入文件失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("无法获取文件大小")]
+    UnknownSize,
+    #[error("离线模式下无法下载，请在设置中关闭离线模式")]
+    Offline,
+    #[error("代理设置无效，已停止下载：{0}")]
+    Proxy(String),
+    // 服务器声明支持 Range，但分段请求没有返回 206，改为单线程下载
+    #[error("服务器未按分段返回数据")]
+    RangeIgnored,
+    #[error("分段下载不完整：应为 {expected} 字节，实际 {actual} 字节")]
+    SegmentIncomplete { expected: u64, actual: u64 },
+    #[error("下载已取消")]
+    Cancelled,
+    #[error("连接超时：{0} 秒内没有收到数据")]
+    Timeout(u64),
+    #[error("文件校验失败，下载的文件可能已损坏：SHA-256 应为 {expected}，实际为 {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl DownloadError {
+    // 连接失败、超时、传输中断、服务器繁忙等可能是暂时的，重试有机会成功；
+    // 链接失效（4xx）、地址无效、磁盘写入失败、校验不符等重试也没有用
+    fn is_retryable(&self) -> bool {
+        match self {
+            DownloadError::Network(e) => match e.status() {
+                Some(status) => status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+                None => e.is_connect() || e.is_timeout() || e.is_body(),
+            },
+            DownloadError::SegmentIncomplete { .. } | DownloadError::Timeout(_) => true,
+            _ => false,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DownloadProgress {
     pub current: u64,
     pub total: u64,
     pub speed: f64, // MB/s
+    // 跟随重定向后实际下载的地址，便于排查失效的 CDN 链接
+    pub resolved_url: Option<String>,
+    // 最终响应是否支持 Range 请求，重定向前后可能不同
+    pub supports_ranges: bool,
+    // 服务器通过 Content-Disposition 提供的文件名
+    pub server_filename: Option<String>,
 }
 
 pub struct Downloader {
     progress: Arc<RwLock<DownloadProgress>>,
-    _threads: u32,
+    threads: u32,
+    // 出错后的最多重试次数，0 表示不重试
+    retries: u32,
+    cancelled: Arc<AtomicBool>,
 }
 
+// 每个分段至少包含的数据量，避免小文件被拆成过多分段
+const MIN_SEGMENT_SIZE: u64 = 4 * 1024 * 1024;
+
+// 下载速度按最近这段时间内的数据量计算，网速变化后显示的速度能较快跟上
+const SPEED_WINDOW: Duration = Duration::from_secs(3);
+// 计算速度时的最小时长。刚开始下载时经过的时间极短，直接相除会得到离谱的速度
+const MIN_SPEED_ELAPSED: f64 = 0.5;
+
 impl Downloader {
-    pub fn new(threads: u32) -> Self {
+    pub fn new(threads: u32, retries: u32) -> Self {
         Self {
             progress: Arc::new(RwLock::new(DownloadProgress {
                 current: 0,
                 total: 0,
                 speed: 0.0,
+                resolved_url: None,
+                supports_ranges: false,
+                server_filename: None,
             })),
-            _threads: threads,
+            threads,
+            retries,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    
+    // 下载到 path，完成并通过校验后才把 .part 重命名为 path
+    pub async fn download(&self, url: &str, path: PathBuf, expected_sha256: Option<&str>) -> Result<(), DownloadError> {
+        let part_path = self.download_part(url, &path, expected_sha256).await?;
+        std::fs::rename(&part_path, &path)?;
+        Ok(())
+    }
+    
+    // 可续传的下载：数据先写入 <path>.part，进度记录在 <path>.part.json 中。
+    // 重试或中断后再次下载同一地址时从记录的位置继续。提供 expected_sha256 时在下载完成后校验，
+    // 不符则删除文件并返回错误。返回下载完成的 .part 路径，由调用方决定何时替换 path
+    pub async fn download_part(&self, url: &str, path: &Path, expected_sha256: Option<&str>) -> Result<PathBuf, DownloadError> {
+        let part_path = path_with_suffix(path, ".part");
+        let state_path = path_with_suffix(path, ".part.json");
+        let cleanup = CancelCleanup::new(&self.cancelled, vec![part_path.clone(), state_path.clone()]);
+        
+        // 重试时已下载的部分仍在 .part 中，从中断处继续
+        self.retry(url, || self.fetch_resumable(url, &part_path, &state_path)).await?;
+        let _ = std::fs::remove_file(&state_path);
+        
+        if let Some(expected) = expected_sha256 {
+            verify_sha256(&part_path, expected)?;
+        }
+        cleanup.disarm();
+        Ok(part_path)
+    }
+    
+    // 出错时间隔 1、2、4 秒……重试，最多重试 retries 次，全部失败时返回最后一次的错误
+    async fn retry<F, Fut>(&self, url: &str, mut attempt: F) -> Result<(), DownloadError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), DownloadError>>,
+    {
+        let mut retried = 0;
+        loop {
+            match attempt().await {
+                Err(e) if retried < self.retries && e.is_retryable() => {
+                    let delay = Duration::from_secs(1 << retried.min(5));
+                    retried += 1;
+                    crate::app_log::log(format!(
+                        "下载 {} 出错: {}，{} 秒后第 {} 次重试",
+                        url, e, delay.as_secs(), retried
+                    ));
+                    tokio::time::sleep(delay).await;
+                    self.check_cancelled()?;
+                }
+                result => return result,
+            }
+        }
+    }
+    
+    // 下载到 part_path，state_path 中的进度记录有效时只下载剩余部分
+    async fn fetch_resumable(&self, url: &str, part_path: &Path, state_path: &Path) -> Result<(), DownloadError> {
+        let (client, response) = self.start_request(url).await?;
+        let total_size = self.progress.read().total;
+        
+        // 不支持 Range 时无法续传，只能从头下载。此时响应就是完整的文件
+        if !self.progress.read().supports_ranges {
+            let _ = std::fs::remove_file(state_path);
+            return self.download_stream(response, part_path).await;
+        }
+        
+        // 探测响应只有一个字节，各分段重新请求
+        let resolved_url = response.url().to_string();
+        drop(response);
+        
+        // 地址或文件大小变化说明服务器上的文件已更新，已下载的部分不能再用
+        let part_size = std::fs::metadata(part_path).map(|metadata| metadata.len()).ok();
+        let saved = ResumeState::load(state_path)
+            .filter(|state| state.url == url && state.total == total_size && part_size == Some(total_size));
+        
+        let (file, state) = match saved {
+            Some(state) => (OpenOptions::new().write(true).open(part_path)?, state),
+            None => {
+                let file = File::create(part_path)?;
+                file.set_len(total_size)?;
+                let segments = split_ranges(total_size, self.segment_count(total_size))
+                    .into_iter()
+                    .map(|range| SegmentState { next: *range.start(), end: *range.end() })
+                    .collect();
+                (file, ResumeState { url: url.to_string(), total: total_size, segments })
+            }
+        };
+        
+        let already = state.downloaded();
+        let ranges = state.remaining();
+        let tracker = ResumeTracker {
+            path: state_path.to_path_buf(),
+            state: Mutex::new(state),
+            last_saved: Mutex::new(Instant::now()),
+        };
+        tracker.save();
+        
+        let result = self.download_segments(&client, &resolved_url, &file, ranges, already, Some(&tracker)).await;
+        drop(file);
+        
+        match result {
+            // 服务器忽略了 Range，清空已下载的部分重新开始
+            Err(DownloadError::RangeIgnored) => {
+                let _ = std::fs::remove_file(state_path);
+                self.restart_as_stream(&client, url, &resolved_url, part_path).await
+            }
+            Err(e) => {
+                tracker.save();
+                Err(e)
+            }
+            Ok(()) => Ok(()),
         }
     }
     
-    pub async fn download(&self, url: &str, path: PathBuf) -> Result<()> {
-        let client = reqwest::Client::new();
-        let response = client.get(url).send().await?;
+    // 只请求第一个字节并记录文件大小等信息。服务器支持 Range 时返回 206，文件大小取自 Content-Range，
+    // 之后按分段下载；不支持时返回完整内容，可以直接读取，不会多下载一次
+    async fn start_request(&self, url: &str) -> Result<(reqwest::Client, reqwest::Response), DownloadError> {
+        if crate::network::is_offline_mode() {
+            return Err(DownloadError::Offline);
+        }
+        if let Some(e) = crate::network::proxy_problem() {
+            return Err(DownloadError::Proxy(e));
+        }
         
-        let total_size = response
-            .content_length()
-            .ok_or_else(|| anyhow::anyhow!("无法获取文件大小"))?;
+        let client = crate::network::client_builder()
+            .connect_timeout(crate::network::download_timeout())
+            .build()?;
+        let probe = client.get(url).header(reqwest::header::RANGE, "bytes=0-0");
+        let probe = within_timeout(crate::network::with_source_auth(probe, url).send()).await??;
+        
+        // 空文件无法满足范围请求，改为普通请求
+        let response = if probe.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            within_timeout(crate::network::with_source_auth(client.get(url), url).send()).await??
+        } else {
+            probe
+        }
+        .error_for_status()?;
+        
+        // 链接可能重定向到 CDN，大小和 Range 支持都以最终响应为准
+        let ranged = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let total_size = if ranged {
+            response
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_range_total)
+        } else {
+            response.content_length()
+        }
+        .ok_or(DownloadError::UnknownSize)?;
         
         {
             let mut progress = self.progress.write();
             progress.total = total_size;
             progress.current = 0;
+            progress.resolved_url = Some(response.url().to_string());
+            progress.supports_ranges = ranged;
+            progress.server_filename = content_disposition_filename(&response);
         }
         
-        let mut file = File::create(&path)?;
+        Ok((client, response))
+    }
+    
+    // 分段请求没有按 Range 返回时，重新请求并单线程下载整个文件
+    async fn restart_as_stream(&self, client: &reqwest::Client, url: &str, resolved_url: &str, path: &Path) -> Result<(), DownloadError> {
+        crate::app_log::log(format!("{} 未按分段返回数据，改为单线程下载", resolved_url));
+        let response = within_timeout(crate::network::with_source_auth(client.get(url), url).send())
+            .await??
+            .error_for_status()?;
+        self.download_stream(response, path).await
+    }
+    
+    // 单线程顺序下载整个响应
+    async fn download_stream(&self, response: reqwest::Response, path: &Path) -> Result<(), DownloadError> {
+        let mut file = File::create(path)?;
         let mut stream = response.bytes_stream();
         let mut downloaded = 0u64;
-        let start_time = std::time::Instant::now();
+        let mut speed_meter = SpeedMeter::new(Instant::now(), 0);
+        self.progress.write().current = 0;
         
-        while let Some(chunk_result) = stream.next().await {
+        while let Some(chunk_result) = within_timeout(stream.next()).await? {
+            self.check_cancelled()?;
             let chunk = chunk_result?;
             file.write_all(&chunk)?;
             
             downloaded += chunk.len() as u64;
-            
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed = if elapsed > 0.0 {
-                (downloaded as f64 / elapsed) / (1024.0 * 1024.0)
-            } else {
-                0.0
-            };
+            let speed = speed_meter.record(Instant::now(), downloaded);
             
             {
                 let mut progress = self.progress.write();
@@ -72,17 +292,424 @@ impl Downloader {
         Ok(())
     }
     
-    #[allow(dead_code)]
+    // 同时下载各个字节范围，写入预先分配好大小的同一个文件中的对应位置。
+    // ranges 中的序号对应续传记录中的分段，already 为之前已下载的字节数
+    async fn download_segments(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        file: &File,
+        ranges: Vec<(usize, RangeInclusive<u64>)>,
+        already: u64,
+        tracker: Option<&ResumeTracker>,
+    ) -> Result<(), DownloadError> {
+        let shared = SegmentProgress {
+            downloaded: AtomicU64::new(already),
+            speed_meter: Mutex::new(SpeedMeter::new(Instant::now(), already)),
+            tracker,
+        };
+        self.progress.write().current = already;
+        
+        let tasks = ranges
+            .into_iter()
+            .map(|(index, range)| self.download_segment(client, url, file, index, range, &shared));
+        
+        futures::future::try_join_all(tasks).await?;
+        Ok(())
+    }
+    
+    // 下载 range 范围的数据并写入文件的对应位置
+    async fn download_segment(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        file: &File,
+        index: usize,
+        range: RangeInclusive<u64>,
+        shared: &SegmentProgress<'_>,
+    ) -> Result<(), DownloadError> {
+        let (start, end) = range.into_inner();
+        let request = crate::network::with_source_auth(client.get(url), url)
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+        let response = within_timeout(request.send())
+            .await??
+            .error_for_status()?;
+        
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(DownloadError::RangeIgnored);
+        }
+        
+        let expected = end - start + 1;
+        let mut offset = start;
+        let mut stream = response.bytes_stream();
+        
+        while let Some(chunk_result) = within_timeout(stream.next()).await? {
+            self.check_cancelled()?;
+            let chunk = chunk_result?;
+            // 服务器多返回的数据不写入，避免覆盖下一段
+            let remaining = (end + 1).saturating_sub(offset) as usize;
+            let chunk = &chunk[..chunk.len().min(remaining)];
+            write_at(file, chunk, offset)?;
+            offset += chunk.len() as u64;
+            
+            // 数据写入后才更新续传记录，记录的位置不会超过实际写入的位置
+            if let Some(tracker) = shared.tracker {
+                tracker.record(index, offset);
+            }
+            
+            let total = shared.downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+            let speed = shared.speed_meter.lock().record(Instant::now(), total);
+            {
+                let mut progress = self.progress.write();
+                progress.current = total;
+                progress.speed = speed;
+            }
+        }
+        
+        let actual = offset - start;
+        if actual != expected {
+            return Err(DownloadError::SegmentIncomplete { expected, actual });
+        }
+        
+        Ok(())
+    }
+    
+    // 实际使用的分段数：不超过设置的线程数，并按文件大小限制为约每 4 MB 一段
+    fn segment_count(&self, total_size: u64) -> u32 {
+        let by_size = (total_size / MIN_SEGMENT_SIZE).clamp(1, u32::MAX as u64) as u32;
+        self.threads.clamp(1, by_size)
+    }
+    
     pub fn get_progress(&self) -> DownloadProgress {
         self.progress.read().clone()
     }
     
-    #[allow(dead_code)]
-    pub async fn download_plugin(&self, url: &str, drive_letter: &str, filename: &str) -> Result<()> {
-        let download_path = format!("{}\\ce-apps", drive_letter);
-        std::fs::create_dir_all(&download_path)?;
+    // 共享的进度句柄，任务列表可以在下载过程中持续读取
+    pub fn progress_handle(&self) -> Arc<RwLock<DownloadProgress>> {
+        self.progress.clone()
+    }
+    
+    // 取消标记，设置后下载停止并删除未完成的文件
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+    
+    fn check_cancelled(&self) -> Result<(), DownloadError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(DownloadError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+// 用户取消时删除未完成的文件。任务被中止时下载的 future 直接被丢弃，只能在 Drop 中清理；
+// 程序退出等其他原因中断时没有设置取消标记，文件保留，可续传的下载下次从中断处继续
+struct CancelCleanup<'a> {
+    cancelled: &'a AtomicBool,
+    paths: Vec<PathBuf>,
+}
+
+impl<'a> CancelCleanup<'a> {
+    fn new(cancelled: &'a AtomicBool, paths: Vec<PathBuf>) -> Self {
+        Self { cancelled, paths }
+    }
+    
+    // 下载已完成，之后再取消也不删除文件
+    fn disarm(mut self) {
+        self.paths.clear();
+    }
+}
+
+impl Drop for CancelCleanup<'_> {
+    fn drop(&mut self) {
+        if self.cancelled.load(Ordering::Relaxed) {
+            for path in &self.paths {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+// 各分段共用的进度，合计后写入 DownloadProgress
+struct SegmentProgress<'a> {
+    downloaded: AtomicU64,
+    speed_meter: Mutex<SpeedMeter>,
+    tracker: Option<&'a ResumeTracker>,
+}
+
+// 续传记录中的一个分段，next 为下一个要下载的字节位置，next > end 表示该段已完成
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SegmentState {
+    next: u64,
+    end: u64,
+}
+
+// 可续传下载的进度记录。url 为原始下载地址，重定向后的地址可能带有会过期的签名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResumeState {
+    url: String,
+    total: u64,
+    segments: Vec<SegmentState>,
+}
+
+impl ResumeState {
+    fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+    
+    // 各分段已下载的字节数之和，第一段从 0 开始，之后每段从上一段结束处开始
+    fn downloaded(&self) -> u64 {
+        let mut segment_start = 0;
+        let mut downloaded = 0;
+        for segment in &self.segments {
+            downloaded += segment.next.saturating_sub(segment_start);
+            segment_start = segment.end + 1;
+        }
+        downloaded
+    }
+    
+    fn remaining(&self) -> Vec<(usize, RangeInclusive<u64>)> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.next <= segment.end)
+            .map(|(index, segment)| (index, segment.next..=segment.end))
+            .collect()
+    }
+}
+
+// 下载过程中更新续传记录，每隔一段时间写入文件
+struct ResumeTracker {
+    path: PathBuf,
+    state: Mutex<ResumeState>,
+    last_saved: Mutex<Instant>,
+}
+
+impl ResumeTracker {
+    const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+    
+    fn record(&self, index: usize, next: u64) {
+        if let Some(segment) = self.state.lock().segments.get_mut(index) {
+            segment.next = next;
+        }
+        
+        let mut last_saved = self.last_saved.lock();
+        if last_saved.elapsed() >= Self::SAVE_INTERVAL {
+            *last_saved = Instant::now();
+            drop(last_saved);
+            self.save();
+        }
+    }
+    
+    fn save(&self) {
+        if let Ok(content) = serde_json::to_string(&*self.state.lock()) {
+            let _ = std::fs::write(&self.path, content);
+        }
+    }
+}
+
+// 按时间窗口计算的下载速度（MB/s），保存窗口内每次收到数据时的已下载量
+struct SpeedMeter {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SpeedMeter {
+    fn new(start: Instant, downloaded: u64) -> Self {
+        Self {
+            samples: VecDeque::from([(start, downloaded)]),
+        }
+    }
+    
+    fn record(&mut self, now: Instant, downloaded: u64) -> f64 {
+        self.samples.push_back((now, downloaded));
+        
+        // 保留一个早于窗口起点的样本，窗口始终覆盖完整的 SPEED_WINDOW
+        while self.samples.len() > 2 && now.saturating_duration_since(self.samples[1].0) >= SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+        
+        let (start, start_downloaded) = self.samples[0];
+        let elapsed = now.saturating_duration_since(start).as_secs_f64().max(MIN_SPEED_ELAPSED);
+        downloaded.saturating_sub(start_downloaded) as f64 / elapsed / (1024.0 * 1024.0)
+    }
+}
+
+// 解析 Content-Disposition 中的文件名，优先使用 RFC 5987 编码的 filename*
+fn content_disposition_filename(response: &reqwest::Response) -> Option<String> {
+    let header = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)?
+        .to_str()
+        .ok()?;
+    
+    let params: Vec<(String, &str)> = header
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+    
+    let encoded = params.iter()
+        .find(|(name, _)| name == "filename*")
+        .and_then(|(_, value)| {
+            // 格式为 charset'language'编码后的文件名
+            let encoded = value.splitn(3, '\'').nth(2)?;
+            percent_encoding::percent_decode_str(encoded)
+                .decode_utf8()
+                .ok()
+                .map(|name| name.to_string())
+        });
+    
+    let filename = encoded.or_else(|| {
+        params.iter()
+            .find(|(name, _)| name == "filename")
+            .map(|(_, value)| value.trim_matches('"').to_string())
+    })?;
+    
+    // 去掉可能携带的路径，只保留文件名部分
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or_default().trim().to_string();
+    if filename.is_empty() {
+        None
+    } else {
+        Some(filename)
+    }
+}
+
+// 在文件的指定位置写入全部数据，不改变其他分段共用的文件位置
+#[cfg(target_os = "windows")]
+fn write_at(file: &File, mut data: &[u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    
+    while !data.is_empty() {
+        let written = file.seek_write(data, offset)?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        data = &data[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn write_at(file: &File, data: &[u8], offset: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    
+    file.write_all_at(data, offset)
+}
+
+// 把 0..total 平均分为 segments 段，返回每段的字节范围（含两端）
+fn split_ranges(total: u64, segments: u32) -> Vec<RangeInclusive<u64>> {
+    let segment_size = total.div_ceil(segments.max(1) as u64).max(1);
+    (0..total)
+        .step_by(segment_size as usize)
+        .map(|start| start..=(start + segment_size).min(total) - 1)
+        .collect()
+}
+
+// 连接无响应或长时间收不到数据时返回 Timeout，不会一直等待下去
+async fn within_timeout<T>(future: impl Future<Output = T>) -> Result<T, DownloadError> {
+    let timeout = crate::network::download_timeout();
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| DownloadError::Timeout(timeout.as_secs()))
+}
+
+// 计算文件的 SHA-256 并与 expected（十六进制，不区分大小写）比较，不符时删除文件。
+// 分段和续传下载的数据不是按顺序到达的，因此在下载完成后读取文件计算
+fn verify_sha256(path: &Path, expected: &str) -> Result<(), DownloadError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    drop(file);
+    
+    let actual: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    let expected = expected.trim().to_lowercase();
+    if actual != expected {
+        let _ = std::fs::remove_file(path);
+        return Err(DownloadError::ChecksumMismatch { expected, actual });
+    }
+    
+    Ok(())
+}
+
+// 在文件名后追加后缀，如 a.ce 变为 a.ce.part
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+// 从 Content-Range（如 bytes 0-0/1234）中取出文件总大小，大小未知（*）时返回 None
+fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const MB: u64 = 1024 * 1024;
+    
+    #[test]
+    fn rapid_chunks_do_not_spike_speed() {
+        let start = Instant::now();
+        let mut meter = SpeedMeter::new(start, 0);
+        
+        // 刚开始时连续收到的数据块按最小时长计算
+        let mut speed = 0.0;
+        for i in 1..=10 {
+            speed = meter.record(start + Duration::from_micros(i * 100), i * MB / 10);
+        }
+        assert!((speed - 1.0 / MIN_SPEED_ELAPSED).abs() < 1e-9, "{}", speed);
+    }
+    
+    #[test]
+    fn speed_follows_recent_window() {
+        let start = Instant::now();
+        let mut meter = SpeedMeter::new(start, 0);
+        
+        let mut downloaded = 0;
+        for second in 1..=10 {
+            downloaded += MB;
+            meter.record(start + Duration::from_secs(second), downloaded);
+        }
         
-        let file_path = PathBuf::from(download_path).join(filename);
-        self.download(url, file_path).await
+        let mut speed = 0.0;
+        for second in 11..=14 {
+            downloaded += 4 * MB;
+            speed = meter.record(start + Duration::from_secs(second), downloaded);
+        }
+        assert!((speed - 4.0).abs() < 1e-9, "{}", speed);
+        // 窗口外的旧样本已丢弃
+        assert!(meter.samples.len() <= 5);
+    }
+    
+    #[test]
+    fn last_range_takes_the_remainder() {
+        assert_eq!(split_ranges(10, 3), vec![0..=3, 4..=7, 8..=9]);
+        assert_eq!(split_ranges(12, 3), vec![0..=3, 4..=7, 8..=11]);
+    }
+    
+    #[test]
+    fn tiny_files_get_one_range_per_byte_at_most() {
+        assert!(split_ranges(0, 4).is_empty());
+        assert_eq!(split_ranges(1, 4), vec![0..=0]);
+        assert_eq!(split_ranges(3, 8), vec![0..=0, 1..=1, 2..=2]);
+        assert_eq!(split_ranges(5, 0), vec![0..=4]);
+    }
+    
+    #[test]
+    fn content_range_gives_total_size() {
+        assert_eq!(content_range_total("bytes 0-0/1234"), Some(1234));
+        assert_eq!(content_range_total("bytes 0-0/*"), None);
+        assert_eq!(content_range_total("bytes 0-0"), None);
     }
 }
\ No newline at end of file
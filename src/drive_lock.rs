@@ -0,0 +1,193 @@
+use crate::mode::PluginMode;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// 写入启动盘前在盘符根目录放置的锁文件，同一个启动盘同时只允许一个进程写入。
+// 多个插件源共用同一个 Cloud-PE 启动盘，因此锁按启动盘而不是按插件目录
+const LOCK_FILE_NAME: &str = "CloudPE-PluginMarket.lock";
+
+// create_new 创建锁文件与写入持有者信息是两步操作，期间其他进程会读到空文件。
+// 这段时间内的空文件或无法解析的锁文件视为已被持有，超过后才当作残留删除
+const LOCK_WRITE_GRACE: Duration = Duration::from_secs(2);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub mode: String,
+}
+
+// 本进程持有锁的启动盘及当前选中该启动盘的窗口数，同一进程内的多个窗口共用。
+// 只因写入操作而获取的锁窗口数为 0，程序退出时释放
+static HELD_DRIVES: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn lock_path(drive_letter: &str) -> PathBuf {
+    crate::utils::drive_path(drive_letter, LOCK_FILE_NAME)
+}
+
+fn drive_key(drive_letter: &str) -> String {
+    drive_letter.trim_end_matches('\\').to_ascii_uppercase()
+}
+
+fn read_holder(drive_letter: &str) -> Option<LockHolder> {
+    let content = fs::read_to_string(lock_path(drive_letter)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// 写入启动盘前调用：已持有锁或成功获取锁时返回 Ok，
+// 启动盘正被其他仍在运行的进程使用时返回持有者信息
+pub fn acquire(drive_letter: &str, mode: PluginMode) -> Result<(), LockHolder> {
+    let mut held = HELD_DRIVES.lock();
+    acquire_locked(&mut held, drive_letter, mode)
+}
+
+fn acquire_locked(held: &mut HashMap<String, usize>, drive_letter: &str, mode: PluginMode) -> Result<(), LockHolder> {
+    let key = drive_key(drive_letter);
+    if held.contains_key(&key) {
+        return Ok(());
+    }
+    
+    let holder = LockHolder {
+        pid: std::process::id(),
+        mode: mode.get_server_name().to_string(),
+    };
+    let path = lock_path(drive_letter);
+    
+    // 以 create_new 创建锁文件，两个进程同时获取时只有一个能成功。
+    // 已有的锁文件属于已退出的进程时删除后再试一次
+    for _ in 0..2 {
+        match create_lock_file(&path, &holder) {
+            Ok(()) => break,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => match wait_for_holder(drive_letter) {
+                Some(other) if other.pid != holder.pid && is_process_running(other.pid) => return Err(other),
+                _ => {
+                    let _ = fs::remove_file(&path);
+                }
+            },
+            // 无法写入锁文件（例如启动盘只读）时不阻止操作，写入本身会给出具体错误
+            Err(_) => break,
+        }
+    }
+    
+    held.insert(key, 0);
+    Ok(())
+}
+
+// 读取已有锁文件的持有者。刚创建、尚未写入内容的锁文件等待其写完，
+// 超过 LOCK_WRITE_GRACE 仍无法解析时返回 None，由调用方当作残留删除
+fn wait_for_holder(drive_letter: &str) -> Option<LockHolder> {
+    loop {
+        if let Some(holder) = read_holder(drive_letter) {
+            return Some(holder);
+        }
+        
+        let age = fs::metadata(lock_path(drive_letter))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        match age {
+            Some(age) if age < LOCK_WRITE_GRACE => std::thread::sleep(LOCK_POLL_INTERVAL),
+            _ => return None,
+        }
+    }
+}
+
+fn create_lock_file(path: &Path, holder: &LockHolder) -> std::io::Result<()> {
+    let content = serde_json::to_string(holder)?;
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(content.as_bytes())
+}
+
+// 窗口选定启动盘时调用，获取锁并计入选中该启动盘的窗口
+pub fn select(drive_letter: &str, mode: PluginMode) -> Result<(), LockHolder> {
+    let mut held = HELD_DRIVES.lock();
+    acquire_locked(&mut held, drive_letter, mode)?;
+    if let Some(count) = held.get_mut(&drive_key(drive_letter)) {
+        *count += 1;
+    }
+    Ok(())
+}
+
+// 窗口改选其他启动盘时调用，没有窗口再选中该启动盘时释放锁
+pub fn deselect(drive_letter: &str) {
+    let key = drive_key(drive_letter);
+    let mut held = HELD_DRIVES.lock();
+    let Some(count) = held.get_mut(&key) else {
+        return;
+    };
+    
+    *count = count.saturating_sub(1);
+    if *count == 0 {
+        held.remove(&key);
+        remove_if_ours(drive_letter);
+    }
+}
+
+fn remove_if_ours(drive_letter: &str) {
+    let is_ours = read_holder(drive_letter).is_some_and(|holder| holder.pid == std::process::id());
+    if is_ours {
+        let _ = fs::remove_file(lock_path(drive_letter));
+    }
+}
+
+// 与 acquire 相同，失败时返回可直接显示给用户的错误信息
+pub fn acquire_for_write(drive_letter: &str, mode: PluginMode) -> Result<(), String> {
+    acquire(drive_letter, mode).map_err(|holder| conflict_message(drive_letter, &holder))
+}
+
+pub fn conflict_message(drive_letter: &str, holder: &LockHolder) -> String {
+    format!(
+        "启动盘 {} 正被另一个 {} 窗口（进程 {}）使用，为避免文件损坏已暂停写入，请关闭该窗口后重试",
+        drive_letter, holder.mode, holder.pid
+    )
+}
+
+// 删除持有进程已退出的锁文件
+pub fn remove_stale(drive_letter: &str) {
+    if let Some(holder) = read_holder(drive_letter) {
+        if !is_process_running(holder.pid) {
+            let _ = fs::remove_file(lock_path(drive_letter));
+        }
+    }
+}
+
+// 程序退出前释放本进程持有的所有锁
+pub fn release_all() {
+    let mut held = HELD_DRIVES.lock();
+    for (drive, _) in held.drain() {
+        remove_if_ours(&drive);
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn is_process_running(pid: u32) -> bool {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+    
+    // GetExitCodeProcess 对仍在运行的进程返回 STILL_ACTIVE
+    const STILL_ACTIVE: u32 = 259;
+    
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        
+        let mut exit_code = 0;
+        let running = GetExitCodeProcess(handle, &mut exit_code) != 0 && exit_code == STILL_ACTIVE;
+        CloseHandle(handle);
+        running
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_process_running(pid: u32) -> bool {
+    std::path::Path::new(&format!("/proc/{}", pid)).exists()
+}
@@ -0,0 +1,224 @@
+use crate::downloader::Downloader;
+use crate::mode::PluginMode;
+use crate::plugins::{format_file_size, parse_size_to_bytes, Plugin, PluginManager};
+use crate::utils::BootDriveManager;
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeployOptions {
+    // 先删除本地已启用的同一插件（更新、重新安装）
+    pub replace_existing: bool,
+    // 安全安装模式：先下载到本地临时目录并校验，再复制到启动盘
+    pub safe_install: bool,
+    // 拒绝安装标注的适用平台与当前插件源不符的插件
+    pub block_incompatible: bool,
+}
+
+fn staging_dir() -> PathBuf {
+    std::env::temp_dir().join("CloudPE").join("staging")
+}
+
+// 校验暂存文件完整：文件非空，且大小与服务器声明的一致
+fn verify_staged_file(path: &Path, expected_size: u64) -> Result<(), String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("无法读取暂存文件: {}", e))?
+        .len();
+    
+    if size == 0 {
+        return Err("下载的文件为空".to_string());
+    }
+    
+    if expected_size > 0 && size != expected_size {
+        return Err(format!("文件大小不符：应为 {} 字节，实际 {} 字节", expected_size, size));
+    }
+    
+    Ok(())
+}
+
+// 按插件市场标注的大小检查启动盘可用空间，避免下载到一半才因空间不足失败。
+// 替换旧版本时新文件写完才删除旧文件，旧文件占用的空间不计入可用空间；大小未知或无法查询可用空间时不阻止安装
+pub fn check_free_space(drive_letter: &str, plugin: &Plugin) -> Result<(), String> {
+    let required = parse_size_to_bytes(&plugin.size);
+    let available = BootDriveManager::get_free_space(drive_letter);
+    let (Some(required), Some(available)) = (required, available) else {
+        return Ok(());
+    };
+    
+    if required > available {
+        return Err(format!(
+            "启动盘 {} 空间不足：{} 需要 {}，可用 {}",
+            drive_letter,
+            plugin.name,
+            format_file_size(required as i64),
+            format_file_size(available as i64),
+        ));
+    }
+    
+    Ok(())
+}
+
+// 把插件下载安装到启动盘
+pub async fn deploy_plugin(
+    downloader: &Downloader,
+    plugin_manager: &Arc<RwLock<PluginManager>>,
+    mode: PluginMode,
+    drive_letter: &str,
+    plugin: &Plugin,
+    filename: &str,
+    options: DeployOptions,
+) -> Result<(), String> {
+    let result = deploy(downloader, plugin_manager, mode, drive_letter, plugin, filename, options).await;
+    crate::app_log::log(match &result {
+        Ok(()) => format!("已安装 {} {} 到 {}", plugin.name, plugin.version, drive_letter),
+        Err(e) => format!("安装 {} {} 到 {} 失败: {}", plugin.name, plugin.version, drive_letter, e),
+    });
+    result
+}
+
+async fn deploy(
+    downloader: &Downloader,
+    plugin_manager: &Arc<RwLock<PluginManager>>,
+    mode: PluginMode,
+    drive_letter: &str,
+    plugin: &Plugin,
+    filename: &str,
+    options: DeployOptions,
+) -> Result<(), String> {
+    if options.block_incompatible {
+        if let Some(message) = plugin.compatibility_error(mode) {
+            return Err(message);
+        }
+    }
+    
+    check_free_space(drive_letter, plugin)?;
+    
+    crate::drive_lock::acquire_for_write(drive_letter, mode)?;
+    
+    let plugin_dir = crate::utils::plugin_dir(drive_letter, mode);
+    
+    if let Err(e) = tokio::fs::create_dir_all(&plugin_dir).await {
+        return Err(format!("无法创建插件目录: {}", e));
+    }
+    
+    let full_filename = format!("{}.{}", filename, mode.get_enabled_extension());
+    let install_path = plugin_dir.join(&full_filename);
+    
+    if !options.safe_install {
+        // 同样先下载到 .part 文件，下载被取消或中断时不会留下看似完整的插件，再次安装时从中断处继续。
+        // 下载完成后才删除旧版本，下载失败时启动盘上的插件保持不变
+        let part_path = downloader.download_part(&plugin.link, &install_path, plugin.sha256.as_deref())
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        if options.replace_existing {
+            if let Err(e) = remove_existing(plugin_manager, drive_letter, plugin) {
+                let _ = std::fs::remove_file(&part_path);
+                return Err(e);
+            }
+        }
+        
+        std::fs::rename(&part_path, &install_path)
+            .map_err(|e| format!("无法写入启动盘: {}", e))?;
+        finish_deploy(plugin_manager, drive_letter, plugin, &full_filename);
+        return Ok(());
+    }
+    
+    let staging_dir = staging_dir();
+    if let Err(e) = tokio::fs::create_dir_all(&staging_dir).await {
+        return Err(format!("无法创建临时目录: {}", e));
+    }
+    
+    let staged_path = staging_dir.join(&full_filename);
+    let result = stage_and_copy(downloader, plugin_manager, drive_letter, plugin, &staged_path, &install_path, options).await;
+    let _ = tokio::fs::remove_file(&staged_path).await;
+    result?;
+    
+    finish_deploy(plugin_manager, drive_letter, plugin, &full_filename);
+    Ok(())
+}
+
+// 记录稳定 ID 并重新读取本地插件
+fn finish_deploy(
+    plugin_manager: &Arc<RwLock<PluginManager>>,
+    drive_letter: &str,
+    plugin: &Plugin,
+    full_filename: &str,
+) {
+    let mut manager = plugin_manager.write();
+    if let Some(id) = &plugin.id {
+        let _ = manager.record_plugin_id(drive_letter, full_filename, id);
+    }
+    let _ = manager.load_local_plugins(drive_letter);
+}
+
+async fn stage_and_copy(
+    downloader: &Downloader,
+    plugin_manager: &Arc<RwLock<PluginManager>>,
+    drive_letter: &str,
+    plugin: &Plugin,
+    staged_path: &Path,
+    install_path: &Path,
+    options: DeployOptions,
+) -> Result<(), String> {
+    downloader.download(&plugin.link, staged_path.to_path_buf(), plugin.sha256.as_deref()).await.map_err(|e| e.to_string())?;
+    verify_staged_file(staged_path, downloader.get_progress().total)?;
+    
+    // 先写入临时文件名再重命名，避免复制中断时留下不完整的插件。
+    // 复制失败或任务在复制期间被中止时由 guard 删除临时文件
+    let partial_path = install_path.with_extension("part");
+    let guard = PartialFileGuard { path: Some(&partial_path) };
+    tokio::fs::copy(staged_path, &partial_path)
+        .await
+        .map_err(|e| format!("复制到启动盘失败: {}", e))?;
+    
+    // 新版本完整写入启动盘后才删除旧版本，失败时启动盘上的插件保持不变
+    if options.replace_existing {
+        remove_existing(plugin_manager, drive_letter, plugin)?;
+    }
+    
+    tokio::fs::rename(&partial_path, install_path)
+        .await
+        .map_err(|e| format!("复制到启动盘失败: {}", e))?;
+    guard.disarm();
+    Ok(())
+}
+
+// 离开作用域时删除未复制完的文件
+struct PartialFileGuard<'a> {
+    path: Option<&'a Path>,
+}
+
+impl PartialFileGuard<'_> {
+    // 已重命名为正式文件，不再删除
+    fn disarm(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for PartialFileGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(path) = self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn remove_existing(
+    plugin_manager: &Arc<RwLock<PluginManager>>,
+    drive_letter: &str,
+    plugin: &Plugin,
+) -> Result<(), String> {
+    let old_file = plugin_manager.read()
+        .get_enabled_plugin_by_id(&plugin.get_plugin_id())
+        .map(|local_plugin| local_plugin.file.clone());
+    
+    if let Some(old_file_name) = old_file {
+        plugin_manager.read()
+            .delete_plugin_file(drive_letter, &old_file_name)
+            .map_err(|e| format!("无法删除旧版本: {}", e))?;
+    }
+    
+    Ok(())
+}
\ No newline at end of file
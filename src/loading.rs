@@ -1,64 +1,59 @@
-use crate::app::CloudPEApp;
+use crate::app::{apply_configured_theme, AppInit, CloudPEApp, INIT_STAGE_SCANNING, MAX_CONCURRENT_DOWNLOADS};
+use crate::config::AppConfig;
 use crate::mode::PluginMode;
+use crate::network::CONNECTION_TEST_ATTEMPTS;
 use eframe::egui;
+use parking_lot::{Mutex, RwLock};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::time::Instant;
 use tokio::runtime::Runtime;
 
 pub struct LoadingScreen {
     is_loading: Arc<AtomicBool>,
     network_check_status: Arc<AtomicU8>, // 0=checking, 1=success, 2=failed
+    // 网络检测当前是第几次尝试
+    network_attempt: Arc<AtomicU32>,
     _network_error_message: Option<String>,
     _start_time: Instant,
-    _runtime: Arc<Runtime>,
+    runtime: Arc<Runtime>,
     app: Option<Box<CloudPEApp>>,
+    // 后台线程完成扫描启动盘等初始化后放入，在界面线程中创建应用
+    pending_init: Arc<Mutex<Option<AppInit>>>,
+    init_stage: Arc<AtomicU8>,
     _init_complete: bool,
     mode: PluginMode,
 }
 
 impl LoadingScreen {
-    pub fn new(cc: &eframe::CreationContext<'_>, runtime: Runtime, mode: PluginMode) -> Self {
+    pub fn new(ctx: &egui::Context, runtime: Runtime, mode: PluginMode) -> Self {
         let runtime = Arc::new(runtime);
         let is_loading = Arc::new(AtomicBool::new(true));
         let network_check_status = Arc::new(AtomicU8::new(0));
+        let network_attempt = Arc::new(AtomicU32::new(0));
         
         let is_loading_clone = is_loading.clone();
         let network_status_clone = network_check_status.clone();
+        let network_attempt_clone = network_attempt.clone();
         let runtime_clone = runtime.clone();
         let mode_clone = mode.clone();
         
-        // 网络检测
+        let mut loaded_config = AppConfig::load().unwrap_or_default();
+        if loaded_config.migrate_download_path(mode) {
+            let _ = loaded_config.save();
+        }
+        let config = Arc::new(RwLock::new(loaded_config));
+        
+        // 加载界面同样按用户设置的颜色模式显示，避免标题和加载动画与背景对比度不足
+        apply_configured_theme(ctx, &config.read());
+        
+        // 网络检测，离线模式下不需要连接服务器
+        let strategy = config.read().connect_test;
         runtime_clone.spawn(async move {
-            let mut retry_count = 0;
-            let max_retries = 3;
-            let mut success = false;
-            
-            let url = mode_clone.get_connect_test_url();
-            
-            while retry_count < max_retries {
-                let client = reqwest::Client::builder()
-                    .timeout(std::time::Duration::from_secs(5))
-                    .build()
-                    .unwrap_or_else(|_| reqwest::Client::new());
-                
-                match client.get(url).send().await {
-                    Ok(response) => {
-                        if let Ok(text) = response.text().await {
-                            if !text.is_empty() {
-                                success = true;
-                                break;
-                            }
-                        }
-                    }
-                    Err(_) => {}
-                }
-                
-                retry_count += 1;
-                if retry_count < max_retries {
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                }
-            }
+            let success = crate::network::is_offline_mode()
+                || crate::network::test_connection_with_progress(mode_clone, strategy, |attempt| {
+                    network_attempt_clone.store(attempt, Ordering::Relaxed);
+                }).await.is_ok();
             
             if success {
                 network_status_clone.store(1, Ordering::Relaxed);
@@ -71,16 +66,27 @@ impl LoadingScreen {
             is_loading_clone.store(false, Ordering::Relaxed);
         });
         
-        // 初始化应用（在后台）
-        let app = CloudPEApp::new(cc, runtime.clone(), mode);
+        // 扫描启动盘和读取本地插件需要访问文件系统，放到后台线程，避免窗口迟迟不出现
+        let pending_init = Arc::new(Mutex::new(None));
+        let init_stage = Arc::new(AtomicU8::new(INIT_STAGE_SCANNING));
+        
+        let pending_init_clone = pending_init.clone();
+        let init_stage_clone = init_stage.clone();
+        runtime.spawn_blocking(move || {
+            let init = AppInit::prepare(mode, config, &init_stage_clone);
+            *pending_init_clone.lock() = Some(init);
+        });
         
         Self {
             is_loading,
             network_check_status,
+            network_attempt,
             _network_error_message: None,
             _start_time: Instant::now(),
-            _runtime: runtime,
-            app: Some(Box::new(app)),
+            runtime,
+            app: None,
+            pending_init,
+            init_stage,
             _init_complete: false,
             mode,
         }
@@ -89,6 +95,14 @@ impl LoadingScreen {
 
 impl eframe::App for LoadingScreen {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.app.is_none() {
+            if let Some(init) = self.pending_init.lock().take() {
+                let download_slots = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_DOWNLOADS));
+                let app = CloudPEApp::from_init(ctx, self.runtime.clone(), download_slots, self.mode, init);
+                self.app = Some(Box::new(app));
+            }
+        }
+        
         let network_status = self.network_check_status.load(Ordering::Relaxed);
         
         if network_status == 2 {
@@ -99,7 +113,8 @@ impl eframe::App for LoadingScreen {
                     ui.add_space(available_height * 0.3);
                     
                     let title = self.mode.get_server_name();
-                    ui.heading(egui::RichText::new(title).size(48.0).strong());
+                    let title_color = ui.visuals().strong_text_color();
+                    ui.heading(egui::RichText::new(title).size(48.0).strong().color(title_color));
                     
                     ui.add_space(40.0);
                     
@@ -107,6 +122,22 @@ impl eframe::App for LoadingScreen {
                     ui.label(egui::RichText::new(error_msg)
                         .color(egui::Color32::from_rgb(255, 100, 100)));
                     
+                    let pinning_warning = if crate::network::pin_mismatch_detected() {
+                        Some("安全警告：服务器证书与固定证书不一致，已拒绝连接。当前网络可能存在中间人攻击，或服务器已更换证书，需要更新固定证书".to_string())
+                    } else {
+                        crate::network::pinning_problem().map(|reason| format!("证书固定无法生效，已拒绝所有连接：{}", reason))
+                    };
+                    
+                    let warning = crate::network::proxy_problem()
+                        .map(|problem| format!("代理设置无效，已拒绝所有网络请求：{}", problem))
+                        .or(pinning_warning);
+                    
+                    if let Some(warning) = warning {
+                        ui.add_space(10.0);
+                        ui.label(egui::RichText::new(warning)
+                            .color(egui::Color32::from_rgb(255, 160, 0)));
+                    }
+                    
                     ui.add_space(20.0);
                     
                     if ui.button("关闭").clicked() {
@@ -114,7 +145,7 @@ impl eframe::App for LoadingScreen {
                     }
                 });
             });
-        } else if self.is_loading.load(Ordering::Relaxed) {
+        } else if self.is_loading.load(Ordering::Relaxed) || self.app.is_none() {
             // 显示加载界面
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.vertical_centered(|ui| {
@@ -123,15 +154,34 @@ impl eframe::App for LoadingScreen {
                     
                     // 显示对应模式的文字
                     let title = self.mode.get_server_name();
-                    ui.heading(egui::RichText::new(title).size(48.0).strong());
+                    let title_color = ui.visuals().strong_text_color();
+                    ui.heading(egui::RichText::new(title).size(48.0).strong().color(title_color));
                     
                     ui.add_space(40.0);
                     
-                    // 加载动画圈
-                    ui.spinner();
+                    // 加载动画圈，使用与标题相同的颜色
+                    ui.add(egui::Spinner::new().color(title_color));
+                    
+                    // 网络检测失败重试时显示当前进度
+                    let attempt = self.network_attempt.load(Ordering::Relaxed);
+                    if attempt > 1 && self.network_check_status.load(Ordering::Relaxed) == 0 {
+                        ui.add_space(10.0);
+                        ui.add(egui::ProgressBar::new(attempt as f32 / CONNECTION_TEST_ATTEMPTS as f32)
+                            .desired_width(160.0)
+                            .desired_height(4.0)
+                            .animate(true));
+                        ui.label(egui::RichText::new(format!("重试中 {}/{}...", attempt, CONNECTION_TEST_ATTEMPTS)).weak());
+                    }
                     
                     ui.add_space(20.0);
-                    ui.label("正在加载...");
+                    let status = if self.app.is_some() {
+                        "正在加载..."
+                    } else if self.init_stage.load(Ordering::Relaxed) == INIT_STAGE_SCANNING {
+                        "正在扫描启动盘..."
+                    } else {
+                        "正在读取本地插件..."
+                    };
+                    ui.label(status);
                 });
             });
             
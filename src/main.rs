@@ -2,15 +2,24 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
+mod app_log;
+mod archive_contents;
 mod config;
 mod downloader;
+mod drive_lock;
+mod installer;
 mod network;
+mod plugin_naming;
+mod plugin_stream;
 mod plugins;
+mod server;
 mod ui;
 mod utils;
 mod loading;
 mod mode;
 mod source_selector;
+mod support_bundle;
+mod tray;
 
 use eframe::egui;
 use std::env;
@@ -51,17 +60,19 @@ fn request_admin() -> bool {
         }
         
         if !is_elevated {
-            let exe = env::current_exe().unwrap();
-            let args: Vec<String> = env::args().skip(1).collect();
-            
-            let result = Command::new("cmd")
-                .arg("/c")
-                .arg("start")
-                .raw_arg(format!("runas /user:Administrator \"{}\" {}", exe.display(), args.join(" ")))
-                .spawn();
+            // 受限的 PE 环境中可能无法获取程序路径，此时跳过提权，以当前权限继续运行
+            if let Ok(exe) = env::current_exe() {
+                let args: Vec<String> = env::args().skip(1).collect();
                 
-            if result.is_ok() {
-                std::process::exit(0);
+                let result = Command::new("cmd")
+                    .arg("/c")
+                    .arg("start")
+                    .raw_arg(format!("runas /user:Administrator \"{}\" {}", exe.display(), args.join(" ")))
+                    .spawn();
+                    
+                if result.is_ok() {
+                    std::process::exit(0);
+                }
             }
         }
         
@@ -69,17 +80,6 @@ fn request_admin() -> bool {
     }
 }
 
-// 检测是否在 PE 环境
-fn is_pe_environment() -> bool {
-    // PE 环境通常有这些特征
-    std::env::var("X:").is_ok() || 
-    std::env::var("WINPE").is_ok() ||
-    std::env::current_dir()
-        .ok()
-        .and_then(|p| p.to_str().map(|s| s.starts_with("X:")))
-        .unwrap_or(false)
-}
-
 fn show_error_message(title: &str, message: &str) {
     #[cfg(target_os = "windows")]
     {
@@ -110,7 +110,7 @@ fn show_error_message(title: &str, message: &str) {
 
 fn main() -> eframe::Result<()> {
     // 检测 PE 环境
-    let in_pe = is_pe_environment();
+    let in_pe = utils::is_pe_environment();
     
     // 在 PE 环境中跳过管理员权限检查
     #[cfg(target_os = "windows")]
@@ -122,16 +122,22 @@ fn main() -> eframe::Result<()> {
     
     // 解析命令行参数
     let args: Vec<String> = env::args().collect();
-    let mode = if args.len() > 1 {
-        match args[1].as_str() {
-            "--hpm" => PluginMode::HotPE,
-            "--edgeless" => PluginMode::Edgeless,
-            "--select" => PluginMode::Select,
-            _ => PluginMode::CloudPE,
-        }
-    } else {
-        PluginMode::CloudPE
-    };
+    let mode = args.iter().skip(1).find_map(|arg| match arg.as_str() {
+        "--hpm" => Some(PluginMode::HotPE),
+        "--edgeless" => Some(PluginMode::Edgeless),
+        "--select" => Some(PluginMode::Select),
+        _ => None,
+    }).unwrap_or(PluginMode::CloudPE);
+    
+    // --serve <端口>：不启动界面，只在本机提供控制接口
+    let serve_port = args.iter()
+        .position(|arg| arg == "--serve")
+        .map(|index| args.get(index + 1).and_then(|port| port.parse::<u16>().ok()));
+    
+    app_log::log(format!("启动 v{}，参数: {:?}，PE 环境: {}", env!("CARGO_PKG_VERSION"), &args[1..], in_pe));
+    
+    // 网络设置需要在任何请求发出前生效
+    config::AppConfig::load().unwrap_or_default().apply_network_settings();
     
     // 初始化运行时
     let rt = match tokio::runtime::Runtime::new() {
@@ -143,6 +149,24 @@ fn main() -> eframe::Result<()> {
         }
     };
     
+    if let Some(port) = serve_port {
+        let Some(port) = port else {
+            show_error_message("启动失败", "--serve 参数需要指定有效的端口号");
+            std::process::exit(1);
+        };
+        
+        if mode == PluginMode::Select {
+            show_error_message("启动失败", "--serve 不能与 --select 同时使用");
+            std::process::exit(1);
+        }
+        
+        if let Err(e) = rt.block_on(server::serve(port, mode)) {
+            show_error_message("控制服务启动失败", &e.to_string());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
     // 设置图标
     let icon_bytes = include_bytes!("../assets/icon.png");
     let icon = match eframe::icon_data::from_png_bytes(icon_bytes) {
@@ -185,13 +209,15 @@ fn main() -> eframe::Result<()> {
             setup_custom_fonts(&cc.egui_ctx);
             
             if mode == PluginMode::Select {
-                Ok(Box::new(source_selector::SourceSelector::new(cc)))
+                Ok(Box::new(source_selector::SourceSelector::new(cc)?))
             } else {
-                Ok(Box::new(loading::LoadingScreen::new(cc, rt, mode)))
+                Ok(Box::new(loading::LoadingScreen::new(&cc.egui_ctx, rt, mode)))
             }
         }),
     );
     
+    drive_lock::release_all();
+    
     match result {
         Ok(_) => Ok(()),
         Err(e) => {
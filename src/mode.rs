@@ -1,10 +1,17 @@
 use std::hash::Hash;
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+// 序列化名称用作配置文件中按插件源保存的设置的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PluginMode {
+    #[serde(rename = "cloudpe")]
     CloudPE,
+    #[serde(rename = "hotpe")]
     HotPE,
+    #[serde(rename = "edgeless")]
     Edgeless,
+    #[serde(rename = "select")]
     Select,
 }
 
@@ -18,12 +25,11 @@ impl PluginMode {
         }
     }
     
+    // 没有专用的连通性测试地址时使用插件列表接口
     pub fn get_connect_test_url(&self) -> &str {
         match self {
             PluginMode::CloudPE => "https://api.cloud-pe.cn/connecttest/",
-            PluginMode::HotPE => "https://api.hotpe.top/API/HotPE/GetHPMList/",
-            PluginMode::Edgeless => "https://api.cloud-pe.cn/EdgelessPlugins/",
-            _ => "",
+            _ => self.get_api_url(),
         }
     }
     
@@ -36,6 +42,34 @@ impl PluginMode {
         }
     }
     
+    // 插件配置备份文件相对启动盘根目录的路径
+    pub fn get_backup_file(&self) -> &str {
+        match self {
+            PluginMode::CloudPE => "cloud-pe\\plugins-backup.json",
+            PluginMode::HotPE => "HotPEModule\\plugins-backup.json",
+            PluginMode::Edgeless => "Edgeless\\plugins-backup.json",
+            _ => "",
+        }
+    }
+    
+    pub fn get_feedback_url(&self) -> &str {
+        match self {
+            PluginMode::CloudPE => "https://www.cloud-pe.cn/",
+            PluginMode::HotPE => "https://www.hotpe.top/",
+            PluginMode::Edgeless => "https://home.edgeless.top/",
+            _ => "",
+        }
+    }
+    
+    pub fn get_plugin_id_file(&self) -> &str {
+        match self {
+            PluginMode::CloudPE => "cloud-pe\\plugin-ids.json",
+            PluginMode::HotPE => "HotPEModule\\plugin-ids.json",
+            PluginMode::Edgeless => "Edgeless\\plugin-ids.json",
+            _ => "",
+        }
+    }
+    
     pub fn get_enabled_extension(&self) -> &str {
         match self {
             PluginMode::CloudPE => "ce",
@@ -85,4 +119,13 @@ impl PluginMode {
             _ => "",
         }
     }
+    
+    pub fn get_accent_color(&self) -> Color32 {
+        match self {
+            PluginMode::CloudPE => Color32::from_rgb(0, 120, 215),
+            PluginMode::HotPE => Color32::from_rgb(230, 120, 30),
+            PluginMode::Edgeless => Color32::from_rgb(40, 160, 90),
+            _ => Color32::from_rgb(0, 120, 215),
+        }
+    }
 }
\ No newline at end of file
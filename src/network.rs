@@ -1,6 +1,413 @@
+use crate::config::{ConnectTestStrategy, SourceAuth};
+use crate::mode::PluginMode;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+// 证书固定的状态。固定的是证书公钥（SubjectPublicKeyInfo）的 SHA-256，
+// 服务器续期证书但沿用同一密钥时无需更新
+enum CertificatePin {
+    Disabled,
+    Pinned([u8; 32]),
+    // 已启用但固定证书无法使用，此时拒绝所有 HTTPS 连接
+    Unusable(String),
+}
+
+static CERTIFICATE_PIN: Lazy<RwLock<CertificatePin>> = Lazy::new(|| RwLock::new(CertificatePin::Disabled));
+
+// 曾有连接因服务器证书与固定证书不一致而被拒绝
+static PIN_MISMATCH: AtomicBool = AtomicBool::new(false);
+
+pub fn set_pinned_certificate(enabled: bool, path: Option<&Path>) {
+    let pin = match (enabled, path) {
+        (false, _) => CertificatePin::Disabled,
+        (true, None) => CertificatePin::Unusable("已启用证书固定，但尚未选择固定证书".to_string()),
+        (true, Some(path)) => match load_certificate_pin(path) {
+            Ok(pin) => CertificatePin::Pinned(pin),
+            Err(e) => CertificatePin::Unusable(e),
+        },
+    };
+    
+    if let CertificatePin::Unusable(reason) = &pin {
+        crate::app_log::log(format!("证书固定无法生效，将拒绝所有 HTTPS 连接: {}", reason));
+    }
+    
+    *CERTIFICATE_PIN.write() = pin;
+    PIN_MISMATCH.store(false, Ordering::Relaxed);
+}
+
+// 已启用证书固定但固定证书无法使用时返回原因
+pub fn pinning_problem() -> Option<String> {
+    match &*CERTIFICATE_PIN.read() {
+        CertificatePin::Unusable(reason) => Some(reason.clone()),
+        _ => None,
+    }
+}
+
+pub fn pin_mismatch_detected() -> bool {
+    PIN_MISMATCH.load(Ordering::Relaxed)
+}
+
+// 所有网络请求经由的 HTTP/HTTPS 代理，为 None 时直接连接。
+// Err 表示设置的代理地址无法使用，此时拒绝所有网络请求，不会绕过代理直接连接
+static PROXY: Lazy<RwLock<Option<Result<reqwest::Proxy, String>>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn set_proxy_url(url: Option<String>) {
+    let proxy = url.map(|url| validate_proxy_url(&url));
+    
+    // 代理地址中可能带有用户名和密码，日志中只记录原因
+    if let Some(Err(e)) = &proxy {
+        crate::app_log::log(format!("代理设置无效，在修正前拒绝所有网络请求: {}", e));
+    }
+    
+    *PROXY.write() = proxy;
+}
+
+// 设置的代理地址无法使用时返回原因，发出请求前需检查
+pub fn proxy_problem() -> Option<String> {
+    match PROXY.read().as_ref() {
+        Some(Err(e)) => Some(e.clone()),
+        _ => None,
+    }
+}
+
+// 检查代理地址的格式，只接受带主机名的 http:// 或 https:// 地址
+pub fn validate_proxy_url(url: &str) -> Result<reqwest::Proxy, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("代理地址格式不正确: {}", e))?;
+    
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("代理地址需以 http:// 或 https:// 开头".to_string());
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err("代理地址缺少主机名".to_string());
+    }
+    
+    reqwest::Proxy::all(url).map_err(|e| format!("代理地址无法使用: {}", e))
+}
+
+// 离线模式下所有网络请求在发出前即被拒绝
+static OFFLINE_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_offline_mode(offline: bool) {
+    OFFLINE_MODE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline_mode() -> bool {
+    OFFLINE_MODE.load(Ordering::Relaxed)
+}
+
+// 下载时连接和等待数据的超时时间（秒），超过后视为连接已中断
+static DOWNLOAD_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(30);
+
+pub fn set_download_timeout(secs: u64) {
+    DOWNLOAD_TIMEOUT_SECS.store(secs.max(1), Ordering::Relaxed);
+}
+
+pub fn download_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(DOWNLOAD_TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+// 当前网络是否按流量计费（如手机热点），无法判断时视为不计费
+#[cfg(target_os = "windows")]
+pub fn is_metered_connection() -> bool {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+    
+    // PE 环境中可能没有 WinRT 网络接口，任何一步失败都视为不计费
+    let cost_type = NetworkInformation::GetInternetConnectionProfile()
+        .and_then(|profile| profile.GetConnectionCost())
+        .and_then(|cost| cost.NetworkCostType());
+    
+    matches!(cost_type, Ok(NetworkCostType::Fixed) | Ok(NetworkCostType::Variable))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_metered_connection() -> bool {
+    false
+}
+
+// 需要认证的主机及其请求头
+static SOURCE_AUTH: Lazy<RwLock<Vec<SourceAuth>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+pub fn set_source_auth(entries: Vec<SourceAuth>) {
+    *SOURCE_AUTH.write() = entries;
+}
+
+// 为指定地址的请求附加已配置的认证请求头。请求头标记为敏感，不会出现在调试输出中；
+// 名称或值无效的条目直接忽略
+pub fn with_source_auth(request: reqwest::RequestBuilder, url: &str) -> reqwest::RequestBuilder {
+    let Some(host) = reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)) else {
+        return request;
+    };
+    
+    let entries = SOURCE_AUTH.read();
+    let Some(entry) = entries.iter().find(|entry| entry.host.trim().eq_ignore_ascii_case(&host)) else {
+        return request;
+    };
+    
+    let name = reqwest::header::HeaderName::from_bytes(entry.header_name.trim().as_bytes());
+    let value = reqwest::header::HeaderValue::from_str(entry.header_value.trim());
+    match (name, value) {
+        (Ok(name), Ok(mut value)) => {
+            value.set_sensitive(true);
+            request.header(name, value)
+        }
+        _ => request,
+    }
+}
+
+fn has_source_auth(host: &str) -> bool {
+    SOURCE_AUTH.read().iter().any(|entry| entry.host.trim().eq_ignore_ascii_case(host))
+}
+
+// 重定向时 reqwest 只会去掉 Authorization 等标准请求头，自定义的认证请求头会原样发往新地址。
+// 因此从需要认证的主机跳转到其他主机时停止跟随，直接返回重定向响应
+fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= 10 {
+            return attempt.error("重定向次数过多");
+        }
+        
+        let origin_host = attempt.previous().first().and_then(|url| url.host_str()).map(str::to_string);
+        if let Some(origin_host) = origin_host.filter(|host| has_source_auth(host)) {
+            let same_host = attempt.url().host_str().is_some_and(|host| host.eq_ignore_ascii_case(&origin_host));
+            if !same_host {
+                crate::app_log::log(format!(
+                    "{} 需要认证，已拒绝跟随重定向到其他主机 {}",
+                    origin_host,
+                    attempt.url().host_str().unwrap_or_default()
+                ));
+                return attempt.stop();
+            }
+        }
+        
+        attempt.follow()
+    })
+}
+
+// 最近一次连接检测的结果，None 表示尚未检测
+static LAST_CONNECTION_TEST: Lazy<RwLock<Option<bool>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn last_connection_test() -> Option<bool> {
+    *LAST_CONNECTION_TEST.read()
+}
+
+// 读取 PEM 或 DER 格式的固定证书，返回其公钥的 SHA-256
+fn load_certificate_pin(path: &Path) -> Result<[u8; 32], String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("无法读取固定证书 {}: {}", path.display(), e))?;
+    let der = match x509_parser::pem::parse_x509_pem(&bytes) {
+        Ok((_, pem)) => pem.contents,
+        Err(_) => bytes,
+    };
+    spki_sha256(&der).ok_or_else(|| format!("{} 不是有效的证书", path.display()))
+}
+
+fn spki_sha256(der: &[u8]) -> Option<[u8; 32]> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(der).ok()?;
+    Some(Sha256::digest(certificate.public_key().raw).into())
+}
+
+// 先按内置根证书正常校验证书链，再要求服务器证书本身的公钥与固定值一致。
+// 中间证书由服务器随意提供，不参与比对，否则中间人可以附带一份公开的固定证书绕过检查；
+// pin 为 None 表示固定证书无法使用，拒绝所有连接
+struct PinnedCertVerifier {
+    inner: Arc<dyn rustls::client::ServerCertVerifier>,
+    pin: Option<[u8; 32]>,
+}
+
+impl rustls::client::ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        server_name: &rustls::ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        let Some(pin) = self.pin else {
+            return Err(rustls::Error::General("固定证书无法使用".to_string()));
+        };
+        
+        let verified = self.inner.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        
+        if spki_sha256(&end_entity.0) == Some(pin) {
+            Ok(verified)
+        } else {
+            PIN_MISMATCH.store(true, Ordering::Relaxed);
+            crate::app_log::log(format!("{:?} 的证书与固定证书不一致，已拒绝连接", server_name));
+            Err(rustls::Error::General("服务器证书与固定证书不一致".to_string()))
+        }
+    }
+}
+
+fn pinned_tls_config(pin: Option<[u8; 32]>) -> rustls::ClientConfig {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+    }));
+    
+    let verifier = PinnedCertVerifier {
+        inner: Arc::new(rustls::client::WebPkiVerifier::new(roots, None)),
+        pin,
+    };
+    
+    rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+// 所有网络请求共用的客户端配置
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder().redirect(redirect_policy());
+    
+    // 代理无法使用时由调用方经 proxy_problem 在发出请求前拒绝
+    if let Some(Ok(proxy)) = PROXY.read().as_ref() {
+        builder = builder.proxy(proxy.clone());
+    }
+    
+    let pin = match &*CERTIFICATE_PIN.read() {
+        CertificatePin::Disabled => return builder,
+        CertificatePin::Pinned(pin) => Some(*pin),
+        CertificatePin::Unusable(_) => None,
+    };
+    
+    builder
+        .use_preconfigured_tls(pinned_tls_config(pin))
+        .https_only(true)
+}
+
+pub fn build_client() -> reqwest::Result<reqwest::Client> {
+    client_builder().build()
+}
+
+// 连接检测的最大尝试次数
+pub const CONNECTION_TEST_ATTEMPTS: u32 = 3;
+
+// 连接检测失败的原因，取最后一次尝试的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionFailure {
+    Offline,
+    Proxy,
+    NoTestUrl,
+    Timeout,
+    Connection,
+    EmptyBody,
+}
+
+impl ConnectionFailure {
+    pub fn description(self) -> &'static str {
+        match self {
+            ConnectionFailure::Offline => "已启用离线模式",
+            ConnectionFailure::Proxy => "代理设置无效，已拒绝直接连接",
+            ConnectionFailure::NoTestUrl => "插件源没有检测地址",
+            ConnectionFailure::Timeout => "连接超时",
+            ConnectionFailure::Connection => "无法连接到服务器",
+            ConnectionFailure::EmptyBody => "服务器返回了空内容",
+        }
+    }
+    
+    fn from_error(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ConnectionFailure::Timeout
+        } else {
+            ConnectionFailure::Connection
+        }
+    }
+}
+
+// 检测插件源是否可用，失败时最多重试 3 次。
+// HEAD 方式只确认服务器可以访问，不会下载整个插件列表；服务器不支持 HEAD 时改用 GET。
+// 每次尝试开始前以尝试序号（从 1 开始）调用 on_attempt，失败时返回最后一次尝试的失败原因
+pub async fn test_connection_with_progress(
+    mode: PluginMode,
+    strategy: ConnectTestStrategy,
+    mut on_attempt: impl FnMut(u32) + Send,
+) -> Result<(), ConnectionFailure> {
+    let result = run_connection_test(mode, strategy, &mut on_attempt).await;
+    *LAST_CONNECTION_TEST.write() = Some(result.is_ok());
+    crate::app_log::log(match result {
+        Ok(()) => format!("连接检测成功: {}", mode.get_title()),
+        Err(failure) => format!("连接检测失败: {}，{}", mode.get_title(), failure.description()),
+    });
+    result
+}
+
+async fn run_connection_test(
+    mode: PluginMode,
+    strategy: ConnectTestStrategy,
+    on_attempt: &mut (impl FnMut(u32) + Send),
+) -> Result<(), ConnectionFailure> {
+    if is_offline_mode() {
+        return Err(ConnectionFailure::Offline);
+    }
+    if proxy_problem().is_some() {
+        return Err(ConnectionFailure::Proxy);
+    }
+    
+    let url = mode.get_connect_test_url();
+    if url.is_empty() {
+        return Err(ConnectionFailure::NoTestUrl);
+    }
+    
+    let client = client_builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| ConnectionFailure::from_error(&e))?;
+    
+    let mut failure = ConnectionFailure::Connection;
+    for attempt in 1..=CONNECTION_TEST_ATTEMPTS {
+        on_attempt(attempt);
+        
+        let result = match strategy {
+            ConnectTestStrategy::Head => match client.head(url).send().await {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(_) => fetch_test_url(&client, url).await,
+                Err(e) => Err(ConnectionFailure::from_error(&e)),
+            },
+            ConnectTestStrategy::Full => fetch_test_url(&client, url).await,
+        };
+        
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) => failure = e,
+        }
+        
+        if attempt < CONNECTION_TEST_ATTEMPTS {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    }
+    
+    Err(failure)
+}
+
+async fn fetch_test_url(client: &reqwest::Client, url: &str) -> Result<(), ConnectionFailure> {
+    let response = client.get(url).send().await
+        .map_err(|e| ConnectionFailure::from_error(&e))?;
+    let text = response.text().await
+        .map_err(|e| ConnectionFailure::from_error(&e))?;
+    
+    if text.is_empty() {
+        Err(ConnectionFailure::EmptyBody)
+    } else {
+        Ok(())
+    }
+}
+
 #[allow(dead_code)]
 pub async fn check_network() -> bool {
-    let client = reqwest::Client::new();
+    if is_offline_mode() || proxy_problem().is_some() {
+        return false;
+    }
+    
+    let client = match build_client() {
+        Ok(client) => client,
+        Err(_) => return false,
+    };
+    
     match client
         .get("https://api.cloud-pe.cn/Hub/connecttest/")
         .timeout(std::time::Duration::from_secs(5))
@@ -10,4 +417,85 @@ pub async fn check_network() -> bool {
         Ok(response) => response.status().is_success(),
         Err(_) => false,
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SERVER_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBfjCCASWgAwIBAgIUA69bL6CNvPFHHpaVV2Fp2ZA21fkwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJYS5leGFtcGxlMCAXDTI2MTAxNjE2MDc0NloYDzIxMjYwOTIy
+MTYwNzQ2WjAUMRIwEAYDVQQDDAlhLmV4YW1wbGUwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAARouY77yT+PHDetywKl2OV7UUw6wpRfIRva7lOgTL6vFN4fKri47rho
+SsMYvfJNzTLJ9dwVbj17xyzSDVSayoY6o1MwUTAdBgNVHQ4EFgQUHOCEGwkU+S1f
+enui15VTibHRsswwHwYDVR0jBBgwFoAUHOCEGwkU+S1fenui15VTibHRsswwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiAl9iAjf03e1evSFazkpdes
+OYCTt3A3MAAAcVuuyr/9OwIgWZX10li9z2kWM25UVY6/ksmcFBorUAsysTFxCgQ1
+Dug=
+-----END CERTIFICATE-----";
+    const PINNED_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBgDCCASWgAwIBAgIUJpKr7hwtaPtSaNXQRwAyWlqVFEYwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJYi5leGFtcGxlMCAXDTI2MTAxNjE2MDc0NloYDzIxMjYwOTIy
+MTYwNzQ2WjAUMRIwEAYDVQQDDAliLmV4YW1wbGUwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAATpnjsAqvtTGXf197OI5AxyoRLVTFYNjLDilCMl+JvDAID7Jy5Nc2xb
+QgEB8K8uFEMCWbuQ3gduqdp5hSdfPwzXo1MwUTAdBgNVHQ4EFgQUpU2TWwbFpWuH
+n/OpEbIRHHWmUlcwHwYDVR0jBBgwFoAUpU2TWwbFpWuHn/OpEbIRHHWmUlcwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEAhZR1MaXOg2uJv2zklANa
+Ge7SptHnHGI/DdS3QcGsHpkCIQCYfw55H0qcp32adDG1HGR8sJnoT2dxtbnoPT00
++KucCA==
+-----END CERTIFICATE-----";
+    
+    // 只测试固定证书的比对，证书链校验总是通过
+    struct AcceptAll;
+    
+    impl rustls::client::ServerCertVerifier for AcceptAll {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &rustls::Certificate,
+            _intermediates: &[rustls::Certificate],
+            _server_name: &rustls::ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: std::time::SystemTime,
+        ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        }
+    }
+    
+    fn certificate(pem: &str) -> rustls::Certificate {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(pem.as_bytes()).unwrap();
+        rustls::Certificate(pem.contents)
+    }
+    
+    fn verify(pin: &str, end_entity: &str, intermediates: &[&str]) -> bool {
+        let verifier = PinnedCertVerifier {
+            inner: Arc::new(AcceptAll),
+            pin: spki_sha256(&certificate(pin).0),
+        };
+        let intermediates: Vec<rustls::Certificate> = intermediates.iter().map(|pem| certificate(pem)).collect();
+        let server_name = rustls::ServerName::try_from("a.example").unwrap();
+        
+        rustls::client::ServerCertVerifier::verify_server_cert(
+            &verifier,
+            &certificate(end_entity),
+            &intermediates,
+            &server_name,
+            &mut std::iter::empty(),
+            &[],
+            std::time::SystemTime::now(),
+        )
+        .is_ok()
+    }
+    
+    #[test]
+    fn pinned_server_certificate_is_accepted() {
+        assert!(verify(PINNED_CERT, PINNED_CERT, &[]));
+    }
+    
+    #[test]
+    fn pinned_certificate_as_extra_intermediate_is_rejected() {
+        assert!(!verify(PINNED_CERT, SERVER_CERT, &[PINNED_CERT]));
+        assert!(!verify(PINNED_CERT, SERVER_CERT, &[]));
+    }
 }
\ No newline at end of file
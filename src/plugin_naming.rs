@@ -0,0 +1,242 @@
+use crate::mode::PluginMode;
+use crate::plugins::Plugin;
+use std::path::Path;
+
+// 插件文件名的生成与解析。各插件源的字段顺序不同：
+//   Cloud-PE  名称_版本_作者_描述.ce
+//   HotPE     名称_作者_版本_描述.HPM（描述为空时以名称代替，解析时还原为空）
+//   Edgeless  名称_版本_作者.7z
+// 生成和解析都以这里为准，避免两边的字段顺序不一致
+
+// 从文件名中解析出的插件字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginFileFields {
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    pub describe: String,
+}
+
+// 按插件源的命名规则生成不含扩展名的插件文件名
+pub fn encode(plugin: &Plugin, mode: PluginMode) -> String {
+    let name = normalize_file_field(&plugin.name);
+    let version = normalize_file_field(&plugin.version);
+    let author = normalize_file_field(&plugin.author);
+    let safe_describe = plugin.describe
+        .trim()
+        .replace([' ', '/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+    let safe_describe = normalize_file_field(&safe_describe);
+    
+    match mode {
+        PluginMode::CloudPE => {
+            format!("{}_{}_{}_{}", name, version, author, safe_describe)
+        }
+        PluginMode::HotPE => {
+            if safe_describe.is_empty() {
+                format!("{}_{}_{}_{}", name, author, version, name)
+            } else {
+                format!("{}_{}_{}_{}", name, author, version, safe_describe)
+            }
+        }
+        PluginMode::Edgeless => {
+            format!("{}_{}_{}", name, version, author)
+        }
+        _ => String::new()
+    }
+}
+
+// 解析插件文件名，可带启用或禁用扩展名，字段不足三段时返回 None
+pub fn decode(file_name: &str, mode: PluginMode) -> Option<PluginFileFields> {
+    let base_name = strip_plugin_extension(file_name, mode);
+    let parts = split_file_fields(base_name);
+    
+    // Cloud-PE 描述为空时文件名以 "_" 结尾，只剩三段
+    if parts.len() < 3 {
+        return None;
+    }
+    
+    let (name, version, author, mut describe) = match mode {
+        PluginMode::CloudPE => (parts[0], parts[1], parts[2].to_string(), parts[3..].join("_")),
+        PluginMode::HotPE => (parts[0], parts[2], parts[1].to_string(), parts[3..].join("_")),
+        // Edgeless 没有描述字段，作者中可能含有 "_"
+        PluginMode::Edgeless => (parts[0], parts[1], parts[2..].join("_"), String::new()),
+        _ => return None,
+    };
+    
+    // HotPE 生成文件名时用名称代替空描述，还原后 decode(encode(p)) 与原插件一致
+    if mode == PluginMode::HotPE && describe == name {
+        describe.clear();
+    }
+    
+    Some(PluginFileFields {
+        name: name.to_string(),
+        version: version.to_string(),
+        author,
+        describe,
+    })
+}
+
+// 去掉启用/禁用扩展名（不区分大小写），描述中的 "." 保持不变。
+// 不是插件扩展名时原样返回
+pub fn strip_plugin_extension(file_name: &str, mode: PluginMode) -> &str {
+    if mode == PluginMode::HotPE {
+        return split_hotpe_file_name(file_name)
+            .map(|(base, _)| base)
+            .unwrap_or(file_name);
+    }
+    
+    let path = Path::new(file_name);
+    let is_plugin_extension = path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case(mode.get_enabled_extension())
+            || extension.eq_ignore_ascii_case(mode.get_disabled_extension())
+    });
+    
+    match path.file_stem().and_then(|stem| stem.to_str()) {
+        Some(stem) if is_plugin_extension => stem,
+        _ => file_name,
+    }
+}
+
+// 启用或禁用后的文件名，只替换末尾的扩展名（不区分大小写）。
+// 文件已处于目标状态或不是插件文件时返回 None
+pub fn toggled_file_name(file_name: &str, mode: PluginMode, enable: bool) -> Option<String> {
+    if mode == PluginMode::HotPE {
+        return match split_hotpe_file_name(file_name)? {
+            // 去掉 .off 即恢复禁用前的原始文件名
+            (_, false) if enable => Some(file_name[..file_name.len() - ".off".len()].to_string()),
+            // 保留原扩展名的大小写，启用时才能还原为同一个文件名
+            (_, true) if !enable => Some(format!("{}.off", file_name)),
+            _ => None,
+        };
+    }
+    
+    let (from, to) = if enable {
+        (mode.get_disabled_extension(), mode.get_enabled_extension())
+    } else {
+        (mode.get_enabled_extension(), mode.get_disabled_extension())
+    };
+    
+    let path = Path::new(file_name);
+    if from.is_empty() || !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case(from)) {
+        return None;
+    }
+    
+    let stem = path.file_stem()?.to_str()?;
+    Some(format!("{}.{}", stem, to))
+}
+
+// 拆分 HotPE 模块文件名，返回去掉扩展名后的部分以及是否为启用状态。
+// 启用为 .HPM，禁用为在启用文件名后追加 .off，扩展名大小写不限。
+pub fn split_hotpe_file_name(file_name: &str) -> Option<(&str, bool)> {
+    let lower = file_name.to_ascii_lowercase();
+    
+    if lower.ends_with(".hpm.off") {
+        Some((&file_name[..file_name.len() - ".hpm.off".len()], false))
+    } else if lower.ends_with(".hpm") {
+        Some((&file_name[..file_name.len() - ".hpm".len()], true))
+    } else {
+        None
+    }
+}
+
+// 按 "_" 拆分文件名字段，去掉每段首尾空白并忽略空段（连续的 "_"）
+fn split_file_fields(base_name: &str) -> Vec<&str> {
+    base_name
+        .split('_')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+// 生成文件名时规范化单个字段，使重新解析的结果与原字段一致
+fn normalize_file_field(field: &str) -> String {
+    split_file_fields(field).join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn plugin(name: &str, version: &str, author: &str, describe: &str) -> Plugin {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "size": "",
+            "version": version,
+            "author": author,
+            "describe": describe,
+            "link": "",
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn hotpe_extension_is_case_insensitive() {
+        assert_eq!(split_hotpe_file_name("a_b_1.0_c.HPM"), Some(("a_b_1.0_c", true)));
+        assert_eq!(split_hotpe_file_name("a_b_1.0_c.hpm"), Some(("a_b_1.0_c", true)));
+        assert_eq!(split_hotpe_file_name("a_b_1.0_c.Hpm.OFF"), Some(("a_b_1.0_c", false)));
+        assert_eq!(split_hotpe_file_name("a_b_1.0_c.7z"), None);
+    }
+    
+    #[test]
+    fn hotpe_toggle_round_trips() {
+        for name in ["a_b_1.0_c.HPM", "a_b_1.0_c.hpm"] {
+            let disabled = toggled_file_name(name, PluginMode::HotPE, false).unwrap();
+            assert_eq!(disabled, format!("{}.off", name));
+            assert_eq!(toggled_file_name(&disabled, PluginMode::HotPE, true).as_deref(), Some(name));
+        }
+        
+        // 已处于目标状态时不改名
+        assert_eq!(toggled_file_name("a_b_1.0_c.HPM", PluginMode::HotPE, true), None);
+        assert_eq!(toggled_file_name("a_b_1.0_c.HPM.off", PluginMode::HotPE, false), None);
+    }
+    
+    #[test]
+    fn decode_trims_fields_and_skips_empty_segments() {
+        let fields = decode(" 名称 _ 1.0 __作者_ 描述 .ce", PluginMode::CloudPE).unwrap();
+        assert_eq!(fields.name, "名称");
+        assert_eq!(fields.version, "1.0");
+        assert_eq!(fields.author, "作者");
+        assert_eq!(fields.describe, "描述");
+    }
+    
+    #[test]
+    fn encode_normalizes_fields() {
+        let plugin = plugin(" 名称 ", "1.0 ", " 作者", "");
+        assert_eq!(encode(&plugin, PluginMode::CloudPE), "名称_1.0_作者_");
+        assert_eq!(encode(&plugin, PluginMode::Edgeless), "名称_1.0_作者");
+    }
+    
+    #[test]
+    fn strip_extension_keeps_dots_in_description() {
+        assert_eq!(strip_plugin_extension("名称_1.0_作者_支持 v2.1.ce", PluginMode::CloudPE), "名称_1.0_作者_支持 v2.1");
+        assert_eq!(strip_plugin_extension("名称_1.0_作者_支持 v2.1.CBK", PluginMode::CloudPE), "名称_1.0_作者_支持 v2.1");
+        assert_eq!(strip_plugin_extension("名称_作者_1.0_a.b.hpm.off", PluginMode::HotPE), "名称_作者_1.0_a.b");
+        // 不是插件扩展名时原样返回
+        assert_eq!(strip_plugin_extension("名称_1.0_作者_支持 v2.1", PluginMode::CloudPE), "名称_1.0_作者_支持 v2.1");
+        
+        let fields = decode("名称_1.0_作者_支持 v2.1.ce", PluginMode::CloudPE).unwrap();
+        assert_eq!(fields.describe, "支持 v2.1");
+    }
+    
+    #[test]
+    fn encode_decode_round_trip() {
+        let modes = [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless];
+        for describe in ["", "说明 文字"] {
+            let plugin = plugin("名称", "1.0", "作者", describe);
+            for mode in modes {
+                let file_name = format!("{}.{}", encode(&plugin, mode), mode.get_enabled_extension());
+                let fields = decode(&file_name, mode).unwrap();
+                assert_eq!(fields.name, plugin.name);
+                assert_eq!(fields.version, plugin.version);
+                assert_eq!(fields.author, plugin.author);
+                
+                // Edgeless 文件名不含描述；描述中的空格生成文件名时替换为 "_"
+                let expected = match mode {
+                    PluginMode::Edgeless => String::new(),
+                    _ => describe.replace(' ', "_"),
+                };
+                assert_eq!(fields.describe, expected, "{:?}", mode);
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::plugins::{PluginCategory, PluginError};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::io::Read;
+use tokio::sync::mpsc::Receiver;
+
+// 插件列表的流式解析：响应一边下载一边解析，每解析完一个分类就交给回调，
+// 不需要先把整个响应读入内存，大型插件源在 PE 中可以明显减少内存占用
+
+// 下载与解析线程之间最多缓存的数据块数
+const CHUNK_BUFFER: usize = 16;
+
+// 响应中分类列表以外的字段。Cloud-PE 和 Edgeless 使用 code 和 message，HotPE 使用 state
+#[derive(Debug, Default)]
+pub struct PageHeader {
+    pub code: Option<i32>,
+    pub message: Option<String>,
+    pub state: Option<String>,
+    // 分页时指向下一页的 URL 或游标，不分页的源不返回此字段
+    pub next: Option<String>,
+}
+
+// 流式读取一页插件列表。data 中的每个分类按 T 解析，经 convert 转换后依次交给 on_category
+pub async fn read_plugin_page<T, F>(
+    mut response: reqwest::Response,
+    convert: fn(T) -> PluginCategory,
+    on_category: F,
+) -> Result<PageHeader, PluginError>
+where
+    T: DeserializeOwned + Send + 'static,
+    F: FnMut(PluginCategory) + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::mpsc::channel(CHUNK_BUFFER);
+    
+    // serde_json 只能从同步的 Read 中解析，放到阻塞线程中运行
+    let parser = tokio::task::spawn_blocking(move || {
+        let mut deserializer = serde_json::Deserializer::from_reader(ChunkReader::new(receiver));
+        let header = PageSeed { convert, on_category }.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok::<_, serde_json::Error>(header)
+    });
+    
+    let mut decoder = LossyUtf8Decoder::default();
+    while let Some(chunk) = response.chunk().await? {
+        // 解析出错时解析线程会提前结束，不再需要后续数据
+        if sender.send(decoder.decode(&chunk)).await.is_err() {
+            break;
+        }
+    }
+    let _ = sender.send(decoder.finish()).await;
+    drop(sender);
+    
+    let header = parser.await
+        .map_err(|e| PluginError::Api(format!("解析插件列表失败: {}", e)))??;
+    Ok(header)
+}
+
+// 不依赖响应声明的编码，按 UTF-8 解码，非法字节替换为占位符，其余内容仍可解析。
+// 跨数据块的字符留到下一块一起解码；部分服务器会在 JSON 前加 UTF-8 BOM，serde_json 无法直接解析，一并去掉
+#[derive(Default)]
+struct LossyUtf8Decoder {
+    pending: Vec<u8>,
+    started: bool,
+}
+
+impl LossyUtf8Decoder {
+    fn decode(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        
+        let mut output = Vec::with_capacity(self.pending.len());
+        let consumed = {
+            let mut rest = &self.pending[..];
+            loop {
+                match std::str::from_utf8(rest) {
+                    Ok(valid) => {
+                        output.extend_from_slice(valid.as_bytes());
+                        rest = &[];
+                        break;
+                    }
+                    Err(e) => {
+                        let (valid, invalid) = rest.split_at(e.valid_up_to());
+                        output.extend_from_slice(valid);
+                        match e.error_len() {
+                            Some(len) => {
+                                output.extend_from_slice(char::REPLACEMENT_CHARACTER.to_string().as_bytes());
+                                rest = &invalid[len..];
+                            }
+                            // 字符不完整，剩余字节可能在下一块中
+                            None => {
+                                rest = invalid;
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            self.pending.len() - rest.len()
+        };
+        self.pending.drain(..consumed);
+        
+        self.strip_bom(output)
+    }
+    
+    // 数据结束时仍未完整的字节替换为占位符
+    fn finish(&mut self) -> Vec<u8> {
+        let output = String::from_utf8_lossy(&self.pending).into_owned().into_bytes();
+        self.pending.clear();
+        self.strip_bom(output)
+    }
+    
+    fn strip_bom(&mut self, mut output: Vec<u8>) -> Vec<u8> {
+        if !self.started && !output.is_empty() {
+            self.started = true;
+            if output.starts_with("\u{feff}".as_bytes()) {
+                output.drain(.."\u{feff}".len());
+            }
+        }
+        output
+    }
+}
+
+// 把下载线程送来的数据块作为连续的 Read 提供给 serde_json，发送端关闭即视为数据结束
+struct ChunkReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChunkReader {
+    fn new(receiver: Receiver<Vec<u8>>) -> Self {
+        Self {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+// 解析整页响应，data 以外的字段记录到 PageHeader，未知字段跳过
+struct PageSeed<T, F> {
+    convert: fn(T) -> PluginCategory,
+    on_category: F,
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for PageSeed<T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(PluginCategory),
+{
+    type Value = PageHeader;
+    
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for PageSeed<T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(PluginCategory),
+{
+    type Value = PageHeader;
+    
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("插件列表")
+    }
+    
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut header = PageHeader::default();
+        let mut has_data = false;
+        
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "code" => header.code = Some(map.next_value()?),
+                "message" => header.message = Some(map.next_value()?),
+                "state" => header.state = Some(map.next_value()?),
+                "next" => header.next = map.next_value()?,
+                "data" => {
+                    map.next_value_seed(CategoriesSeed {
+                        convert: self.convert,
+                        on_category: &mut self.on_category,
+                    })?;
+                    has_data = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        
+        if !has_data {
+            return Err(de::Error::missing_field("data"));
+        }
+        
+        Ok(header)
+    }
+}
+
+// 逐个解析 data 中的分类，解析完一个就交出一个
+struct CategoriesSeed<'a, T, F> {
+    convert: fn(T) -> PluginCategory,
+    on_category: &'a mut F,
+}
+
+impl<'de, T, F> DeserializeSeed<'de> for CategoriesSeed<'_, T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(PluginCategory),
+{
+    type Value = ();
+    
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T, F> Visitor<'de> for CategoriesSeed<'_, T, F>
+where
+    T: DeserializeOwned,
+    F: FnMut(PluginCategory),
+{
+    type Value = ();
+    
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("插件分类列表")
+    }
+    
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while let Some(category) = seq.next_element::<T>()? {
+            (self.on_category)((self.convert)(category));
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,51 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 use std::collections::{HashSet, HashMap};
+use crate::config::ReleaseChannel;
 use crate::mode::PluginMode;
+use crate::plugin_naming::{self, split_hotpe_file_name};
+use crate::plugin_stream;
+use crate::utils::{drive_path, plugin_dir};
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// 分页获取插件列表时的最大页数，防止服务器返回异常游标导致死循环
+const MAX_PLUGIN_PAGES: usize = 50;
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("网络请求失败: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("数据解析失败: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("文件操作失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("文件不存在")]
+    NotFound,
+    #[error("{0}")]
+    Api(String),
+    #[error("不支持的模式")]
+    UnsupportedMode,
+    #[error("离线模式下无法获取插件列表，请在设置中关闭离线模式")]
+    Offline,
+    #[error("代理设置无效，无法获取插件列表：{0}")]
+    Proxy(String),
+    #[error("{0}")]
+    DriveLocked(String),
+    #[error("已存在同名文件 {0}，为避免覆盖未进行重命名")]
+    AlreadyExists(String),
+    #[error("启用状态可能不完整：{0}，请检查启动盘")]
+    RenameIncomplete(String),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plugin {
+    // 服务器提供的稳定 ID，名称或作者变更后仍能识别为同一插件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub name: String,
     pub size: String,
     pub version: String,
@@ -16,15 +55,122 @@ pub struct Plugin {
     #[serde(default)]
     pub file: String,
     pub link: String,
+    // 依赖的插件 ID 列表（名称_作者）
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    // 插件作者提供的问题反馈地址和主页
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    // 插件适用的平台（cloudpe、hotpe、edgeless），未标注时不做检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    // 插件文件的 SHA-256（十六进制），提供时下载完成后校验，旧接口没有此字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // 所属的市场分类，获取列表后填充；本地插件为空
+    #[serde(skip)]
+    pub category: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DependencyResolution {
+    // 需要安装的依赖，按安装顺序排列，不含已安装的插件
+    pub to_install: Vec<Plugin>,
+    pub missing: Vec<String>,
+    pub cycles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginBackup {
+    pub created: String,
+    pub enabled: Vec<Plugin>,
+    pub disabled: Vec<Plugin>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    pub changed: usize,
+    // 启动盘上已不存在、但能在市场中找到的插件
+    pub missing: Vec<Plugin>,
+    // 启动盘和市场中都找不到的插件名称
+    pub unavailable: Vec<String>,
+}
+
+// 启动盘上文件名与市场信息不一致的插件，以及按命名规则生成的新文件名
+#[derive(Debug, Clone)]
+pub struct NameFix {
+    pub file: String,
+    pub new_file: String,
+    pub market_plugin: Plugin,
 }
 
 impl Plugin {
-    fn get_unique_key(&self) -> String {
-        format!("{}_{}_{}_{}", self.name, self.version, self.author, self.size)
+    // 去重用的键。同一工具的不同架构版本名称、版本、作者、大小可能完全相同，只有下载地址不同，
+    // 因此把下载地址也算在内，避免被当作重复项丢弃
+    pub fn get_unique_key(&self) -> String {
+        format!("{}_{}_{}_{}_{}", self.name, self.version, self.author, self.size, self.link)
+    }
+    
+    // 问题反馈地址，插件未提供时依次使用主页和插件源的反馈页面
+    pub fn get_report_url(&self, mode: PluginMode) -> String {
+        [&self.issue_url, &self.homepage]
+            .into_iter()
+            .flatten()
+            .find(|url| url.starts_with("http"))
+            .cloned()
+            .unwrap_or_else(|| mode.get_feedback_url().to_string())
+    }
+    
+    // 用于在论坛等处分享的插件信息
+    pub fn share_text(&self) -> String {
+        format!(
+            "名称: {}\n版本: {}\n作者: {}\n大小: {}\n链接: {}",
+            self.name, self.version, self.author, self.size, self.link
+        )
+    }
+    
+    pub fn share_markdown(&self) -> String {
+        format!(
+            "**{}** {}\n\n- 作者: {}\n- 大小: {}\n- 链接: <{}>",
+            self.name, self.version, self.author, self.size, self.link
+        )
+    }
+    
+    // 标注的适用平台，未标注或无法识别时返回 None
+    pub fn target_mode(&self) -> Option<PluginMode> {
+        let platform = self.platform.as_deref()?.trim().to_ascii_lowercase().replace(['-', '_', ' '], "");
+        match platform.as_str() {
+            "cloudpe" => Some(PluginMode::CloudPE),
+            "hotpe" => Some(PluginMode::HotPE),
+            "edgeless" => Some(PluginMode::Edgeless),
+            _ => None,
+        }
+    }
+    
+    // 标注的适用平台与当前插件源不符时返回提示信息
+    pub fn compatibility_error(&self, mode: PluginMode) -> Option<String> {
+        let target = self.target_mode().filter(|target| *target != mode)?;
+        Some(format!(
+            "{} 适用于 {}，与当前的 {} 不兼容",
+            self.name,
+            target.get_server_name(),
+            mode.get_server_name(),
+        ))
     }
     
     pub fn get_plugin_id(&self) -> String {
-        format!("{}_{}", self.name, self.author)
+        match &self.id {
+            Some(id) if !id.trim().is_empty() => id.trim().to_string(),
+            _ => self.get_legacy_id(),
+        }
+    }
+    
+    // 没有稳定 ID 时使用的 名称_作者
+    fn get_legacy_id(&self) -> String {
+        // 与解析文件名时的规范化保持一致，避免首尾空白导致 ID 不匹配
+        format!("{}_{}", self.name.trim(), self.author.trim())
     }
 }
 
@@ -33,27 +179,20 @@ pub struct PluginCategory {
     pub class: String,
     #[serde(default)]
     pub icon: Option<String>,
+    // 服务器指定的分类排序，数值小的靠前
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
     pub list: Vec<Plugin>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CloudPEResponse {
-    pub code: i32,
-    pub message: String,
-    pub data: Vec<PluginCategory>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HotPEResponse {
-    pub state: String,
-    pub data: Vec<HotPECategory>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotPECategory {
     pub class: String,
     #[serde(default)]
     pub icon: Option<String>,
+    // 服务器指定的分类排序，数值小的靠前
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
     pub list: Vec<HotPEPlugin>,
 }
 
@@ -95,11 +234,79 @@ fn format_timestamp(timestamp: i64) -> String {
     }
 }
 
+// 服务器返回的下载地址可能是相对地址（如 /files/foo.ce），按插件列表的地址补全为绝对地址。
+// 绝对地址原样保留
+fn resolve_plugin_link(page_url: &reqwest::Url, link: &str) -> String {
+    let link = link.trim();
+    if link.is_empty() || reqwest::Url::parse(link).is_ok() {
+        return link.to_string();
+    }
+    
+    match page_url.join(link) {
+        Ok(url) => url.to_string(),
+        Err(_) => link.to_string(),
+    }
+}
+
+// 把 HotPE 的模块分类转换为通用的插件分类，名称、作者等字段从文件名中解析
+fn convert_hotpe_category(hotpe_category: HotPECategory) -> PluginCategory {
+    let mut plugins = Vec::new();
+    
+    for hotpe_plugin in hotpe_category.list {
+        let file_name = hotpe_plugin.name.clone();
+        
+        let (name, author, version, describe) = match plugin_naming::decode(&file_name, PluginMode::HotPE) {
+            Some(fields) => (fields.name, fields.author, fields.version, fields.describe),
+            None => (file_name.clone(), String::new(), String::new(), String::new()),
+        };
+        
+        let size_str = match hotpe_plugin.size {
+            serde_json::Value::Number(n) => {
+                if let Some(size) = n.as_i64() {
+                    format_file_size(size)
+                } else if let Some(size) = n.as_f64() {
+                    format_file_size(size as i64)
+                } else {
+                    "未知大小".to_string()
+                }
+            }
+            serde_json::Value::String(s) => s,
+            _ => "未知大小".to_string(),
+        };
+        
+        plugins.push(Plugin {
+            id: None,
+            name,
+            size: size_str,
+            version,
+            author,
+            describe,
+            file: hotpe_plugin.name,
+            link: hotpe_plugin.link,
+            dependencies: Vec::new(),
+            issue_url: None,
+            homepage: None,
+            platform: None,
+            sha256: None,
+            category: String::new(),
+        });
+    }
+    
+    PluginCategory {
+        class: hotpe_category.class,
+        icon: hotpe_category.icon,
+        order: hotpe_category.order,
+        list: plugins,
+    }
+}
+
 pub struct PluginManager {
     pub categories: Vec<PluginCategory>,
     enabled_plugins: Vec<Plugin>,
     disabled_plugins: Vec<Plugin>,
     enabled_plugin_map: HashMap<String, Plugin>,
+    // 启动盘上记录的 文件名（不含启用/禁用扩展名）-> 稳定 ID
+    plugin_ids: HashMap<String, String>,
     mode: PluginMode,
 }
 
@@ -110,107 +317,125 @@ impl PluginManager {
             enabled_plugins: Vec::new(),
             disabled_plugins: Vec::new(),
             enabled_plugin_map: HashMap::new(),
+            plugin_ids: HashMap::new(),
             mode,
         }
     }
     
-    pub async fn fetch_plugins_async(mode: PluginMode) -> Result<Vec<PluginCategory>> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(mode.get_api_url())
-            .send()
-            .await?;
+    // 按 next 字段逐页获取并合并分类，pages_loaded 记录已加载的页数。
+    // 每解析完一个分类就合并到 partial 中，加载结束前界面可以先显示已到达的分类；
+    // 加载成功后 partial 被取空，返回排序去重后的完整列表
+    pub async fn fetch_plugins_async(
+        mode: PluginMode,
+        channel: ReleaseChannel,
+        pages_loaded: Arc<AtomicUsize>,
+        partial: Arc<RwLock<Vec<PluginCategory>>>,
+    ) -> Result<Vec<PluginCategory>, PluginError> {
+        if crate::network::is_offline_mode() {
+            return Err(PluginError::Offline);
+        }
+        if let Some(e) = crate::network::proxy_problem() {
+            return Err(PluginError::Proxy(e));
+        }
+        
+        let client = crate::network::build_client()?;
+        let base_url = channel.apply_to_url(mode.get_api_url());
+        let mut url = base_url.clone();
+        partial.write().clear();
+        let mut complete = false;
         
-        let text = response.text().await?;
+        for _ in 0..MAX_PLUGIN_PAGES {
+            let response = crate::network::with_source_auth(client.get(&url), &url)
+                .send()
+                .await?;
+            
+            let next = Self::read_plugin_page(mode, response, partial.clone()).await?;
+            
+            pages_loaded.fetch_add(1, Ordering::Relaxed);
+            
+            match next.filter(|n| !n.is_empty()) {
+                Some(next) if next.starts_with("http") => url = next,
+                Some(cursor) => {
+                    let separator = if base_url.contains('?') { '&' } else { '?' };
+                    let cursor = percent_encoding::utf8_percent_encode(&cursor, percent_encoding::NON_ALPHANUMERIC);
+                    url = format!("{}{}cursor={}", base_url, separator, cursor);
+                }
+                None => {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+        
+        // 达到页数上限时服务器仍返回了下一页，列表可能不完整
+        if !complete {
+            crate::app_log::log(format!(
+                "{}插件列表超过 {} 页，后面的部分未加载",
+                mode.get_server_name(),
+                MAX_PLUGIN_PAGES,
+            ));
+        }
+        
+        let mut categories = std::mem::take(&mut *partial.write());
+        
+        if mode != PluginMode::HotPE {
+            for category in &mut categories {
+                let mut seen = HashSet::new();
+                category.list.retain(|plugin| seen.insert(plugin.get_unique_key()));
+            }
+        }
+        
+        sort_categories(&mut categories);
+        
+        Ok(categories)
+    }
+    
+    // 流式解析一页插件列表并合并到 partial，返回下一页的 URL 或游标
+    async fn read_plugin_page(
+        mode: PluginMode,
+        response: reqwest::Response,
+        partial: Arc<RwLock<Vec<PluginCategory>>>,
+    ) -> Result<Option<String>, PluginError> {
+        let page_url = response.url().clone();
+        let on_category = move |mut category: PluginCategory| {
+            // 统一大小文本格式，使市场与本地插件显示一致
+            for plugin in &mut category.list {
+                plugin.size = normalize_size_text(&plugin.size);
+                plugin.link = resolve_plugin_link(&page_url, &plugin.link);
+                plugin.category = category.class.clone();
+            }
+            
+            let mut categories = partial.write();
+            match categories.iter_mut().find(|c| c.class == category.class) {
+                Some(existing) => existing.list.extend(category.list),
+                None => categories.push(category),
+            }
+        };
         
         match mode {
             PluginMode::CloudPE | PluginMode::Edgeless => {
-                let mut plugins_response: CloudPEResponse = serde_json::from_str(&text)?;
+                let header = plugin_stream::read_plugin_page::<PluginCategory, _>(
+                    response,
+                    std::convert::identity,
+                    on_category,
+                ).await?;
                 
-                if plugins_response.code == 200 {
-                    for category in &mut plugins_response.data {
-                        let mut seen = HashSet::new();
-                        let mut unique_plugins = Vec::new();
-                        
-                        for plugin in &category.list {
-                            let key = plugin.get_unique_key();
-                            if seen.insert(key) {
-                                unique_plugins.push(plugin.clone());
-                            }
-                        }
-                        
-                        category.list = unique_plugins;
-                    }
-                    
-                    Ok(plugins_response.data)
+                if header.code == Some(200) {
+                    Ok(header.next)
                 } else {
-                    anyhow::bail!("获取插件列表失败: {}", plugins_response.message)
+                    Err(PluginError::Api(format!("获取插件列表失败: {}", header.message.unwrap_or_default())))
                 }
             }
             PluginMode::HotPE => {
-                let hotpe_response: HotPEResponse = match serde_json::from_str(&text) {
-                    Ok(resp) => resp,
-                    Err(e) => {
-                        return Err(anyhow::anyhow!("解析HotPE响应失败: {}", e));
-                    }
-                };
+                let header = plugin_stream::read_plugin_page(response, convert_hotpe_category, on_category).await?;
                 
-                if hotpe_response.state == "success" {
-                    let mut categories = Vec::new();
-                    
-                    for hotpe_category in hotpe_response.data {
-                        let mut plugins = Vec::new();
-                        
-                        for hotpe_plugin in hotpe_category.list {
-                            let file_name = hotpe_plugin.name.clone();
-                            let parts: Vec<&str> = file_name.trim_end_matches(".HPM").split('_').collect();
-                            
-                            let (name, author, version, describe) = if parts.len() >= 4 {
-                                (parts[0].to_string(), parts[1].to_string(), parts[2].to_string(), parts[3..].join("_"))
-                            } else if parts.len() == 3 {
-                                (parts[0].to_string(), parts[1].to_string(), parts[2].to_string(), String::new())
-                            } else {
-                                (file_name.clone(), String::new(), String::new(), String::new())
-                            };
-                            
-                            let size_str = match hotpe_plugin.size {
-                                serde_json::Value::Number(n) => {
-                                    if let Some(size) = n.as_i64() {
-                                        format_file_size(size)
-                                    } else if let Some(size) = n.as_f64() {
-                                        format_file_size(size as i64)
-                                    } else {
-                                        "未知大小".to_string()
-                                    }
-                                }
-                                serde_json::Value::String(s) => s,
-                                _ => "未知大小".to_string(),
-                            };
-                            
-                            plugins.push(Plugin {
-                                name,
-                                size: size_str,
-                                version,
-                                author,
-                                describe,
-                                file: hotpe_plugin.name,
-                                link: hotpe_plugin.link,
-                            });
-                        }
-                        
-                        categories.push(PluginCategory {
-                            class: hotpe_category.class,
-                            icon: hotpe_category.icon,
-                            list: plugins,
-                        });
-                    }
-                    
-                    Ok(categories)
+                if header.state.as_deref() == Some("success") {
+                    Ok(header.next)
                 } else {
-                    anyhow::bail!("获取HotPE模块列表失败")
+                    Err(PluginError::Api("获取HotPE模块列表失败".to_string()))
                 }
             }
-            _ => anyhow::bail!("不支持的模式"),
+            _ => Err(PluginError::UnsupportedMode),
         }
     }
     
@@ -218,7 +443,8 @@ impl PluginManager {
         &self.categories
     }
     
-    pub fn search_plugins(&self, keyword: &str) -> Vec<Plugin> {
+    // 返回前 limit 个匹配的插件和匹配总数。结果较多时只复制需要显示的部分
+    pub fn search_plugins(&self, keyword: &str, limit: usize) -> (Vec<Plugin>, usize) {
         let keyword = keyword.to_lowercase();
         let mut results = Vec::new();
         let mut seen = HashSet::new();
@@ -230,28 +456,80 @@ impl PluginManager {
                     
                 if search_text.contains(&keyword) {
                     let key = plugin.get_unique_key();
-                    if seen.insert(key) {
+                    if seen.insert(key) && results.len() < limit {
                         results.push(plugin.clone());
                     }
                 }
             }
         }
         
-        results
+        (results, seen.len())
+    }
+    
+    fn clear_local_plugins(&mut self) {
+        self.enabled_plugins.clear();
+        self.disabled_plugins.clear();
+        self.enabled_plugin_map.clear();
+    }
+    
+    // 换入另一个管理器读取到的本地插件，用于在后台扫描完成后一次性更新列表
+    pub fn replace_local_plugins(&mut self, scanned: PluginManager) {
+        self.enabled_plugins = scanned.enabled_plugins;
+        self.disabled_plugins = scanned.disabled_plugins;
+        self.plugin_ids = scanned.plugin_ids;
+        self.rebuild_index();
+    }
+    
+    // 按 enabled_plugins 重建 ID 索引。修改已启用插件列表后都要调用，
+    // 否则 get_enabled_plugin_by_id 会查到过期的数据，导致更新检测出错
+    pub fn rebuild_index(&mut self) {
+        self.enabled_plugin_map = self.enabled_plugins
+            .iter()
+            .map(|plugin| (plugin.get_plugin_id(), plugin.clone()))
+            .collect();
+        
+        debug_assert!(self.index_is_consistent(), "已启用插件索引与列表不一致");
+    }
+    
+    // 索引中的每一项都来自列表，列表中的每个插件都能按 ID 查到
+    fn index_is_consistent(&self) -> bool {
+        self.enabled_plugins.iter().all(|plugin| self.enabled_plugin_map.contains_key(&plugin.get_plugin_id()))
+            && self.enabled_plugin_map.iter().all(|(id, plugin)| {
+                *id == plugin.get_plugin_id() && self.enabled_plugins.iter().any(|p| p.file == plugin.file)
+            })
     }
     
-    pub fn load_local_plugins(&mut self, drive_letter: &str) -> Result<()> {
-        let plugin_dir = format!("{}\\{}", drive_letter, self.mode.get_plugin_folder());
-        let dir_path = Path::new(&plugin_dir);
+    pub fn load_local_plugins(&mut self, drive_letter: &str) -> Result<(), PluginError> {
+        // 先清空列表，读取失败时也不会残留其他启动盘或其他模式的插件
+        self.clear_local_plugins();
+        
+        let dir_path = plugin_dir(drive_letter, self.mode);
         
         if !dir_path.exists() {
-            fs::create_dir_all(dir_path)?;
+            fs::create_dir_all(&dir_path)?;
         }
         
-        self.enabled_plugins.clear();
-        self.disabled_plugins.clear();
-        self.enabled_plugin_map.clear();
+        self.plugin_ids = self.read_plugin_ids(drive_letter);
+        
+        // 读取中途出错时已读到的插件仍然保留，索引也要与之一致
+        let result = self.read_plugin_dir(&dir_path);
+        self.rebuild_index();
+        result
+    }
+    
+    // 只统计启动盘上已启用和已禁用的插件数，不创建插件目录，用于选择启动盘前的预览。目录不存在时为 0
+    pub fn count_local_plugins(mode: PluginMode, drive_letter: &str) -> Result<(usize, usize), PluginError> {
+        let dir_path = plugin_dir(drive_letter, mode);
+        if !dir_path.exists() {
+            return Ok((0, 0));
+        }
         
+        let mut scanned = PluginManager::new(mode);
+        scanned.read_plugin_dir(&dir_path)?;
+        Ok((scanned.enabled_plugins.len(), scanned.disabled_plugins.len()))
+    }
+    
+    fn read_plugin_dir(&mut self, dir_path: &Path) -> Result<(), PluginError> {
         let mut seen_enabled = HashSet::new();
         let mut seen_disabled = HashSet::new();
         
@@ -268,25 +546,22 @@ impl PluginManager {
                     let disabled_ext = self.mode.get_disabled_extension().to_lowercase();
                     
                     let is_enabled = match self.mode {
-                        PluginMode::HotPE => {
-                            ext == "hpm" && !file_name.ends_with(".hpm.off")
-                        }
+                        PluginMode::HotPE => matches!(split_hotpe_file_name(&file_name), Some((_, true))),
                         _ => ext == enabled_ext,
                     };
                     
                     let is_disabled = match self.mode {
-                        PluginMode::HotPE => file_name.ends_with(".hpm.off"),
+                        PluginMode::HotPE => matches!(split_hotpe_file_name(&file_name), Some((_, false))),
                         _ => ext == disabled_ext,
                     };
                     
                     if is_enabled || is_disabled {
-                        if let Some(plugin) = self.parse_plugin_file(&path) {
+                        if let Some(mut plugin) = self.parse_plugin_file(&path) {
+                            plugin.id = self.plugin_ids.get(&self.plugin_base_name(&file_name)).cloned();
                             let key = plugin.get_unique_key();
                             
                             if is_enabled {
                                 if seen_enabled.insert(key) {
-                                    let plugin_id = plugin.get_plugin_id();
-                                    self.enabled_plugin_map.insert(plugin_id, plugin.clone());
                                     self.enabled_plugins.push(plugin);
                                 }
                             } else {
@@ -305,154 +580,71 @@ impl PluginManager {
     
     fn parse_plugin_file(&self, path: &Path) -> Option<Plugin> {
         let file_name = path.file_name()?.to_string_lossy().to_string();
+        let fields = plugin_naming::decode(&file_name, self.mode)?;
         
-        match self.mode {
-            PluginMode::CloudPE => {
-                let parts: Vec<&str> = file_name.split('_').collect();
-                
-                if parts.len() >= 4 {
-                    let name = parts[0].to_string();
-                    let version = parts[1].to_string();
-                    let author = parts[2].to_string();
-                    
-                    let describe_with_ext = parts[3..].join("_");
-                    let describe = describe_with_ext
-                        .strip_suffix(".ce")
-                        .or_else(|| describe_with_ext.strip_suffix(".CBK"))
-                        .unwrap_or(&describe_with_ext)
-                        .to_string();
-                    
-                    let metadata = fs::metadata(path).ok()?;
-                    let size = format!("{:.2} MB", metadata.len() as f64 / 1024.0 / 1024.0);
-                    
-                    Some(Plugin {
-                        name,
-                        size,
-                        version,
-                        author,
-                        describe,
-                        file: file_name,
-                        link: String::new(),
-                    })
-                } else {
-                    None
-                }
-            }
-            PluginMode::HotPE => {
-                let base_name = file_name
-                    .strip_suffix(".HPM")
-                    .or_else(|| file_name.strip_suffix(".hpm.off"))
-                    .unwrap_or(&file_name);
-                    
-                let parts: Vec<&str> = base_name.split('_').collect();
-                
-                if parts.len() >= 3 {
-                    let name = parts[0].to_string();
-                    let author = parts[1].to_string();
-                    let version = parts[2].to_string();
-                    let describe = if parts.len() > 3 {
-                        parts[3..].join("_")
-                    } else {
-                        String::new()
-                    };
-                    
-                    let metadata = fs::metadata(path).ok()?;
-                    let size = format!("{:.2} MB", metadata.len() as f64 / 1024.0 / 1024.0);
-                    
-                    Some(Plugin {
-                        name,
-                        size,
-                        version,
-                        author,
-                        describe,
-                        file: file_name,
-                        link: String::new(),
-                    })
-                } else {
-                    None
-                }
-            }
-            PluginMode::Edgeless => {
-                let base_name = file_name
-                    .strip_suffix(".7z")
-                    .or_else(|| file_name.strip_suffix(".7zf"))
-                    .unwrap_or(&file_name);
-                    
-                let parts: Vec<&str> = base_name.split('_').collect();
-                
-                if parts.len() >= 3 {
-                    let name = parts[0].to_string();
-                    let version = parts[1].to_string();
-                    let author = parts[2..].join("_");
-                    
-                    let metadata = fs::metadata(path).ok()?;
-                    let size = format!("{:.2} MB", metadata.len() as f64 / 1024.0 / 1024.0);
-                    
-                    Some(Plugin {
-                        name,
-                        size,
-                        version,
-                        author,
-                        describe: String::new(),
-                        file: file_name,
-                        link: String::new(),
-                    })
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        }
+        let metadata = fs::metadata(path).ok()?;
+        let size = format_file_size(metadata.len() as i64);
+        
+        Some(Plugin {
+            id: None,
+            name: fields.name,
+            size,
+            version: fields.version,
+            author: fields.author,
+            describe: fields.describe,
+            file: file_name,
+            link: String::new(),
+            dependencies: Vec::new(),
+            issue_url: None,
+            homepage: None,
+            platform: None,
+            sha256: None,
+            category: String::new(),
+        })
     }
     
-    pub fn enable_plugin(&mut self, drive_letter: &str, file_name: &str) -> Result<()> {
-        let plugin_dir = format!("{}\\{}", drive_letter, self.mode.get_plugin_folder());
-        let file_path = Path::new(&plugin_dir).join(file_name);
+    pub fn enable_plugin(&mut self, drive_letter: &str, file_name: &str) -> Result<(), PluginError> {
+        self.toggle_plugin(drive_letter, file_name, true)
+    }
+    
+    pub fn disable_plugin(&mut self, drive_letter: &str, file_name: &str) -> Result<(), PluginError> {
+        self.toggle_plugin(drive_letter, file_name, false)
+    }
+    
+    // 通过重命名启用或禁用插件。目标文件已存在时不覆盖；
+    // 重命名后确认新文件存在、旧文件已消失，U 盘被拔出或被占用时可能只完成一半
+    fn toggle_plugin(&mut self, drive_letter: &str, file_name: &str, enable: bool) -> Result<(), PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let file_path = plugin_dir(drive_letter, self.mode).join(file_name);
         
         if !file_path.exists() {
-            anyhow::bail!("文件不存在");
+            return Err(PluginError::NotFound);
         }
         
-        let new_file_name = match self.mode {
-            PluginMode::CloudPE => file_name.replace(".CBK", ".ce"),
-            PluginMode::HotPE => file_name.replace(".hpm.off", ".HPM"),
-            PluginMode::Edgeless => file_name.replace(".7zf", ".7z"),
-            _ => return Ok(()),
+        let Some(new_file_name) = plugin_naming::toggled_file_name(file_name, self.mode, enable) else {
+            return Ok(());
         };
         
-        let new_file_path = Path::new(&plugin_dir).join(&new_file_name);
+        let new_file_path = plugin_dir(drive_letter, self.mode).join(&new_file_name);
         
-        fs::rename(&file_path, &new_file_path)?;
-        self.load_local_plugins(drive_letter)?;
-        
-        Ok(())
-    }
-    
-    pub fn disable_plugin(&mut self, drive_letter: &str, file_name: &str) -> Result<()> {
-        let plugin_dir = format!("{}\\{}", drive_letter, self.mode.get_plugin_folder());
-        let file_path = Path::new(&plugin_dir).join(file_name);
-        
-        if !file_path.exists() {
-            anyhow::bail!("文件不存在");
+        // Windows 上 fs::rename 会直接替换已存在的文件。文件名只有大小写不同时指向同一个文件，不算冲突
+        if new_file_path.exists() && !new_file_name.eq_ignore_ascii_case(file_name) {
+            return Err(PluginError::AlreadyExists(new_file_name));
         }
         
-        let new_file_name = match self.mode {
-            PluginMode::CloudPE => file_name.replace(".ce", ".CBK"),
-            PluginMode::HotPE => {
-                if file_name.ends_with(".HPM") {
-                    file_name.replace(".HPM", ".hpm.off")
-                } else {
-                    format!("{}.off", file_name)
-                }
-            }
-            PluginMode::Edgeless => file_name.replace(".7z", ".7zf"),
-            _ => return Ok(()),
-        };
-        
-        let new_file_path = Path::new(&plugin_dir).join(&new_file_name);
+        let result = fs::rename(&file_path, &new_file_path);
         
-        fs::rename(&file_path, &new_file_path)?;
+        // 无论成功与否都重新读取，界面显示的是启动盘上的实际状态
         self.load_local_plugins(drive_letter)?;
+        result?;
+        
+        if !new_file_path.exists() {
+            return Err(PluginError::RenameIncomplete(format!("重命名后找不到 {}", new_file_name)));
+        }
+        if file_path.exists() && !new_file_name.eq_ignore_ascii_case(file_name) {
+            return Err(PluginError::RenameIncomplete(format!("重命名后 {} 仍然存在", file_name)));
+        }
         
         Ok(())
     }
@@ -466,7 +658,21 @@ impl PluginManager {
     }
     
     pub fn get_enabled_plugin_by_id(&self, plugin_id: &str) -> Option<&Plugin> {
-        self.enabled_plugin_map.get(plugin_id)
+        if let Some(plugin) = self.enabled_plugin_map.get(plugin_id) {
+            return Some(plugin);
+        }
+        
+        // 依赖列表仍使用 名称_作者，按旧格式匹配已记录稳定 ID 的插件
+        if let Some(plugin) = self.enabled_plugins.iter().find(|plugin| plugin.get_legacy_id() == plugin_id) {
+            return Some(plugin);
+        }
+        
+        // 在引入稳定 ID 之前安装的插件没有记录 ID，按市场中对应插件的 名称_作者 查找
+        self.categories
+            .iter()
+            .flat_map(|category| category.list.iter())
+            .find(|plugin| plugin.get_plugin_id() == plugin_id)
+            .and_then(|plugin| self.enabled_plugin_map.get(&plugin.get_legacy_id()))
     }
     
     pub fn compare_versions(&self, version1: &str, version2: &str) -> std::cmp::Ordering {
@@ -488,12 +694,13 @@ impl PluginManager {
         std::cmp::Ordering::Equal
     }
     
-    pub fn delete_plugin_file(&self, drive_letter: &str, file_name: &str) -> Result<()> {
-        let plugin_dir = format!("{}\\{}", drive_letter, self.mode.get_plugin_folder());
-        let file_path = Path::new(&plugin_dir).join(file_name);
+    pub fn delete_plugin_file(&self, drive_letter: &str, file_name: &str) -> Result<(), PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let file_path = plugin_dir(drive_letter, self.mode).join(file_name);
         
         if !file_path.exists() {
-            anyhow::bail!("文件不存在");
+            return Err(PluginError::NotFound);
         }
         
         fs::remove_file(&file_path)?;
@@ -501,10 +708,345 @@ impl PluginManager {
         Ok(())
     }
     
+    pub fn resolve_dependencies(&self, plugin: &Plugin) -> DependencyResolution {
+        let mut resolution = DependencyResolution::default();
+        let mut visiting = HashSet::new();
+        let mut visited = HashSet::new();
+        
+        visiting.insert(plugin.get_plugin_id());
+        for dependency_id in &plugin.dependencies {
+            self.visit_dependency(dependency_id, &mut visiting, &mut visited, &mut resolution);
+        }
+        
+        resolution
+    }
+    
+    fn visit_dependency(
+        &self,
+        plugin_id: &str,
+        visiting: &mut HashSet<String>,
+        visited: &mut HashSet<String>,
+        resolution: &mut DependencyResolution,
+    ) {
+        if visited.contains(plugin_id) {
+            return;
+        }
+        
+        if visiting.contains(plugin_id) {
+            if !resolution.cycles.iter().any(|id| id == plugin_id) {
+                resolution.cycles.push(plugin_id.to_string());
+            }
+            return;
+        }
+        
+        let Some(dependency) = self.find_market_plugin_by_id(plugin_id) else {
+            if !resolution.missing.iter().any(|id| id == plugin_id) {
+                resolution.missing.push(plugin_id.to_string());
+            }
+            return;
+        };
+        
+        visiting.insert(plugin_id.to_string());
+        for child_id in &dependency.dependencies {
+            self.visit_dependency(child_id, visiting, visited, resolution);
+        }
+        visiting.remove(plugin_id);
+        visited.insert(plugin_id.to_string());
+        
+        if self.get_enabled_plugin_by_id(plugin_id).is_none() {
+            resolution.to_install.push(dependency);
+        }
+    }
+    
+    pub fn backup_to_drive(&self, drive_letter: &str) -> Result<PathBuf, PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let backup_path = drive_path(drive_letter, self.mode.get_backup_file());
+        
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let backup = PluginBackup {
+            created: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            enabled: self.enabled_plugins.clone(),
+            disabled: self.disabled_plugins.clone(),
+        };
+        
+        fs::write(&backup_path, serde_json::to_string_pretty(&backup)?)?;
+        
+        Ok(backup_path)
+    }
+    
+    pub fn restore_from_drive(&mut self, drive_letter: &str) -> Result<RestoreReport, PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let backup_path = drive_path(drive_letter, self.mode.get_backup_file());
+        
+        if !backup_path.exists() {
+            return Err(PluginError::NotFound);
+        }
+        
+        let backup: PluginBackup = serde_json::from_str(&fs::read_to_string(&backup_path)?)?;
+        self.load_local_plugins(drive_letter)?;
+        
+        let mut report = RestoreReport::default();
+        let wanted = backup.enabled.iter().map(|p| (p, true))
+            .chain(backup.disabled.iter().map(|p| (p, false)));
+        
+        for (plugin, should_enable) in wanted {
+            let plugin_id = plugin.get_plugin_id();
+            let enabled_file = self.enabled_plugins.iter()
+                .find(|p| p.get_plugin_id() == plugin_id)
+                .map(|p| p.file.clone());
+            let disabled_file = self.disabled_plugins.iter()
+                .find(|p| p.get_plugin_id() == plugin_id)
+                .map(|p| p.file.clone());
+            
+            match (enabled_file, disabled_file) {
+                (Some(file), _) if !should_enable => {
+                    self.disable_plugin(drive_letter, &file)?;
+                    report.changed += 1;
+                }
+                (None, Some(file)) if should_enable => {
+                    self.enable_plugin(drive_letter, &file)?;
+                    report.changed += 1;
+                }
+                (None, None) => match self.find_market_plugin_by_id(&plugin_id) {
+                    Some(market_plugin) => report.missing.push(market_plugin),
+                    None => report.unavailable.push(plugin.name.clone()),
+                },
+                _ => {}
+            }
+        }
+        
+        Ok(report)
+    }
+    
+    // 扫描插件目录中异常中断留下的残留：无法续传的 .part 文件、失去对应 .part 的续传记录、写入检测文件和空的插件文件。
+    // 带有 .part.json 续传记录的 .part 下次安装时会从中断处继续，有内容的禁用插件（.CBK/.7zf/.hpm.off）是正常文件，都不会被列入
+    pub fn find_residue_files(&self, drive_letter: &str) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(plugin_dir(drive_letter, self.mode)) else {
+            return Vec::new();
+        };
+        
+        let enabled_ext = self.mode.get_enabled_extension().to_lowercase();
+        let disabled_ext = self.mode.get_disabled_extension().to_lowercase();
+        let mut residue = Vec::new();
+        
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let ext = path.extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            
+            let is_plugin_file = match self.mode {
+                PluginMode::HotPE => split_hotpe_file_name(&file_name).is_some(),
+                _ => ext == enabled_ext || ext == disabled_ext,
+            };
+            let is_empty = entry.metadata().map(|metadata| metadata.len() == 0).unwrap_or(false);
+            
+            let lower_name = file_name.to_lowercase();
+            let is_partial = if ext == "part" {
+                !path.with_file_name(format!("{}.json", file_name)).exists()
+            } else if lower_name.ends_with(".part.json") {
+                !path.with_file_name(&file_name[..file_name.len() - ".json".len()]).exists()
+            } else {
+                false
+            };
+            if is_partial || file_name.starts_with(".cloudpe_write_test_") || (is_plugin_file && is_empty) {
+                residue.push(path);
+            }
+        }
+        
+        residue.sort();
+        residue
+    }
+    
+    // 检查插件目录中每个文件的文件名，能对应到市场中同一版本的插件但文件名不符合命名规则时给出新文件名。
+    // 优先按记录的插件 ID 对应，否则按名称模糊匹配，且文件名中必须含有市场插件的版本号，避免把旧版本改成新版本的名称
+    pub fn find_name_fixes(&self, drive_letter: &str) -> Vec<NameFix> {
+        let Ok(entries) = fs::read_dir(plugin_dir(drive_letter, self.mode)) else {
+            return Vec::new();
+        };
+        
+        let plugin_ids = self.read_plugin_ids(drive_letter);
+        let mut fixes = Vec::new();
+        
+        for entry in entries.flatten() {
+            if !entry.path().is_file() {
+                continue;
+            }
+            
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let is_enabled = plugin_naming::toggled_file_name(&file_name, self.mode, false).is_some();
+            let is_disabled = plugin_naming::toggled_file_name(&file_name, self.mode, true).is_some();
+            if !is_enabled && !is_disabled {
+                continue;
+            }
+            
+            let base_name = self.plugin_base_name(&file_name);
+            let market_plugin = plugin_ids
+                .get(&base_name)
+                .and_then(|id| self.find_market_plugin_by_id(id))
+                .or_else(|| self.match_market_plugin_by_name(&file_name, &base_name));
+            let Some(market_plugin) = market_plugin else {
+                continue;
+            };
+            
+            let enabled_name = format!(
+                "{}.{}",
+                plugin_naming::encode(&market_plugin, self.mode),
+                self.mode.get_enabled_extension()
+            );
+            let new_file = if is_enabled {
+                Some(enabled_name)
+            } else {
+                plugin_naming::toggled_file_name(&enabled_name, self.mode, false)
+            };
+            
+            if let Some(new_file) = new_file {
+                if new_file != file_name {
+                    fixes.push(NameFix {
+                        file: file_name,
+                        new_file,
+                        market_plugin,
+                    });
+                }
+            }
+        }
+        
+        fixes.sort_by(|a, b| a.file.cmp(&b.file));
+        fixes
+    }
+    
+    // 名称只比较字母、数字和汉字，忽略大小写、空格和符号。版本必须与文件名中解析出的版本字段一致，
+    // 避免 1.2 误配到 1.2.3 这类文件。有多个候选时取名称最长（最具体）的一个
+    fn match_market_plugin_by_name(&self, file_name: &str, base_name: &str) -> Option<Plugin> {
+        let simplify = |text: &str| -> String {
+            text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+        };
+        
+        let fields = plugin_naming::decode(file_name, self.mode)?;
+        let local_name = simplify(&fields.name);
+        let simple_base = simplify(base_name);
+        
+        self.categories
+            .iter()
+            .flat_map(|category| &category.list)
+            .filter(|plugin| {
+                let name = simplify(&plugin.name);
+                let version = plugin.version.trim();
+                !name.is_empty()
+                    && !version.is_empty()
+                    && (local_name == name || simple_base.starts_with(&name))
+                    && fields.version.eq_ignore_ascii_case(version)
+            })
+            .max_by_key(|plugin| simplify(&plugin.name).len())
+            .cloned()
+    }
+    
+    // 按 find_name_fixes 的结果重命名，已存在同名文件的跳过。插件 ID 记录随文件名一起更新。
+    // 返回成功重命名的数量和失败的说明
+    pub fn apply_name_fixes(&mut self, drive_letter: &str, fixes: &[NameFix]) -> Result<(usize, Vec<String>), PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let plugin_dir = plugin_dir(drive_letter, self.mode);
+        let mut plugin_ids = self.read_plugin_ids(drive_letter);
+        let mut renamed = 0;
+        let mut failures = Vec::new();
+        
+        for fix in fixes {
+            let new_path = plugin_dir.join(&fix.new_file);
+            if new_path.exists() && !fix.new_file.eq_ignore_ascii_case(&fix.file) {
+                failures.push(PluginError::AlreadyExists(fix.new_file.clone()).to_string());
+                continue;
+            }
+            
+            match fs::rename(plugin_dir.join(&fix.file), &new_path) {
+                Ok(()) => {
+                    plugin_ids.remove(&self.plugin_base_name(&fix.file));
+                    plugin_ids.insert(self.plugin_base_name(&fix.new_file), fix.market_plugin.get_plugin_id());
+                    renamed += 1;
+                }
+                Err(e) => failures.push(format!("{}: {}", fix.file, e)),
+            }
+        }
+        
+        if renamed > 0 {
+            self.write_plugin_ids(drive_letter, &plugin_ids)?;
+        }
+        self.load_local_plugins(drive_letter)?;
+        
+        Ok((renamed, failures))
+    }
+    
+    // 记录安装到启动盘的插件的稳定 ID，下次读取本地插件时使用
+    pub fn record_plugin_id(&mut self, drive_letter: &str, file_name: &str, plugin_id: &str) -> Result<(), PluginError> {
+        crate::drive_lock::acquire_for_write(drive_letter, self.mode).map_err(PluginError::DriveLocked)?;
+        
+        let mut plugin_ids = self.read_plugin_ids(drive_letter);
+        plugin_ids.insert(self.plugin_base_name(file_name), plugin_id.to_string());
+        self.write_plugin_ids(drive_letter, &plugin_ids)?;
+        
+        self.plugin_ids = plugin_ids;
+        
+        // 已读取的插件同步使用新记录的 ID，并更新索引
+        let base_name = self.plugin_base_name(file_name);
+        let matching: Vec<usize> = self.enabled_plugins
+            .iter()
+            .enumerate()
+            .filter(|(_, plugin)| self.plugin_base_name(&plugin.file) == base_name)
+            .map(|(index, _)| index)
+            .collect();
+        for index in matching {
+            self.enabled_plugins[index].id = Some(plugin_id.to_string());
+        }
+        self.rebuild_index();
+        
+        Ok(())
+    }
+    
+    fn read_plugin_ids(&self, drive_letter: &str) -> HashMap<String, String> {
+        let index_path = drive_path(drive_letter, self.mode.get_plugin_id_file());
+        fs::read_to_string(index_path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    
+    fn write_plugin_ids(&self, drive_letter: &str, plugin_ids: &HashMap<String, String>) -> Result<(), PluginError> {
+        let index_path = drive_path(drive_letter, self.mode.get_plugin_id_file());
+        if let Some(parent) = index_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&index_path, serde_json::to_string_pretty(plugin_ids)?)?;
+        Ok(())
+    }
+    
+    // 去掉启用/禁用扩展名，切换状态后仍能对应到同一条记录
+    fn plugin_base_name(&self, file_name: &str) -> String {
+        match self.mode {
+            PluginMode::HotPE => split_hotpe_file_name(file_name)
+                .map(|(base, _)| base)
+                .unwrap_or(file_name)
+                .to_string(),
+            _ => Path::new(file_name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_name.to_string()),
+        }
+    }
+    
     pub fn find_market_plugin_by_id(&self, plugin_id: &str) -> Option<Plugin> {
         for category in &self.categories {
             for plugin in &category.list {
-                if plugin.get_plugin_id() == plugin_id {
+                if plugin.get_plugin_id() == plugin_id || plugin.get_legacy_id() == plugin_id {
                     return Some(plugin.clone());
                 }
             }
@@ -565,7 +1107,7 @@ fn parse_version(version: &str) -> Vec<VersionPart> {
     parts
 }
 
-fn format_file_size(size: i64) -> String {
+pub fn format_file_size(size: i64) -> String {
     if size < 1024 {
         format!("{} B", size)
     } else if size < 1024 * 1024 {
@@ -576,3 +1118,77 @@ fn format_file_size(size: i64) -> String {
         format!("{:.2} GB", size as f64 / 1024.0 / 1024.0 / 1024.0)
     }
 }
+
+// 下载速度按大小选择单位，慢速网络下显示 "20.0 KB/s" 而不是 "0.02 MB/s"
+pub fn format_speed(bytes_per_second: f64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    
+    if bytes_per_second < KB {
+        format!("{:.0} B/s", bytes_per_second)
+    } else if bytes_per_second < MB {
+        format!("{:.1} KB/s", bytes_per_second / KB)
+    } else {
+        format!("{:.2} MB/s", bytes_per_second / MB)
+    }
+}
+
+// 将 "4.2 MB"、"512KB"、"1048576" 等大小文本解析为字节数
+pub fn parse_size_to_bytes(size: &str) -> Option<u64> {
+    let size = size.trim().to_lowercase();
+    let number_end = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    
+    let number: f64 = size[..number_end].parse().ok()?;
+    let multiplier = match size[number_end..].trim() {
+        "" | "b" | "byte" | "bytes" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    
+    Some((number * multiplier).round() as u64)
+}
+
+// 统一大小的显示格式。无法解析的原样保留，只有为空时才显示为未知大小
+fn normalize_size_text(size: &str) -> String {
+    match parse_size_to_bytes(size) {
+        Some(bytes) => format_file_size(bytes as i64),
+        None if size.trim().is_empty() => "未知大小".to_string(),
+        None => size.trim().to_string(),
+    }
+}
+
+// 服务器为分类指定了顺序时按其排序，未指定的分类排在后面并保持原有顺序；
+// "推荐"始终排在最前面。所有分类都未指定顺序时保持接口返回的顺序
+fn sort_categories(categories: &mut [PluginCategory]) {
+    if categories.iter().all(|category| category.order.is_none()) {
+        return;
+    }
+    
+    categories.sort_by_key(|category| {
+        (category.class != "推荐", category.order.unwrap_or(i32::MAX))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn relative_links_resolve_against_list_url() {
+        let page = reqwest::Url::parse("https://example.com/api/plugins?page=2").unwrap();
+        assert_eq!(resolve_plugin_link(&page, "/files/a.ce"), "https://example.com/files/a.ce");
+        assert_eq!(resolve_plugin_link(&page, "files/a.ce"), "https://example.com/api/files/a.ce");
+        assert_eq!(resolve_plugin_link(&page, " //cdn.example.com/a.ce "), "https://cdn.example.com/a.ce");
+    }
+    
+    #[test]
+    fn absolute_and_empty_links_are_kept() {
+        let page = reqwest::Url::parse("https://example.com/api/plugins").unwrap();
+        assert_eq!(resolve_plugin_link(&page, "http://mirror.example.org/a.ce"), "http://mirror.example.org/a.ce");
+        assert_eq!(resolve_plugin_link(&page, ""), "");
+    }
+}
@@ -0,0 +1,394 @@
+use crate::config::{AppConfig, CompatibilityCheck};
+use crate::downloader::Downloader;
+use crate::installer::{deploy_plugin, DeployOptions};
+use crate::plugin_naming;
+use crate::mode::PluginMode;
+use crate::plugins::PluginManager;
+use crate::utils::{is_system_drive, normalize_drive_letter, BootDriveManager};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 请求头和请求体的最大长度，超出后直接拒绝
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+// 客户端迟迟不发完请求时断开连接，避免连接一直占用
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TaskStatus {
+    Downloading,
+    Done,
+    Failed,
+}
+
+struct ServerTask {
+    plugin_name: String,
+    downloader: Arc<Downloader>,
+    status: TaskStatus,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TaskInfo {
+    id: String,
+    plugin_name: String,
+    status: TaskStatus,
+    current: u64,
+    total: u64,
+    resolved_url: Option<String>,
+    supports_ranges: bool,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InstallRequest {
+    id: String,
+    drive: String,
+}
+
+struct ServerState {
+    mode: PluginMode,
+    port: u16,
+    token: String,
+    config: AppConfig,
+    // 只用于插件市场列表
+    plugin_manager: Arc<RwLock<PluginManager>>,
+    // 按启动盘区分的插件管理器，同时安装到不同启动盘时不会互相覆盖本地插件列表
+    drive_managers: Mutex<HashMap<String, Arc<RwLock<PluginManager>>>>,
+    tasks: Arc<RwLock<HashMap<String, ServerTask>>>,
+    next_task_id: AtomicUsize,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    // 请求头名称统一为小写
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+struct Response {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl Response {
+    fn ok(body: serde_json::Value) -> Self {
+        Self { status: 200, body }
+    }
+    
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message.into() }),
+        }
+    }
+}
+
+// 在本机回环地址上提供 JSON 接口，供外部脚本控制插件安装：
+//   GET  /categories   获取插件分类列表
+//   POST /install      按插件 ID 安装到指定启动盘，请求体 {"id": "...", "drive": "D:"}
+//   GET  /tasks        获取全部任务进度
+//   GET  /tasks/<id>   获取单个任务进度
+// 每个请求都需带上 "Authorization: Bearer <令牌>"，令牌在启动时生成并写入数据目录下的 server_token.txt。
+// 本机网页也能访问回环地址，因此同时拒绝 Host 或 Origin 不是本机的请求
+pub async fn serve(port: u16, mode: PluginMode) -> std::io::Result<()> {
+    let plugin_manager = Arc::new(RwLock::new(PluginManager::new(mode)));
+    
+    let config = AppConfig::load().unwrap_or_default();
+    
+    let categories = PluginManager::fetch_plugins_async(mode, config.channel, Arc::new(AtomicUsize::new(0)), Arc::default())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    plugin_manager.write().categories = categories;
+    
+    let token = generate_token()?;
+    let token_path = crate::config::save_server_token(&token)
+        .map_err(|e| std::io::Error::other(format!("无法保存访问令牌: {}", e)))?;
+    crate::app_log::log(format!("控制接口访问令牌已写入 {}", token_path.display()));
+    
+    let state = Arc::new(ServerState {
+        mode,
+        port,
+        token,
+        config,
+        plugin_manager,
+        drive_managers: Mutex::new(HashMap::new()),
+        tasks: Arc::new(RwLock::new(HashMap::new())),
+        next_task_id: AtomicUsize::new(1),
+    });
+    
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = state.clone();
+        
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, state).await;
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<ServerState>) -> std::io::Result<()> {
+    let Ok(request) = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await else {
+        return Ok(());
+    };
+    
+    let response = match request? {
+        Some(request) => match authorize(&state, &request) {
+            // 安装前需要扫描启动盘、读取插件目录，放到阻塞线程中处理
+            Ok(()) => tokio::task::spawn_blocking(move || route(&state, &request.method, &request.path, &request.body))
+                .await
+                .unwrap_or_else(|_| Response::error(500, "处理请求时出错")),
+            Err(response) => response,
+        },
+        None => Response::error(400, "无效的请求"),
+    };
+    
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        _ => "Error",
+    };
+    
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        body.len()
+    );
+    
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// 读取一个 HTTP 请求
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buffer.len() > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+        
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+    
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Ok(None),
+    };
+    
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    
+    let content_length = headers.get("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    
+    if content_length > MAX_REQUEST_SIZE {
+        return Ok(None);
+    }
+    
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    
+    let body = String::from_utf8_lossy(&buffer[header_end..header_end + content_length]).to_string();
+    Ok(Some(Request { method, path, headers, body }))
+}
+
+// 32 字节随机令牌的十六进制形式，取自操作系统的安全随机数生成器
+fn generate_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| std::io::Error::other(format!("无法生成访问令牌: {}", e)))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn drive_manager(state: &ServerState, drive_letter: &str) -> Arc<RwLock<PluginManager>> {
+    state.drive_managers.lock()
+        .entry(drive_letter.to_string())
+        .or_insert_with(|| {
+            // 按市场信息识别启动盘上没有记录 ID 的旧插件
+            let mut manager = PluginManager::new(state.mode);
+            manager.categories = state.plugin_manager.read().get_categories().clone();
+            Arc::new(RwLock::new(manager))
+        })
+        .clone()
+}
+
+// Host 只能是本机地址加监听端口，防止经 DNS 重绑定的网页访问接口；
+// 带有 Origin 的请求来自浏览器，只接受本机页面
+fn authorize(state: &ServerState, request: &Request) -> Result<(), Response> {
+    let local_hosts = [
+        format!("127.0.0.1:{}", state.port),
+        format!("localhost:{}", state.port),
+    ];
+    
+    let host_allowed = request.headers.get("host")
+        .is_some_and(|host| local_hosts.iter().any(|allowed| host.eq_ignore_ascii_case(allowed)));
+    if !host_allowed {
+        return Err(Response::error(403, "只接受发往本机地址的请求"));
+    }
+    
+    if let Some(origin) = request.headers.get("origin") {
+        let origin_allowed = local_hosts.iter()
+            .any(|allowed| origin.eq_ignore_ascii_case(&format!("http://{}", allowed)));
+        if !origin_allowed {
+            return Err(Response::error(403, "不接受来自其他网页的请求"));
+        }
+    }
+    
+    let token = request.headers.get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    if token != Some(state.token.as_str()) {
+        return Err(Response::error(401, "缺少或错误的访问令牌"));
+    }
+    
+    Ok(())
+}
+
+fn route(state: &Arc<ServerState>, method: &str, path: &str, body: &str) -> Response {
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    
+    match (method, path) {
+        ("GET", "/categories") => {
+            let categories = state.plugin_manager.read().get_categories().clone();
+            Response::ok(serde_json::json!(categories))
+        }
+        ("POST", "/install") => match serde_json::from_str::<InstallRequest>(body) {
+            Ok(request) => start_install(state, request),
+            Err(e) => Response::error(400, format!("请求体格式错误: {}", e)),
+        },
+        ("GET", "/tasks") => {
+            let tasks = state.tasks.read();
+            let mut list: Vec<TaskInfo> = tasks.iter().map(|(id, task)| task_info(id, task)).collect();
+            list.sort_by_key(|info| info.id.parse::<usize>().unwrap_or(0));
+            Response::ok(serde_json::json!(list))
+        }
+        ("GET", path) if path.starts_with("/tasks/") => {
+            let id = &path["/tasks/".len()..];
+            match state.tasks.read().get(id) {
+                Some(task) => Response::ok(serde_json::json!(task_info(id, task))),
+                None => Response::error(404, "任务不存在"),
+            }
+        }
+        (_, "/categories") | (_, "/install") | (_, "/tasks") => Response::error(405, "不支持的请求方法"),
+        _ => Response::error(404, "接口不存在"),
+    }
+}
+
+fn start_install(state: &Arc<ServerState>, request: InstallRequest) -> Response {
+    let plugin = match state.plugin_manager.read().find_market_plugin_by_id(&request.id) {
+        Some(plugin) => plugin,
+        None => return Response::error(404, "插件不存在"),
+    };
+    
+    let Some(drive_letter) = normalize_drive_letter(&request.drive) else {
+        return Response::error(400, "未指定有效的启动盘");
+    };
+    
+    // 只允许安装到扫描识别出的启动盘，系统盘即使被识别也不接受
+    let is_boot_drive = BootDriveManager::new(state.mode)
+        .get_all_drives()
+        .iter()
+        .any(|drive| drive.letter.eq_ignore_ascii_case(&drive_letter));
+    if !is_boot_drive {
+        return Response::error(400, format!("{} 不是已识别的启动盘", drive_letter));
+    }
+    if is_system_drive(&drive_letter) {
+        return Response::error(400, "不能通过控制接口安装到系统盘");
+    }
+    
+    // 确保能读到启动盘上已有的插件，以便替换旧版本
+    let plugin_manager = drive_manager(state, &drive_letter);
+    let _ = plugin_manager.write().load_local_plugins(&drive_letter);
+    let replace_existing = plugin_manager.read()
+        .get_enabled_plugin_by_id(&plugin.get_plugin_id())
+        .is_some();
+    
+    let task_id = state.next_task_id.fetch_add(1, Ordering::SeqCst).to_string();
+    let downloader = Arc::new(Downloader::new(state.config.download_threads, state.config.download_retries));
+    
+    state.tasks.write().insert(task_id.clone(), ServerTask {
+        plugin_name: plugin.name.clone(),
+        downloader: downloader.clone(),
+        status: TaskStatus::Downloading,
+        error: None,
+    });
+    
+    let options = DeployOptions {
+        replace_existing,
+        safe_install: state.config.safe_install,
+        block_incompatible: state.config.compatibility_check == CompatibilityCheck::Block,
+    };
+    let filename = plugin_naming::encode(&plugin, state.mode);
+    let mode = state.mode;
+    let tasks = state.tasks.clone();
+    let id = task_id.clone();
+    
+    tokio::spawn(async move {
+        let result = deploy_plugin(&downloader, &plugin_manager, mode, &drive_letter, &plugin, &filename, options).await;
+        
+        if let Some(task) = tasks.write().get_mut(&id) {
+            match result {
+                Ok(()) => task.status = TaskStatus::Done,
+                Err(e) => {
+                    task.status = TaskStatus::Failed;
+                    task.error = Some(e);
+                }
+            }
+        }
+    });
+    
+    Response::ok(serde_json::json!({ "task_id": task_id }))
+}
+
+fn task_info(id: &str, task: &ServerTask) -> TaskInfo {
+    let progress = task.downloader.get_progress();
+    
+    TaskInfo {
+        id: id.to_string(),
+        plugin_name: task.plugin_name.clone(),
+        status: task.status.clone(),
+        current: progress.current,
+        total: progress.total,
+        resolved_url: progress.resolved_url,
+        supports_ranges: progress.supports_ranges,
+        error: task.error.clone(),
+    }
+}
@@ -1,52 +1,56 @@
 use eframe::egui;
 use crate::mode::PluginMode;
+use crate::network::{ConnectionFailure, CONNECTION_TEST_ATTEMPTS};
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
-use std::process::Command;
+use crate::loading::LoadingScreen;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct SourceStatus {
     available: Option<bool>,
     checking: bool,
+    // 当前是第几次尝试，0 表示尚未开始
+    attempt: u32,
+    failure: Option<ConnectionFailure>,
 }
 
 pub struct SourceSelector {
     sources: Arc<RwLock<HashMap<PluginMode, SourceStatus>>>,
     is_checking: bool,
-    runtime: tokio::runtime::Runtime,
+    // 选定插件源后交给加载界面使用
+    runtime: Option<tokio::runtime::Runtime>,
+    // 选定插件源后在当前窗口中加载，不再重新启动程序
+    loading: Option<LoadingScreen>,
 }
 
 impl SourceSelector {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> std::io::Result<Self> {
         let mut sources = HashMap::new();
-        sources.insert(PluginMode::CloudPE, SourceStatus { available: None, checking: false });
-        sources.insert(PluginMode::HotPE, SourceStatus { available: None, checking: false });
-        sources.insert(PluginMode::Edgeless, SourceStatus { available: None, checking: false });
+        sources.insert(PluginMode::CloudPE, SourceStatus::default());
+        sources.insert(PluginMode::HotPE, SourceStatus::default());
+        sources.insert(PluginMode::Edgeless, SourceStatus::default());
         
-        Self {
+        Ok(Self {
             sources: Arc::new(RwLock::new(sources)),
             is_checking: false,
-            runtime: tokio::runtime::Runtime::new().unwrap(),
-        }
+            runtime: Some(tokio::runtime::Runtime::new()?),
+            loading: None,
+        })
     }
     
-    fn launch_mode(&self, mode: PluginMode) {
-        let exe = std::env::current_exe().unwrap();
-        let arg = match mode {
-            PluginMode::CloudPE => "",
-            PluginMode::HotPE => "--hpm",
-            PluginMode::Edgeless => "--edgeless",
-            _ => return,
+    // 在当前窗口中进入选定的插件源，窗口恢复为插件市场的大小
+    fn launch_mode(&mut self, ctx: &egui::Context, mode: PluginMode) {
+        let Some(runtime) = self.runtime.take() else {
+            return;
         };
         
-        if arg.is_empty() {
-            Command::new(exe).spawn().ok();
-        } else {
-            Command::new(exe).arg(arg).spawn().ok();
-        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(mode.get_title().to_string()));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Resizable(true));
+        ctx.send_viewport_cmd(egui::ViewportCommand::MinInnerSize(egui::vec2(800.0, 600.0)));
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(1024.0, 630.0)));
         
-        std::process::exit(0);
+        self.loading = Some(LoadingScreen::new(ctx, runtime, mode));
     }
     
     fn check_availability(&mut self) {
@@ -54,89 +58,59 @@ impl SourceSelector {
             return;
         }
         
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        
         self.is_checking = true;
         
         // 重置状态
         {
             let mut sources = self.sources.write();
             for (_, status) in sources.iter_mut() {
-                status.checking = true;
-                status.available = None;
+                *status = SourceStatus {
+                    checking: true,
+                    ..SourceStatus::default()
+                };
             }
         }
         
-        // 检查Cloud-PE
-        let sources_clone = self.sources.clone();
-        self.runtime.spawn(async move {
-            let available = check_source_async(PluginMode::CloudPE).await;
-            let mut sources = sources_clone.write();
-            if let Some(status) = sources.get_mut(&PluginMode::CloudPE) {
-                status.available = Some(available);
-                status.checking = false;
-            }
-        });
-        
-        // 检查HotPE
-        let sources_clone = self.sources.clone();
-        self.runtime.spawn(async move {
-            let available = check_source_async(PluginMode::HotPE).await;
-            let mut sources = sources_clone.write();
-            if let Some(status) = sources.get_mut(&PluginMode::HotPE) {
-                status.available = Some(available);
-                status.checking = false;
-            }
-        });
-        
-        // 检查Edgeless
-        let sources_clone = self.sources.clone();
-        self.runtime.spawn(async move {
-            let available = check_source_async(PluginMode::Edgeless).await;
-            let mut sources = sources_clone.write();
-            if let Some(status) = sources.get_mut(&PluginMode::Edgeless) {
-                status.available = Some(available);
-                status.checking = false;
-            }
-        });
-    }
-}
-
-async fn check_source_async(mode: PluginMode) -> bool {
-    let url = mode.get_connect_test_url();
-    if url.is_empty() {
-        return false;
-    }
-    
-    let mut retry_count = 0;
-    let max_retries = 3;
-    
-    while retry_count < max_retries {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(5))
-            .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
-        
-        match client.get(url).send().await {
-            Ok(response) => {
-                if let Ok(text) = response.text().await {
-                    if !text.is_empty() {
-                        return true;
+        for mode in [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless] {
+            let sources_clone = self.sources.clone();
+            runtime.spawn(async move {
+                let progress_sources = sources_clone.clone();
+                let result = check_source_async(mode, move |attempt| {
+                    if let Some(status) = progress_sources.write().get_mut(&mode) {
+                        status.attempt = attempt;
                     }
+                }).await;
+                
+                let mut sources = sources_clone.write();
+                if let Some(status) = sources.get_mut(&mode) {
+                    status.available = Some(result.is_ok());
+                    status.failure = result.err();
+                    status.checking = false;
                 }
-            }
-            Err(_) => {}
-        }
-        
-        retry_count += 1;
-        if retry_count < max_retries {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            });
         }
     }
-    
-    false
+}
+
+async fn check_source_async(
+    mode: PluginMode,
+    on_attempt: impl FnMut(u32) + Send,
+) -> Result<(), ConnectionFailure> {
+    let strategy = crate::config::AppConfig::load().unwrap_or_default().connect_test;
+    crate::network::test_connection_with_progress(mode, strategy, on_attempt).await
 }
 
 impl eframe::App for SourceSelector {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if let Some(loading) = &mut self.loading {
+            loading.update(ctx, frame);
+            return;
+        }
+        
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(20.0);
@@ -146,64 +120,47 @@ impl eframe::App for SourceSelector {
                 
                 let button_enabled = !self.is_checking;
                 
-                // 创建按钮的函数，包含状态图标
-                let create_button = |name: &str, status: Option<bool>| -> String {
-                    match status {
+                // 创建按钮的函数，包含状态图标或检测进度
+                let create_button = |name: &str, status: &SourceStatus| -> String {
+                    if status.checking && status.attempt > 0 {
+                        return format!("{}  检测中 ({}/{})", name, status.attempt, CONNECTION_TEST_ATTEMPTS);
+                    }
+                    
+                    match status.available {
                         Some(true) => format!("✓  {}", name),
                         Some(false) => format!("✗  {}", name),
                         None => name.to_string(),
                     }
                 };
                 
-                // Cloud-PE按钮
-                {
-                    let sources = self.sources.read();
-                    let status = sources.get(&PluginMode::CloudPE)
-                        .and_then(|s| s.available);
-                    let button_text = create_button("Cloud-PE", status);
-                    
-                    if ui.add_enabled(
-                        button_enabled, 
-                        egui::Button::new(button_text)
-                            .min_size(egui::Vec2::new(200.0, 40.0))
-                    ).clicked() {
-                        self.launch_mode(PluginMode::CloudPE);
+                for (index, (mode, name)) in [
+                    (PluginMode::CloudPE, "Cloud-PE"),
+                    (PluginMode::HotPE, "HotPE"),
+                    (PluginMode::Edgeless, "Edgeless"),
+                ].into_iter().enumerate() {
+                    if index > 0 {
+                        ui.add_space(10.0);
                     }
-                }
-                
-                ui.add_space(10.0);
-                
-                // HotPE按钮
-                {
-                    let sources = self.sources.read();
-                    let status = sources.get(&PluginMode::HotPE)
-                        .and_then(|s| s.available);
-                    let button_text = create_button("HotPE", status);
                     
-                    if ui.add_enabled(
+                    let status = self.sources.read().get(&mode).cloned().unwrap_or_default();
+                    let button_text = create_button(name, &status);
+                    
+                    let mut response = ui.add_enabled(
                         button_enabled,
                         egui::Button::new(button_text)
                             .min_size(egui::Vec2::new(200.0, 40.0))
-                    ).clicked() {
-                        self.launch_mode(PluginMode::HotPE);
+                    );
+                    
+                    // 检测失败时提示最后一次尝试的失败原因
+                    if let Some(failure) = status.failure {
+                        let reason = format!("检测失败: {}", failure.description());
+                        response = response
+                            .on_hover_text(&reason)
+                            .on_disabled_hover_text(&reason);
                     }
-                }
-                
-                ui.add_space(10.0);
-                
-                // Edgeless按钮
-                {
-                    let sources = self.sources.read();
-                    let status = sources.get(&PluginMode::Edgeless)
-                        .and_then(|s| s.available);
-                    let button_text = create_button("Edgeless", status);
                     
-                    if ui.add_enabled(
-                        button_enabled,
-                        egui::Button::new(button_text)
-                            .min_size(egui::Vec2::new(200.0, 40.0))
-                    ).clicked() {
-                        self.launch_mode(PluginMode::Edgeless);
+                    if response.clicked() {
+                        self.launch_mode(ctx, mode);
                     }
                 }
                 
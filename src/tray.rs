@@ -0,0 +1,222 @@
+use eframe::egui;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 系统托盘图标。启用后关闭窗口只是隐藏到托盘，下载在后台继续；从托盘菜单退出时才真正关闭程序。
+// 窗口隐藏后 eframe 不再调用 update，托盘事件直接在消息循环中处理，显示与隐藏窗口都通过 Win32 API 完成
+
+// 托盘菜单中选择了退出，关闭窗口时不再隐藏到托盘
+static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn quit_requested() -> bool {
+    QUIT_REQUESTED.load(Ordering::Relaxed)
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use super::QUIT_REQUESTED;
+    use eframe::egui;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicIsize, Ordering};
+    use std::sync::Once;
+    use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use tray_icon::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+    use winapi::um::winuser::{GetActiveWindow, IsWindowVisible, SetForegroundWindow, ShowWindow, SW_HIDE, SW_SHOW};
+    
+    const MENU_TOGGLE: &str = "toggle";
+    const MENU_QUIT: &str = "quit";
+    
+    // 主窗口句柄，窗口获得焦点时记录
+    static WINDOW: AtomicIsize = AtomicIsize::new(0);
+    static HANDLERS: Once = Once::new();
+    
+    // 托盘图标只能在创建它的线程中使用，事件也在该线程的消息循环中处理
+    thread_local! {
+        static TRAY: RefCell<Option<Tray>> = const { RefCell::new(None) };
+    }
+    
+    struct Tray {
+        icon: TrayIcon,
+        toggle_item: MenuItem,
+        count_item: MenuItem,
+        // 查询进行中的任务数，窗口隐藏后悬停托盘图标时也能更新
+        counter: Box<dyn Fn() -> usize>,
+        shown_count: Option<usize>,
+    }
+    
+    impl Tray {
+        fn build(ctx: &egui::Context) -> Option<Self> {
+            let icon_data = eframe::icon_data::from_png_bytes(include_bytes!("../assets/icon.png")).ok()?;
+            let icon = tray_icon::Icon::from_rgba(icon_data.rgba, icon_data.width, icon_data.height).ok()?;
+            
+            let toggle_item = MenuItem::with_id(MENU_TOGGLE, "隐藏窗口", true, None);
+            let count_item = MenuItem::new("没有进行中的下载", false, None);
+            let quit_item = MenuItem::with_id(MENU_QUIT, "退出", true, None);
+            
+            let menu = Menu::new();
+            menu.append(&toggle_item).ok()?;
+            menu.append(&count_item).ok()?;
+            menu.append(&PredefinedMenuItem::separator()).ok()?;
+            menu.append(&quit_item).ok()?;
+            
+            let icon = match TrayIconBuilder::new()
+                .with_icon(icon)
+                .with_tooltip("Cloud-PE 插件市场")
+                .with_menu(Box::new(menu))
+                .build()
+            {
+                Ok(icon) => icon,
+                Err(e) => {
+                    crate::app_log::log(format!("创建托盘图标失败: {}", e));
+                    return None;
+                }
+            };
+            
+            let ctx = ctx.clone();
+            HANDLERS.call_once(move || {
+                let menu_ctx = ctx.clone();
+                MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                    if event.id == MENU_TOGGLE {
+                        if window_visible() {
+                            hide_window();
+                        } else {
+                            show_window(&menu_ctx);
+                        }
+                    } else if event.id == MENU_QUIT {
+                        QUIT_REQUESTED.store(true, Ordering::Relaxed);
+                        // 窗口需要可见才会继续运行 update，由主程序关闭窗口并完成退出前的清理
+                        show_window(&menu_ctx);
+                        menu_ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                }));
+                
+                TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| match event {
+                    TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } => {
+                        show_window(&ctx);
+                    }
+                    TrayIconEvent::Enter { .. } => refresh(),
+                    _ => {}
+                }));
+            });
+            
+            Some(Self {
+                icon,
+                toggle_item,
+                count_item,
+                counter: Box::new(|| 0),
+                shown_count: None,
+            })
+        }
+        
+        fn refresh(&mut self) {
+            let count = (self.counter)();
+            if self.shown_count != Some(count) {
+                self.shown_count = Some(count);
+                let text = if count == 0 {
+                    "没有进行中的下载".to_string()
+                } else {
+                    format!("进行中的下载: {}", count)
+                };
+                self.count_item.set_text(&text);
+                let _ = self.icon.set_tooltip(Some(format!("Cloud-PE 插件市场 - {}", text)));
+            }
+            
+            self.toggle_item.set_text(if window_visible() { "隐藏窗口" } else { "显示窗口" });
+        }
+    }
+    
+    // 托盘事件可能在 update 持有托盘时到达，此时跳过，下一帧会再次更新
+    fn refresh() {
+        TRAY.with(|tray| {
+            if let Ok(mut tray) = tray.try_borrow_mut() {
+                if let Some(tray) = tray.as_mut() {
+                    tray.refresh();
+                }
+            }
+        });
+    }
+    
+    fn window_visible() -> bool {
+        let hwnd = WINDOW.load(Ordering::Relaxed);
+        hwnd == 0 || unsafe { IsWindowVisible(hwnd as _) != 0 }
+    }
+    
+    fn show_window(ctx: &egui::Context) {
+        let hwnd = WINDOW.load(Ordering::Relaxed);
+        if hwnd != 0 {
+            unsafe {
+                ShowWindow(hwnd as _, SW_SHOW);
+                SetForegroundWindow(hwnd as _);
+            }
+        }
+        ctx.request_repaint();
+        refresh();
+    }
+    
+    pub fn hide_window() {
+        let hwnd = WINDOW.load(Ordering::Relaxed);
+        if hwnd != 0 {
+            unsafe {
+                ShowWindow(hwnd as _, SW_HIDE);
+            }
+        }
+        refresh();
+    }
+    
+    pub fn update(ctx: &egui::Context, enabled: bool, counter: Box<dyn Fn() -> usize>) {
+        // 独立窗口获得焦点时 GetActiveWindow 返回的是独立窗口，只在主窗口获得焦点时记录
+        if ctx.input(|i| i.viewport().focused == Some(true)) {
+            let hwnd = unsafe { GetActiveWindow() };
+            if !hwnd.is_null() {
+                WINDOW.store(hwnd as isize, Ordering::Relaxed);
+            }
+        }
+        
+        TRAY.with(|tray| {
+            let mut tray = tray.borrow_mut();
+            if !enabled {
+                *tray = None;
+                return;
+            }
+            
+            if tray.is_none() {
+                *tray = Tray::build(ctx);
+            }
+            if let Some(tray) = tray.as_mut() {
+                tray.counter = counter;
+                tray.refresh();
+            }
+        });
+    }
+    
+    // 托盘图标创建成功且已知道窗口句柄时才能隐藏到托盘，否则窗口隐藏后将无法找回
+    pub fn is_active() -> bool {
+        WINDOW.load(Ordering::Relaxed) != 0 && TRAY.with(|tray| tray.borrow().is_some())
+    }
+}
+
+// 每帧由主窗口调用：按设置创建或移除托盘图标，并更新菜单中的下载数量
+#[cfg(target_os = "windows")]
+pub fn update(ctx: &egui::Context, enabled: bool, counter: Box<dyn Fn() -> usize>) {
+    imp::update(ctx, enabled, counter);
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn update(_ctx: &egui::Context, _enabled: bool, _counter: Box<dyn Fn() -> usize>) {}
+
+#[cfg(target_os = "windows")]
+pub fn is_active() -> bool {
+    imp::is_active()
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_active() -> bool {
+    false
+}
+
+#[cfg(target_os = "windows")]
+pub fn hide_window() {
+    imp::hide_window();
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn hide_window() {}
@@ -0,0 +1,77 @@
+use eframe::egui;
+
+// 市场中的插件在当前启动盘上的状态
+#[derive(Clone, Copy, PartialEq)]
+pub enum PluginStatus {
+    NotInstalled,
+    Installed,
+    UpdateAvailable,
+    // 启动盘上的版本比市场中的新，通常是手动安装的预览版
+    LocalNewer,
+}
+
+// 插件操作按钮不可用的原因，市场页和管理页共用同一套说明
+#[derive(Clone, Copy, PartialEq)]
+pub enum Unavailable {
+    NoBootDrive,
+    Offline,
+    UpToDate,
+    Installing,
+    Updating,
+    Downgrading,
+    Downloading,
+}
+
+impl Unavailable {
+    pub fn hint(self) -> &'static str {
+        match self {
+            Unavailable::NoBootDrive => "未选择启动盘，无法安装",
+            Unavailable::Offline => "离线模式下无法联网，可在设置中关闭离线模式",
+            Unavailable::UpToDate => "已是最新版本",
+            Unavailable::Installing => "正在安装中",
+            Unavailable::Updating => "正在更新中",
+            Unavailable::Downgrading => "正在降级安装中",
+            Unavailable::Downloading => "正在下载中",
+        }
+    }
+}
+
+// 安装或更新按钮不可用的原因，None 表示可以操作。busy 表示该插件的安装或更新任务正在进行，优先说明
+pub fn install_unavailable(has_boot_drive: bool, offline: bool, status: PluginStatus, busy: bool) -> Option<Unavailable> {
+    if busy {
+        return Some(match status {
+            PluginStatus::UpdateAvailable => Unavailable::Updating,
+            PluginStatus::LocalNewer => Unavailable::Downgrading,
+            _ => Unavailable::Installing,
+        });
+    }
+    
+    if !has_boot_drive {
+        Some(Unavailable::NoBootDrive)
+    } else if status == PluginStatus::Installed {
+        Some(Unavailable::UpToDate)
+    } else if offline {
+        Some(Unavailable::Offline)
+    } else {
+        None
+    }
+}
+
+// 下载按钮不可用的原因，None 表示可以下载
+pub fn download_unavailable(offline: bool, busy: bool) -> Option<Unavailable> {
+    if busy {
+        Some(Unavailable::Downloading)
+    } else if offline {
+        Some(Unavailable::Offline)
+    } else {
+        None
+    }
+}
+
+// 按钮不可用时在悬停提示中说明原因
+pub fn explain_unavailable(response: egui::Response, reason: Option<Unavailable>) -> egui::Response {
+    match reason {
+        Some(reason) => response.on_disabled_hover_text(reason.hint()),
+        None => response,
+    }
+}
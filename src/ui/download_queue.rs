@@ -0,0 +1,72 @@
+use crate::downloader::DownloadProgress;
+use crate::plugins::{format_file_size, format_speed};
+use eframe::egui;
+
+// 下载队列中的一项，由市场页和管理页的任务列表汇总而来
+pub struct QueueEntry {
+    pub task_id: String,
+    pub plugin_name: String,
+    pub progress: DownloadProgress,
+    // 尚未开始下载，仍在批量安装的队列中等待
+    pub waiting: bool,
+}
+
+// 插件卡片中的下载进度条，显示百分比和速度；还没有收到文件大小时显示"计算中..."
+pub fn show_task_progress(ui: &mut egui::Ui, progress: &DownloadProgress) {
+    let bar = if progress.total > 0 {
+        let fraction = progress.current as f32 / progress.total as f32;
+        egui::ProgressBar::new(fraction).text(format!(
+            "{:.0}% · {}",
+            fraction * 100.0,
+            format_speed(progress.speed * 1024.0 * 1024.0)
+        ))
+    } else {
+        egui::ProgressBar::new(0.0).text("计算中...")
+    };
+    ui.add(bar.desired_width(140.0));
+}
+
+// 显示下载队列，返回用户要取消的任务在 entries 中的位置
+pub fn show_download_queue(ui: &mut egui::Ui, entries: &[QueueEntry]) -> Option<usize> {
+    let mut cancelled = None;
+    
+    egui::CollapsingHeader::new(format!("下载队列 ({})", entries.len()))
+        .id_salt("download_queue")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    for (index, entry) in entries.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("取消").clicked() {
+                                cancelled = Some(index);
+                            }
+                            
+                            ui.label(&entry.plugin_name);
+                            
+                            let progress = &entry.progress;
+                            if entry.waiting {
+                                ui.weak("等待中");
+                            } else if progress.total > 0 {
+                                let fraction = progress.current as f32 / progress.total as f32;
+                                ui.add(egui::ProgressBar::new(fraction)
+                                    .desired_width(160.0)
+                                    .show_percentage());
+                                ui.label(format!(
+                                    "{} / {}",
+                                    format_file_size(progress.current as i64),
+                                    format_file_size(progress.total as i64)
+                                ));
+                                ui.label(format_speed(progress.speed * 1024.0 * 1024.0));
+                            } else {
+                                ui.spinner();
+                                ui.weak("连接中");
+                            }
+                        });
+                    }
+                });
+        });
+    
+    cancelled
+}
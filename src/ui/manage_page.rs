@@ -1,20 +1,49 @@
-use crate::plugins::{Plugin, PluginManager};
-use crate::utils::BootDriveManager;
+use crate::archive_contents::{self, ArchiveContents};
+use crate::plugins::{format_file_size, NameFix, Plugin, PluginError, PluginManager};
+use crate::utils::{is_system_drive, BootDriveManager};
 use crate::mode::PluginMode;
-use crate::downloader::Downloader;
-use crate::config::AppConfig;
+use crate::downloader::{DownloadProgress, Downloader};
+use crate::installer::{check_free_space, deploy_plugin, DeployOptions};
+use crate::plugin_naming;
+use crate::config::{AppConfig, CompatibilityCheck};
+use crate::network::is_offline_mode;
+use crate::ui::QueueEntry;
+use crate::ui::action_hint::{explain_unavailable, install_unavailable, PluginStatus, Unavailable};
 use eframe::egui;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::AbortHandle;
 use std::time::{Duration, Instant};
 
 #[derive(Clone)]
-#[allow(dead_code)]
 struct UpdateTask {
     plugin_name: String,
-    progress: Arc<RwLock<f32>>,
+    progress: Arc<RwLock<DownloadProgress>>,
+    cancelled: Arc<AtomicBool>,
+    abort_handle: Option<AbortHandle>,
+}
+
+// 正在查看内容的插件，文件列表在后台读取
+struct ContentsDialog {
+    plugin_name: String,
+    result: Arc<RwLock<Option<Result<ArchiveContents, String>>>>,
+}
+
+// 需要用户确认后才执行的操作
+enum PendingAction {
+    Disable(Plugin),
+    Delete(Plugin),
+    Restore,
+}
+
+// 写入系统盘前需要确认的操作：更新插件，或重新下载恢复备份时缺失的插件
+enum SystemDrivePrompt {
+    Update(Box<Plugin>),
+    Reinstall(Vec<Plugin>),
 }
 
 pub struct PluginsManagePage {
@@ -22,10 +51,36 @@ pub struct PluginsManagePage {
     boot_drive_manager: Arc<RwLock<BootDriveManager>>,
     mode: PluginMode,
     updating_tasks: Arc<RwLock<HashMap<String, UpdateTask>>>,
+    // 后台更新、重新下载失败的说明，下一帧显示到状态栏
+    task_failures: Arc<RwLock<Vec<String>>>,
     runtime: Arc<Runtime>,
+    // 与其他窗口共用的下载名额
+    download_slots: Arc<Semaphore>,
     config: Arc<RwLock<AppConfig>>,
     last_refresh: Option<Instant>,
     need_refresh: bool,
+    // 后台正在扫描启动盘上的插件，扫描期间继续显示旧列表
+    scanning: Arc<AtomicBool>,
+    // 每次扫描递增，扫描结束时编号已变化说明结果过时，直接丢弃
+    scan_generation: Arc<AtomicU64>,
+    // 最近一次扫描的启动盘，切换启动盘后立即重新扫描
+    scanned_drive: Option<String>,
+    // 用户手动刷新启动盘和插件，完成前显示加载动画
+    refreshing_all: Arc<AtomicBool>,
+    status_message: Option<String>,
+    restore_missing: Vec<Plugin>,
+    pending_action: Option<PendingAction>,
+    // 等待用户确认是否写入系统盘的操作
+    system_drive_prompt: Option<SystemDrivePrompt>,
+    // 已确认可以写入的系统盘，换了启动盘后需要重新确认
+    system_drive_confirmed: Option<String>,
+    // 等待用户确认是否更新为适用平台不符的版本：启动盘上的插件及提示
+    compatibility_prompt: Option<(Plugin, String)>,
+    // 最近一次未经确认直接禁用的插件，可以撤销
+    undo_disable: Option<Plugin>,
+    contents_dialog: Option<ContentsDialog>,
+    // 检测到的文件名修复，确认前只预览
+    name_fixes: Option<Vec<NameFix>>,
 }
 
 impl PluginsManagePage {
@@ -34,6 +89,7 @@ impl PluginsManagePage {
         boot_drive_manager: Arc<RwLock<BootDriveManager>>,
         mode: PluginMode,
         runtime: Arc<Runtime>,
+        download_slots: Arc<Semaphore>,
         config: Arc<RwLock<AppConfig>>,
     ) -> Self {
         Self {
@@ -41,23 +97,53 @@ impl PluginsManagePage {
             boot_drive_manager,
             mode,
             updating_tasks: Arc::new(RwLock::new(HashMap::new())),
+            task_failures: Arc::new(RwLock::new(Vec::new())),
             runtime,
+            download_slots,
             config,
             last_refresh: None,
             need_refresh: true,
+            scanning: Arc::new(AtomicBool::new(false)),
+            scan_generation: Arc::new(AtomicU64::new(0)),
+            scanned_drive: None,
+            refreshing_all: Arc::new(AtomicBool::new(false)),
+            status_message: None,
+            restore_missing: Vec::new(),
+            pending_action: None,
+            system_drive_prompt: None,
+            system_drive_confirmed: None,
+            compatibility_prompt: None,
+            undo_disable: None,
+            contents_dialog: None,
+            name_fixes: None,
         }
     }
     
     pub fn show(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        ui.heading(self.mode.get_plugin_manage_name());
+        ui.horizontal(|ui| {
+            ui.heading(self.mode.get_plugin_manage_name());
+            
+            if let Some(boot_drive) = self.boot_drive_manager.read().get_current_boot_drive() {
+                ui.add_space(20.0);
+                ui.label(format!("启动盘: {}", boot_drive.display_name()));
+            }
+        });
         ui.separator();
         
         let current_drive = self.boot_drive_manager.read().get_current_drive();
         
         if let Some(drive) = current_drive {
+            if self.scanned_drive.as_deref() != Some(drive.as_str()) {
+                // 旧启动盘的扫描结果不再显示
+                self.scan_generation.fetch_add(1, Ordering::AcqRel);
+                self.scanning.store(false, Ordering::Release);
+                self.scanned_drive = Some(drive.clone());
+                self.need_refresh = true;
+            }
+            
             let has_updating_tasks = !self.updating_tasks.read().is_empty();
             
-            let should_refresh = if has_updating_tasks {
+            let should_refresh = if has_updating_tasks || self.scanning.load(Ordering::Acquire) {
                 false
             } else if self.need_refresh {
                 true
@@ -68,11 +154,24 @@ impl PluginsManagePage {
             };
             
             if should_refresh {
-                let _ = self.plugin_manager.write().load_local_plugins(&drive);
+                self.start_scan(&drive);
                 self.last_refresh = Some(Instant::now());
                 self.need_refresh = false;
             }
             
+            let failures = std::mem::take(&mut *self.task_failures.write());
+            if !failures.is_empty() {
+                self.status_message = Some(failures.join("；"));
+            }
+            
+            self.show_backup_actions(ui, &drive);
+            self.show_confirm_dialog(ctx, &drive);
+            self.show_system_drive_dialog(ctx, &drive);
+            self.show_compatibility_dialog(ctx, &drive);
+            self.show_contents_dialog(ctx);
+            self.show_name_fix_dialog(ctx, &drive);
+            ui.separator();
+            
             let enabled_label = match self.mode {
                 PluginMode::HotPE => "已启用模块",
                 _ => "已启用插件",
@@ -127,6 +226,231 @@ impl PluginsManagePage {
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
     }
     
+    // 在后台线程读取插件目录，插件较多时不会卡住界面。
+    // 扫描期间再次请求刷新时 need_refresh 保持为 true，扫描结束后会再刷新一次
+    fn start_scan(&self, drive: &str) {
+        self.scanning.store(true, Ordering::Release);
+        
+        let plugin_manager = self.plugin_manager.clone();
+        let scanning = self.scanning.clone();
+        let scan_generation = self.scan_generation.clone();
+        let generation = scan_generation.fetch_add(1, Ordering::AcqRel) + 1;
+        let mode = self.mode;
+        let drive = drive.to_string();
+        
+        self.runtime.spawn_blocking(move || {
+            let mut scanned = PluginManager::new(mode);
+            let _ = scanned.load_local_plugins(&drive);
+            finish_scan(&plugin_manager, &scanning, &scan_generation, generation, scanned);
+        });
+    }
+    
+    // 重新扫描启动盘，并立即重新读取当前启动盘上的插件，外部改动插件目录后可以马上看到结果
+    fn start_full_refresh(&mut self) {
+        self.scanning.store(true, Ordering::Release);
+        self.refreshing_all.store(true, Ordering::Release);
+        self.last_refresh = Some(Instant::now());
+        self.need_refresh = false;
+        
+        let boot_drive_manager = self.boot_drive_manager.clone();
+        let plugin_manager = self.plugin_manager.clone();
+        let scanning = self.scanning.clone();
+        let scan_generation = self.scan_generation.clone();
+        let generation = scan_generation.fetch_add(1, Ordering::AcqRel) + 1;
+        let refreshing_all = self.refreshing_all.clone();
+        let mode = self.mode;
+        
+        self.runtime.spawn_blocking(move || {
+            let drives = boot_drive_manager.read().scan_boot_drives();
+            boot_drive_manager.write().replace_drives(drives);
+            
+            let current_drive = boot_drive_manager.read().get_current_drive();
+            let mut scanned = PluginManager::new(mode);
+            if let Some(drive) = current_drive {
+                let _ = scanned.load_local_plugins(&drive);
+            }
+            finish_scan(&plugin_manager, &scanning, &scan_generation, generation, scanned);
+            refreshing_all.store(false, Ordering::Release);
+        });
+    }
+    
+    fn show_backup_actions(&mut self, ui: &mut egui::Ui, drive: &str) {
+        ui.horizontal(|ui| {
+            let refreshing = self.refreshing_all.load(Ordering::Acquire);
+            if ui.add_enabled(!refreshing, egui::Button::new("刷新启动盘并重新加载插件"))
+                .on_hover_text("重新扫描所有启动盘，并重新读取当前启动盘上的插件")
+                .clicked()
+            {
+                self.start_full_refresh();
+            }
+            if refreshing {
+                ui.spinner();
+            }
+            
+
+            if ui.button("备份当前插件配置到启动盘").clicked() {
+                self.status_message = Some(match self.plugin_manager.read().backup_to_drive(drive) {
+                    Ok(path) => format!("已备份到 {}", path.display()),
+                    Err(e) => format!("备份失败: {}", e),
+                });
+            }
+            
+            if ui.button("从启动盘恢复插件配置").clicked() {
+                self.request_action(PendingAction::Restore, drive);
+            }
+            
+            if ui.button("检测并修复插件文件名")
+                .on_hover_text("与插件市场中的信息对比，把不符合命名规则的文件重命名，重命名前会先列出所有改动")
+                .clicked()
+            {
+                self.detect_name_fixes(drive);
+            }
+        });
+        
+        if let Some(message) = self.status_message.clone() {
+            ui.horizontal(|ui| {
+                ui.label(message);
+                
+                if self.undo_disable.is_some() && ui.button("撤销").clicked() {
+                    self.undo_last_disable(drive);
+                }
+            });
+        }
+        
+        if !self.restore_missing.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                let names: Vec<&str> = self.restore_missing.iter().map(|p| p.name.as_str()).collect();
+                ui.label(format!("缺失: {}", names.join("、")));
+                
+                if ui.add_enabled(!is_offline_mode(), egui::Button::new("重新下载缺失项"))
+                    .on_disabled_hover_text("离线模式下无法下载")
+                    .clicked()
+                {
+                    let missing = std::mem::take(&mut self.restore_missing);
+                    self.request_reinstall(missing, drive);
+                }
+            });
+        }
+    }
+    
+    // 开启确认时先弹出确认框，否则直接执行
+    fn request_action(&mut self, action: PendingAction, drive: &str) {
+        if self.config.read().confirm_destructive {
+            self.pending_action = Some(action);
+        } else {
+            self.run_action(action, drive, true);
+        }
+    }
+    
+    fn run_action(&mut self, action: PendingAction, drive: &str, allow_undo: bool) {
+        self.undo_disable = None;
+        
+        match action {
+            PendingAction::Disable(plugin) => {
+                match self.plugin_manager.write().disable_plugin(drive, &plugin.file) {
+                    Ok(_) => {
+                        self.status_message = Some(format!("已禁用 {}", plugin.name));
+                        if allow_undo {
+                            self.undo_disable = Some(plugin);
+                        }
+                    }
+                    Err(e) => self.status_message = Some(format!("禁用失败: {}", e)),
+                }
+                self.need_refresh = true;
+            }
+            // 删除无法撤销
+            PendingAction::Delete(plugin) => {
+                self.status_message = Some(match self.plugin_manager.read().delete_plugin_file(drive, &plugin.file) {
+                    Ok(_) => format!("已删除 {}", plugin.name),
+                    Err(e) => format!("删除失败: {}", e),
+                });
+                self.need_refresh = true;
+            }
+            PendingAction::Restore => {
+                let result = self.plugin_manager.write().restore_from_drive(drive);
+                self.need_refresh = true;
+                self.status_message = Some(match result {
+                    Ok(report) => {
+                        let mut message = format!("已恢复 {} 项启用/禁用状态", report.changed);
+                        if !report.missing.is_empty() {
+                            message.push_str(&format!("，{} 项需要重新下载", report.missing.len()));
+                        }
+                        if !report.unavailable.is_empty() {
+                            message.push_str(&format!("，市场中找不到: {}", report.unavailable.join("、")));
+                        }
+                        self.restore_missing = report.missing;
+                        message
+                    }
+                    Err(PluginError::NotFound) => "启动盘上没有找到插件配置备份".to_string(),
+                    Err(e) => format!("恢复失败: {}", e),
+                });
+            }
+        }
+    }
+    
+    fn undo_last_disable(&mut self, drive: &str) {
+        let Some(plugin) = self.undo_disable.take() else {
+            return;
+        };
+        
+        let _ = self.plugin_manager.write().load_local_plugins(drive);
+        let disabled_file = self.plugin_manager.read()
+            .get_disabled_plugins()
+            .iter()
+            .find(|p| p.name == plugin.name && p.version == plugin.version && p.author == plugin.author)
+            .map(|p| p.file.clone());
+        
+        self.status_message = Some(match disabled_file {
+            Some(file) => match self.plugin_manager.write().enable_plugin(drive, &file) {
+                Ok(_) => format!("已重新启用 {}", plugin.name),
+                Err(e) => format!("撤销失败: {}", e),
+            },
+            None => "撤销失败: 找不到已禁用的文件".to_string(),
+        });
+        self.need_refresh = true;
+    }
+    
+    fn show_confirm_dialog(&mut self, ctx: &egui::Context, drive: &str) {
+        let Some(action) = &self.pending_action else {
+            return;
+        };
+        
+        let message = match action {
+            PendingAction::Disable(plugin) => format!("确定要禁用 {} 吗？", plugin.name),
+            PendingAction::Delete(plugin) => format!("确定要从启动盘删除 {} 吗？删除后无法撤销", plugin.name),
+            PendingAction::Restore => "恢复备份会覆盖当前的启用/禁用状态，确定要继续吗？".to_string(),
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("确认操作")
+            .id(egui::Id::new("manage_confirm").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(message);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("确定").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            if let Some(action) = self.pending_action.take() {
+                self.run_action(action, drive, false);
+            }
+        } else if cancelled {
+            self.pending_action = None;
+        }
+    }
+    
     fn show_plugin_item(&mut self, ui: &mut egui::Ui, plugin: &Plugin, is_enabled: bool, drive: &str) {
         let plugin_id = plugin.get_plugin_id();
         let update_task_id = format!("{}_update", plugin_id);
@@ -159,34 +483,215 @@ impl PluginsManagePage {
                     
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if is_enabled {
-                            if !is_updating {
-                                if ui.button("禁用").clicked() {
-                                    let _ = self.plugin_manager.write()
-                                        .disable_plugin(drive, &plugin.file);
-                                    self.need_refresh = true;
-                                }
+                            if is_updating {
+                                // 更新完成前不能禁用，仍显示按钮并说明原因
+                                explain_unavailable(ui.add_enabled(false, egui::Button::new("禁用")), Some(Unavailable::Updating));
+                            } else if ui.button("禁用")
+                                .on_hover_text(rename_preview(&plugin.file, self.mode, false))
+                                .clicked()
+                            {
+                                self.request_action(PendingAction::Disable(plugin.clone()), drive);
                             }
                             
                             if self.check_update_available(plugin) {
+                                let offline = is_offline_mode();
+                                let update_reason = install_unavailable(true, offline, PluginStatus::UpdateAvailable, is_updating);
                                 if is_updating {
-                                    ui.add_enabled(false, egui::Button::new("更新中..."));
+                                    explain_unavailable(ui.add_enabled(false, egui::Button::new("更新中...")), update_reason);
                                     ui.spinner();
+                                    if ui.button("取消").clicked() {
+                                        self.cancel_task(&update_task_id);
+                                    }
                                 } else {
-                                    if ui.button("更新").clicked() {
-                                        self.update_plugin(plugin.clone(), drive);
+                                    if explain_unavailable(ui.add_enabled(!offline, egui::Button::new("更新")), update_reason)
+                                        .clicked()
+                                    {
+                                        self.request_update(plugin, drive);
                                     }
                                 }
                             }
                         } else {
-                            if ui.button("启用").clicked() {
-                                let _ = self.plugin_manager.write()
-                                    .enable_plugin(drive, &plugin.file);
+                            if ui.button("启用")
+                                .on_hover_text(rename_preview(&plugin.file, self.mode, true))
+                                .clicked()
+                            {
+                                self.status_message = Some(match self.plugin_manager.write().enable_plugin(drive, &plugin.file) {
+                                    Ok(_) => format!("已启用 {}", plugin.name),
+                                    Err(e) => format!("启用失败: {}", e),
+                                });
                                 self.need_refresh = true;
                             }
                         }
+                        
+                        if is_updating {
+                            explain_unavailable(ui.add_enabled(false, egui::Button::new("删除")), Some(Unavailable::Updating));
+                        } else if ui.button("删除")
+                            .on_hover_text("从启动盘删除插件文件")
+                            .clicked()
+                        {
+                            self.request_action(PendingAction::Delete(plugin.clone()), drive);
+                        }
+                        
+                        if ui.button("查看内容")
+                            .on_hover_text("列出插件压缩包中的文件，不会解压或修改插件")
+                            .clicked()
+                        {
+                            self.open_contents_dialog(plugin, drive);
+                        }
+                    });
+                });
+            });
+    }
+    
+    fn open_contents_dialog(&mut self, plugin: &Plugin, drive: &str) {
+        let path = crate::utils::plugin_dir(drive, self.mode).join(&plugin.file);
+        let result = Arc::new(RwLock::new(None));
+        
+        let spawned_result = result.clone();
+        self.runtime.spawn_blocking(move || {
+            *spawned_result.write() = Some(archive_contents::list_entries(&path));
+        });
+        
+        self.contents_dialog = Some(ContentsDialog {
+            plugin_name: plugin.name.clone(),
+            result,
+        });
+    }
+    
+    fn show_contents_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &self.contents_dialog else {
+            return;
+        };
+        
+        let mut open = true;
+        
+        egui::Window::new(format!("{} 的内容", dialog.plugin_name))
+            .id(egui::Id::new("plugin_contents").with(self.mode))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([480.0, 360.0])
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match &*dialog.result.read() {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("正在读取文件列表...");
+                        });
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), e);
+                    }
+                    Some(Ok(contents)) => {
+                        let files = contents.entries.iter().filter(|entry| !entry.is_dir).count();
+                        ui.label(format!("{} 压缩包，共 {} 个文件", contents.format, files));
+                        if contents.encrypted {
+                            ui.colored_label(egui::Color32::from_rgb(255, 160, 0), "部分文件已加密，只能查看文件名");
+                        }
+                        ui.separator();
+                        
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            egui::Grid::new("plugin_contents_grid")
+                                .striped(true)
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    for entry in &contents.entries {
+                                        ui.label(&entry.name);
+                                        if entry.is_dir {
+                                            ui.weak("文件夹");
+                                        } else {
+                                            ui.label(format_file_size(entry.size as i64));
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    }
+                }
+            });
+        
+        if !open {
+            self.contents_dialog = None;
+        }
+    }
+    
+    fn detect_name_fixes(&mut self, drive: &str) {
+        let manager = self.plugin_manager.read();
+        if manager.get_categories().is_empty() {
+            self.status_message = Some("插件市场列表尚未加载，无法检测文件名".to_string());
+            return;
+        }
+        
+        let fixes = manager.find_name_fixes(drive);
+        drop(manager);
+        
+        // 批量重命名前总是先列出改动，不受“操作前确认”设置影响
+        if fixes.is_empty() {
+            self.status_message = Some("没有发现需要修复的插件文件名".to_string());
+        } else {
+            self.name_fixes = Some(fixes);
+        }
+    }
+    
+    fn apply_name_fixes(&mut self, drive: &str, fixes: &[NameFix]) {
+        let result = self.plugin_manager.write().apply_name_fixes(drive, fixes);
+        self.need_refresh = true;
+        self.status_message = Some(match result {
+            Ok((renamed, failures)) if failures.is_empty() => format!("已修复 {} 个插件文件名", renamed),
+            Ok((renamed, failures)) => {
+                format!("已修复 {} 个插件文件名，{} 个失败: {}", renamed, failures.len(), failures.join("；"))
+            }
+            Err(e) => format!("修复文件名失败: {}", e),
+        });
+    }
+    
+    fn show_name_fix_dialog(&mut self, ctx: &egui::Context, drive: &str) {
+        let Some(fixes) = &self.name_fixes else {
+            return;
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("修复插件文件名")
+            .id(egui::Id::new("name_fix_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(true)
+            .default_width(560.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("以下 {} 个文件的文件名与插件市场中的信息不一致，将按命名规则重命名：", fixes.len()));
+                ui.separator();
+                
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for fix in fixes {
+                            ui.label(egui::RichText::new(&fix.market_plugin.name).strong());
+                            ui.label(format!("当前: {}", fix.file));
+                            ui.label(format!("改为: {}", fix.new_file));
+                            ui.add_space(6.0);
+                        }
                     });
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("全部重命名").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
                 });
             });
+        
+        if confirmed {
+            if let Some(fixes) = self.name_fixes.take() {
+                self.apply_name_fixes(drive, &fixes);
+            }
+        } else if cancelled {
+            self.name_fixes = None;
+        }
     }
     
     fn check_update_available(&self, local_plugin: &Plugin) -> bool {
@@ -201,92 +706,259 @@ impl PluginsManagePage {
         }
     }
     
+    // 与插件市场相同，启动盘是系统盘且尚未确认过时先询问
+    fn needs_system_drive_confirmation(&self, drive: &str) -> bool {
+        is_system_drive(drive) && self.system_drive_confirmed.as_deref() != Some(drive)
+    }
+    
+    fn request_update(&mut self, local_plugin: &Plugin, drive: &str) {
+        if self.needs_system_drive_confirmation(drive) {
+            self.system_drive_prompt = Some(SystemDrivePrompt::Update(Box::new(local_plugin.clone())));
+            return;
+        }
+        
+        // 与插件市场相同，设置为提示时先确认是否更新为适用平台不符的版本
+        if self.config.read().compatibility_check == CompatibilityCheck::Warn {
+            let message = self.plugin_manager.read()
+                .find_market_plugin_by_id(&local_plugin.get_plugin_id())
+                .and_then(|market_plugin| market_plugin.compatibility_error(self.mode));
+            if let Some(message) = message {
+                self.compatibility_prompt = Some((local_plugin.clone(), message));
+                return;
+            }
+        }
+        
+        self.update_plugin(local_plugin.clone(), drive);
+    }
+    
+    fn show_compatibility_dialog(&mut self, ctx: &egui::Context, drive: &str) {
+        let Some((_, message)) = &self.compatibility_prompt else {
+            return;
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("插件可能不兼容")
+            .id(egui::Id::new("manage_compatibility").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 160, 0), message);
+                ui.label("更新后可能无法正常加载，确定要继续吗？");
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("仍然更新").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            if let Some((plugin, _)) = self.compatibility_prompt.take() {
+                self.update_plugin(plugin, drive);
+            }
+        } else if cancelled {
+            self.compatibility_prompt = None;
+        }
+    }
+    
+    fn request_reinstall(&mut self, plugins: Vec<Plugin>, drive: &str) {
+        if self.needs_system_drive_confirmation(drive) {
+            self.system_drive_prompt = Some(SystemDrivePrompt::Reinstall(plugins));
+            return;
+        }
+        
+        for plugin in plugins {
+            self.reinstall_plugin(plugin, drive);
+        }
+    }
+    
+    fn show_system_drive_dialog(&mut self, ctx: &egui::Context, drive: &str) {
+        let Some(prompt) = &self.system_drive_prompt else {
+            return;
+        };
+        let target = match prompt {
+            SystemDrivePrompt::Update(plugin) => plugin.name.clone(),
+            SystemDrivePrompt::Reinstall(plugins) => format!("{} 个缺失的插件", plugins.len()),
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("写入系统盘？")
+            .id(egui::Id::new("manage_system_drive").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 100, 100),
+                    format!("当前启动盘 {} 是正在运行的系统盘", drive),
+                );
+                ui.label(format!(
+                    "继续会在 {} 中写入 {}，这通常不是期望的操作。",
+                    crate::utils::plugin_dir(drive, self.mode).display(),
+                    target,
+                ));
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("仍然继续").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            self.system_drive_confirmed = Some(drive.to_string());
+            match self.system_drive_prompt.take() {
+                Some(SystemDrivePrompt::Update(plugin)) => self.request_update(&plugin, drive),
+                Some(SystemDrivePrompt::Reinstall(plugins)) => self.request_reinstall(plugins, drive),
+                None => {}
+            }
+        } else if cancelled {
+            // 取消后仍可稍后重新下载缺失项
+            if let Some(SystemDrivePrompt::Reinstall(plugins)) = self.system_drive_prompt.take() {
+                self.restore_missing = plugins;
+            }
+        }
+    }
+    
     fn update_plugin(&mut self, local_plugin: Plugin, drive: &str) {
         let plugin_id = local_plugin.get_plugin_id();
         let update_task_id = format!("{}_update", plugin_id);
         
-        let task = UpdateTask {
-            plugin_name: local_plugin.name.clone(),
-            progress: Arc::new(RwLock::new(0.0)),
+        let market_plugin = match self.plugin_manager.read().find_market_plugin_by_id(&plugin_id) {
+            Some(p) => p,
+            None => return,
         };
         
-        self.updating_tasks.write().insert(update_task_id.clone(), task.clone());
+        if let Err(message) = check_free_space(drive, &market_plugin) {
+            self.status_message = Some(message);
+            return;
+        }
         
-        let plugin_manager = self.plugin_manager.clone();
+        let options = DeployOptions {
+            replace_existing: true,
+            safe_install: self.config.read().safe_install,
+            block_incompatible: self.config.read().compatibility_check == CompatibilityCheck::Block,
+        };
+        self.spawn_deploy(market_plugin, drive, update_task_id, options);
+    }
+    
+    // 按市场信息把插件重新下载到启动盘，用于恢复备份时补齐缺失的插件
+    fn reinstall_plugin(&mut self, market_plugin: Plugin, drive: &str) {
+        let task_id = format!("{}_restore", market_plugin.get_plugin_id());
         
-        let market_plugin = match plugin_manager.read().find_market_plugin_by_id(&plugin_id) {
-            Some(p) => p,
-            None => {
-                self.updating_tasks.write().remove(&update_task_id);
-                return;
-            }
+        let options = DeployOptions {
+            replace_existing: false,
+            safe_install: self.config.read().safe_install,
+            block_incompatible: self.config.read().compatibility_check == CompatibilityCheck::Block,
         };
+        self.spawn_deploy(market_plugin, drive, task_id, options);
+    }
+    
+    fn spawn_deploy(&self, market_plugin: Plugin, drive: &str, task_id: String, options: DeployOptions) {
+        let config = self.config.read();
+        let downloader = Downloader::new(config.download_threads, config.download_retries);
+        drop(config);
+        
+        self.updating_tasks.write().insert(task_id.clone(), UpdateTask {
+            plugin_name: market_plugin.name.clone(),
+            progress: downloader.progress_handle(),
+            cancelled: downloader.cancel_flag(),
+            abort_handle: None,
+        });
         
-        let downloader = Arc::new(Downloader::new(self.config.read().download_threads));
         let drive_letter = drive.to_string();
         let updating_tasks = self.updating_tasks.clone();
-        let mode = self.mode.clone();
-        
-        let plugin_url = market_plugin.link.clone();
+        let task_failures = self.task_failures.clone();
+        let action = if options.replace_existing { "更新" } else { "重新下载" };
+        let plugin_manager = self.plugin_manager.clone();
+        let mode = self.mode;
         let filename = self.generate_plugin_filename(&market_plugin);
-        let old_file = local_plugin.file.clone();
+        let spawned_task_id = task_id.clone();
+        let download_slots = self.download_slots.clone();
         
-        self.runtime.spawn(async move {
-            let plugin_dir = format!("{}\\{}", drive_letter, mode.get_plugin_folder());
-            
-            if let Err(_) = tokio::fs::create_dir_all(&plugin_dir).await {
-                updating_tasks.write().remove(&update_task_id);
-                return;
+        let handle = self.runtime.spawn(async move {
+            let _permit = download_slots.acquire_owned().await;
+            if let Err(e) = deploy_plugin(&downloader, &plugin_manager, mode, &drive_letter, &market_plugin, &filename, options).await {
+                task_failures.write().push(format!("{} {} 失败: {}", action, market_plugin.name, e));
             }
-            
-            if let Err(_) = plugin_manager.read().delete_plugin_file(&drive_letter, &old_file) {
-                updating_tasks.write().remove(&update_task_id);
-                return;
-            }
-            
-            let extension = mode.get_enabled_extension();
-            let install_path = std::path::PathBuf::from(plugin_dir).join(format!("{}.{}", filename, extension));
-            
-            match downloader.download(&plugin_url, install_path.clone()).await {
-                Ok(_) => {
-                    let _ = plugin_manager.write().load_local_plugins(&drive_letter);
-                }
-                Err(_) => {
-                }
-            }
-            
-            updating_tasks.write().remove(&update_task_id);
+            updating_tasks.write().remove(&spawned_task_id);
         });
+        
+        // 任务可能在此之前就已完成并移除
+        if let Some(task) = self.updating_tasks.write().get_mut(&task_id) {
+            task.abort_handle = Some(handle.abort_handle());
+        }
     }
     
-    fn generate_plugin_filename(&self, plugin: &Plugin) -> String {
-        let safe_describe = plugin.describe
-            .replace(' ', "_")
-            .replace('/', "_")
-            .replace('\\', "_")
-            .replace(':', "_")
-            .replace('*', "_")
-            .replace('?', "_")
-            .replace('"', "_")
-            .replace('<', "_")
-            .replace('>', "_")
-            .replace('|', "_");
-        
-        match self.mode {
-            PluginMode::CloudPE => {
-                format!("{}_{}_{}_{}", plugin.name, plugin.version, plugin.author, safe_describe)
-            }
-            PluginMode::HotPE => {
-                if safe_describe.is_empty() {
-                    format!("{}_{}_{}_{}", plugin.name, plugin.author, plugin.version, plugin.name)
-                } else {
-                    format!("{}_{}_{}_{}", plugin.name, plugin.author, plugin.version, safe_describe)
-                }
-            }
-            PluginMode::Edgeless => {
-                format!("{}_{}_{}", plugin.name, plugin.version, plugin.author)
+    // 供下载队列显示的全部任务，按名称排序以保持顺序稳定
+    pub fn queue_entries(&self) -> Vec<QueueEntry> {
+        let mut entries: Vec<QueueEntry> = self.updating_tasks.read()
+            .iter()
+            .map(|(task_id, task)| QueueEntry {
+                task_id: task_id.clone(),
+                plugin_name: task.plugin_name.clone(),
+                progress: task.progress.read().clone(),
+                waiting: task.abort_handle.is_none(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.plugin_name.cmp(&b.plugin_name).then_with(|| a.task_id.cmp(&b.task_id)));
+        entries
+    }
+    
+    pub fn task_counter(&self) -> Box<dyn Fn() -> usize> {
+        let tasks = self.updating_tasks.clone();
+        Box::new(move || tasks.read().len())
+    }
+    
+    pub fn cancel_task(&mut self, task_id: &str) {
+        if let Some(task) = self.updating_tasks.write().remove(task_id) {
+            task.cancelled.store(true, Ordering::Relaxed);
+            if let Some(handle) = task.abort_handle {
+                handle.abort();
             }
-            _ => String::new()
         }
+        self.need_refresh = true;
+    }
+    
+    fn generate_plugin_filename(&self, plugin: &Plugin) -> String {
+        plugin_naming::encode(plugin, self.mode)
+    }
+}
+
+// 扫描完成后更新插件列表。期间切换了启动盘或开始了新的扫描时丢弃结果，
+// 也不清除 scanning，由最新的扫描结束时清除
+fn finish_scan(
+    plugin_manager: &RwLock<PluginManager>,
+    scanning: &AtomicBool,
+    scan_generation: &AtomicU64,
+    generation: u64,
+    scanned: PluginManager,
+) {
+    let mut manager = plugin_manager.write();
+    if scan_generation.load(Ordering::Acquire) != generation {
+        return;
+    }
+    manager.replace_local_plugins(scanned);
+    scanning.store(false, Ordering::Release);
+}
+
+// 启用/禁用按钮的提示，显示切换前后的文件名
+fn rename_preview(file_name: &str, mode: PluginMode, enable: bool) -> String {
+    match plugin_naming::toggled_file_name(file_name, mode, enable) {
+        Some(new_file_name) => format!("当前文件名: {}\n{}后: {}", file_name, if enable { "启用" } else { "禁用" }, new_file_name),
+        None => format!("当前文件名: {}\n无法识别扩展名，不会重命名", file_name),
     }
 }
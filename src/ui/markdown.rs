@@ -0,0 +1,195 @@
+use eframe::egui;
+
+// 插件说明是否使用了 Markdown 语法，普通文本仍按原样显示
+pub fn looks_like_markdown(text: &str) -> bool {
+    text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with('#')
+            || line.starts_with("- ")
+            || line.starts_with("* ")
+            || line.starts_with("```")
+            || numbered_item(line).is_some()
+    }) || text.contains("](") || text.contains("**") || text.contains('`')
+}
+
+// 去掉 Markdown 标记后的第一行，用于卡片上的简短说明
+pub fn summary_line(text: &str) -> String {
+    let line = text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("```"))
+        .unwrap_or_default();
+    let line = line.trim_start_matches('#').trim_start();
+    let line = line.strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .unwrap_or(line);
+    
+    parse_inline(line)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(text) | Span::Bold(text) | Span::Code(text) | Span::Link(text, _) => text,
+        })
+        .collect()
+}
+
+// 渲染常用的 Markdown 语法：标题、列表、代码块、粗体、行内代码和链接
+pub fn show_markdown(ui: &mut egui::Ui, text: &str) {
+    let mut in_code_block = false;
+    let mut code_block = String::new();
+    
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        
+        if trimmed.starts_with("```") {
+            if in_code_block {
+                show_code_block(ui, &code_block);
+                code_block.clear();
+            }
+            in_code_block = !in_code_block;
+            continue;
+        }
+        
+        if in_code_block {
+            code_block.push_str(line);
+            code_block.push('\n');
+            continue;
+        }
+        
+        if trimmed.is_empty() {
+            ui.add_space(6.0);
+        } else if let Some((level, heading)) = heading(trimmed) {
+            let size = match level {
+                1 => 20.0,
+                2 => 17.0,
+                _ => 15.0,
+            };
+            ui.add_space(4.0);
+            ui.label(egui::RichText::new(heading).strong().size(size));
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            show_list_item(ui, "•".to_string(), item);
+        } else if let Some((number, item)) = numbered_item(trimmed) {
+            show_list_item(ui, format!("{}.", number), item);
+        } else {
+            show_inline(ui, trimmed);
+        }
+    }
+    
+    // 未闭合的代码块也要显示出来
+    if in_code_block && !code_block.is_empty() {
+        show_code_block(ui, &code_block);
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(|text| (level, text.trim()))
+}
+
+fn numbered_item(line: &str) -> Option<(&str, &str)> {
+    let (number, rest) = line.split_once(". ")?;
+    if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        Some((number, rest))
+    } else {
+        None
+    }
+}
+
+fn show_list_item(ui: &mut egui::Ui, marker: String, text: &str) {
+    ui.horizontal_wrapped(|ui| {
+        ui.add_space(8.0);
+        ui.label(marker);
+        show_spans(ui, text);
+    });
+}
+
+fn show_code_block(ui: &mut egui::Ui, code: &str) {
+    egui::Frame::default()
+        .fill(ui.visuals().extreme_bg_color)
+        .inner_margin(6.0)
+        .rounding(4.0)
+        .show(ui, |ui| {
+            ui.label(egui::RichText::new(code.trim_end()).monospace());
+        });
+}
+
+fn show_inline(ui: &mut egui::Ui, text: &str) {
+    ui.horizontal_wrapped(|ui| {
+        show_spans(ui, text);
+    });
+}
+
+fn show_spans(ui: &mut egui::Ui, text: &str) {
+    ui.spacing_mut().item_spacing.x = 0.0;
+    
+    for span in parse_inline(text) {
+        match span {
+            Span::Text(text) => {
+                ui.label(text);
+            }
+            Span::Bold(text) => {
+                ui.label(egui::RichText::new(text).strong());
+            }
+            Span::Code(text) => {
+                ui.label(egui::RichText::new(text).code());
+            }
+            Span::Link(text, url) => {
+                ui.hyperlink_to(text, url);
+            }
+        }
+    }
+}
+
+enum Span {
+    Text(String),
+    Bold(String),
+    Code(String),
+    Link(String, String),
+}
+
+// 解析一行中的行内标记，无法配对的符号按普通文本保留
+fn parse_inline(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    
+    while !rest.is_empty() {
+        let parsed = if let Some(after) = rest.strip_prefix("**") {
+            after.split_once("**").map(|(bold, remaining)| (Span::Bold(bold.to_string()), remaining))
+        } else if let Some(after) = rest.strip_prefix('`') {
+            after.split_once('`').map(|(code, remaining)| (Span::Code(code.to_string()), remaining))
+        } else if let Some(after) = rest.strip_prefix('[') {
+            after.split_once("](").and_then(|(label, after_label)| {
+                after_label.split_once(')').map(|(url, remaining)| {
+                    (Span::Link(label.to_string(), url.to_string()), remaining)
+                })
+            })
+        } else {
+            None
+        };
+        
+        match parsed {
+            Some((span, remaining)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::Text(std::mem::take(&mut plain)));
+                }
+                spans.push(span);
+                rest = remaining;
+            }
+            None => {
+                let mut chars = rest.chars();
+                if let Some(c) = chars.next() {
+                    plain.push(c);
+                }
+                rest = chars.as_str();
+            }
+        }
+    }
+    
+    if !plain.is_empty() {
+        spans.push(Span::Text(plain));
+    }
+    
+    spans
+}
@@ -1,41 +1,250 @@
-use crate::plugins::{Plugin, PluginManager};
-use crate::config::AppConfig;
-use crate::downloader::Downloader;
-use crate::utils::BootDriveManager;
+use crate::plugins::{format_file_size, parse_size_to_bytes, DependencyResolution, Plugin, PluginCategory, PluginManager};
+use crate::config::{self, AppConfig, CompatibilityCheck, PendingBatch, PostDownloadAction, ReleaseChannel};
+use crate::downloader::{DownloadProgress, Downloader};
+use crate::installer::{check_free_space, deploy_plugin, DeployOptions};
+use crate::plugin_naming;
+use crate::utils::{is_dir_writable, is_system_drive, open_in_explorer, ram_disk_free_memory, sanitize_windows_filename, BootDriveManager};
 use crate::mode::PluginMode;
+use crate::network::is_offline_mode;
+use crate::ui::QueueEntry;
+use crate::ui::download_queue::show_task_progress;
+use crate::ui::action_hint::{download_unavailable, explain_unavailable, install_unavailable, PluginStatus};
+use crate::ui::markdown::{looks_like_markdown, show_markdown, summary_line};
 use eframe::egui;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::{AbortHandle, JoinHandle};
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// 写入系统盘前需要确认的操作：安装、更新或降级单个插件，或批量安装
+enum SystemDrivePrompt {
+    Install(Box<Plugin>),
+    Update(Box<Plugin>),
+    Bulk,
+}
+
+// 等待用户确认是否安装或更新适用平台不符的插件
+struct CompatibilityPrompt {
+    plugin: Plugin,
+    message: String,
+    // 更新或降级安装，确认后替换启动盘上的版本
+    update: bool,
+}
 
 #[derive(Clone)]
 #[allow(dead_code)]
 struct DownloadTask {
     plugin_name: String,
-    progress: Arc<RwLock<f32>>,
+    progress: Arc<RwLock<DownloadProgress>>,
+    // 取消时先设置，下载器据此删除未完成的文件
+    cancelled: Arc<AtomicBool>,
+    // 任务开始后用于取消，批量安装中尚在排队的任务为 None
+    abort_handle: Option<AbortHandle>,
     is_install: bool,
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum PluginStatus {
-    NotInstalled,
-    Installed,
-    UpdateAvailable,
+struct DependencyDialog {
+    plugin: Plugin,
+    resolution: DependencyResolution,
+    // false 时仅查看依赖，不提供安装按钮
+    install: bool,
+}
+
+// 批量安装的进度与结果统计
+#[derive(Clone, Default)]
+struct BulkSummary {
+    total: usize,
+    processed: usize,
+    installed: usize,
+    updated: usize,
+    reinstalled: usize,
+    skipped: usize,
+    failed: usize,
+    finished: bool,
+    // 每个插件的最终状态，按处理顺序排列
+    report: Vec<InstallReportEntry>,
+}
+
+// 批量安装结束后核对单个插件：处理结果、下载是否完整、启动盘上能否读取到且已启用
+#[derive(Clone)]
+struct InstallReportEntry {
+    name: String,
+    version: String,
+    // 成功时为处理方式，失败时为原因
+    result: Result<&'static str, String>,
+    // 实际下载的字节数与服务器声明的大小，没有下载时为 None
+    bytes: Option<(u64, u64)>,
+    // 插件市场提供了 SHA-256，下载成功即说明已通过校验
+    checksum: bool,
+    local: LocalState,
+}
+
+// 安装后重新读取启动盘得到的插件状态
+#[derive(Clone)]
+enum LocalState {
+    Enabled { version: String, current: bool },
+    Disabled,
+    // 文件不存在，或文件名无法解析为该插件
+    Missing,
+}
+
+impl InstallReportEntry {
+    fn new(plugin: &Plugin, result: Result<&'static str, String>, bytes: Option<(u64, u64)>, local: LocalState) -> Self {
+        Self {
+            name: plugin.name.clone(),
+            version: plugin.version.clone(),
+            result,
+            bytes,
+            checksum: plugin.sha256.is_some(),
+            local,
+        }
+    }
+    
+    // 处理成功、下载完整，且启动盘上的插件已启用、版本不低于市场版本
+    fn is_ok(&self) -> bool {
+        self.result.is_ok()
+            && self.bytes.is_none_or(|(downloaded, expected)| expected == 0 || downloaded == expected)
+            && matches!(self.local, LocalState::Enabled { current: true, .. })
+    }
+    
+    fn result_text(&self) -> String {
+        match &self.result {
+            Ok(action) => action.to_string(),
+            Err(e) => format!("失败: {}", e),
+        }
+    }
+    
+    fn bytes_text(&self) -> String {
+        match self.bytes {
+            Some((downloaded, 0)) => format!("{}（大小未知）", format_file_size(downloaded as i64)),
+            Some((downloaded, _)) if self.checksum && self.result.is_ok() => format!("{}，已通过 SHA-256 校验", format_file_size(downloaded as i64)),
+            Some((downloaded, expected)) if downloaded == expected => format!("{}，完整", format_file_size(downloaded as i64)),
+            Some((downloaded, expected)) => format!("{} / {}，不完整", downloaded, expected),
+            None => "未下载".to_string(),
+        }
+    }
+    
+    fn local_text(&self) -> String {
+        match &self.local {
+            LocalState::Enabled { version, current: true } => format!("已启用 {}", version),
+            LocalState::Enabled { version, current: false } => format!("已启用，但版本为 {}", version),
+            LocalState::Disabled => "已禁用".to_string(),
+            LocalState::Missing => "未找到".to_string(),
+        }
+    }
+    
+    fn log_line(&self) -> String {
+        format!(
+            "安装核对 {} {}: {}；下载 {}；启动盘上{}",
+            self.name, self.version, self.result_text(), self.bytes_text(), self.local_text(),
+        )
+    }
+}
+
+// 重新读取启动盘后按 ID 查找插件，找不到说明文件缺失或文件名无法解析
+fn check_local_state(plugin_manager: &Arc<RwLock<PluginManager>>, plugin: &Plugin) -> LocalState {
+    let manager = plugin_manager.read();
+    let plugin_id = plugin.get_plugin_id();
+    
+    if let Some(local) = manager.get_enabled_plugin_by_id(&plugin_id) {
+        LocalState::Enabled {
+            version: local.version.clone(),
+            current: manager.compare_versions(&local.version, &plugin.version) != std::cmp::Ordering::Less,
+        }
+    } else if manager.get_disabled_plugins().iter().any(|local| local.get_plugin_id() == plugin_id) {
+        LocalState::Disabled
+    } else {
+        LocalState::Missing
+    }
+}
+
+struct BulkInstallDialog {
+    title: String,
+    plugins: Vec<Plugin>,
+    // 与 plugins 一一对应，取消勾选的插件不会安装
+    selected: Vec<bool>,
+    // 打开对话框时启动盘的可用空间
+    available_space: Option<u64>,
+    force_reinstall: bool,
+    summary: Option<Arc<RwLock<BulkSummary>>>,
 }
 
+// 卡片宽度超过此值时把操作按钮放在右侧，否则放在下方。
+// 两个值都以逻辑点为单位，不受显示缩放影响
+const WIDE_CARD_MIN_WIDTH: f32 = 400.0;
+const CARD_ACTIONS_WIDTH: f32 = 180.0;
+// 卡片宽度低于此值时所有内容纵向排列，信息标签缩写
+const COMPACT_CARD_MAX_WIDTH: f32 = 250.0;
+
+// 最多保留的搜索记录条数
+const MAX_SEARCH_HISTORY: usize = 10;
+
+// 下载到 PE 内存盘时至少保留的剩余内存，避免占满内存导致 PE 崩溃
+const RAM_DISK_RESERVE: u64 = 512 * 1024 * 1024;
+
+// 下载完成提示的显示时长
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
 pub struct PluginsMarketPage {
     plugin_manager: Arc<RwLock<PluginManager>>,
     config: Arc<RwLock<AppConfig>>,
     runtime: Arc<Runtime>,
+    // 与其他窗口共用的下载名额，安装、更新和下载都需先取得名额
+    download_slots: Arc<Semaphore>,
     boot_drive_manager: Arc<RwLock<BootDriveManager>>,
     search_text: String,
     selected_category: String,
     last_selected_category: String,
     downloading_tasks: Arc<RwLock<HashMap<String, DownloadTask>>>,
+    // 以任务 ID 记录最近一次失败的错误信息，成功后清除
+    failed_tasks: Arc<RwLock<HashMap<String, String>>>,
     is_loading: bool,
     show_search_category: bool,
     mode: PluginMode,
+    dependency_dialog: Option<DependencyDialog>,
+    pages_loaded: Arc<AtomicUsize>,
+    // 加载过程中已解析完成的分类，每次加载使用新的实例，避免被中止的旧任务写入
+    partial_categories: Arc<RwLock<Vec<PluginCategory>>>,
+    // 部分列表是否已经显示在界面上，以及当时的插件数量，数量变化时重新显示
+    partial_shown: Arc<AtomicBool>,
+    partial_plugin_count: usize,
+    // 加载结束后的结果，None 表示仍在加载
+    fetch_result: Arc<RwLock<Option<Result<(), String>>>>,
+    // 当前列表所属的频道，设置中切换频道后重新加载
+    fetched_channel: ReleaseChannel,
+    // 当前列表是否在离线模式下加载，关闭离线模式后重新加载
+    fetched_offline: bool,
+    // 等待用户确认是否安装到系统盘的操作
+    system_drive_prompt: Option<SystemDrivePrompt>,
+    // 已确认可以安装到的系统盘及插件源，换了启动盘或插件源后需要重新确认
+    system_drive_confirmed: Option<(String, PluginMode)>,
+    compatibility_prompt: Option<CompatibilityPrompt>,
+    // 搜索结果已显示的批数，以及对应的关键词
+    search_pages: usize,
+    search_paged_text: String,
+    // 等待用户确认降级安装的插件，以及启动盘上的版本
+    downgrade_prompt: Option<(Plugin, String)>,
+    // 无法开始安装时显示的错误（标题、说明）
+    error_dialog: Option<(String, String)>,
+    bulk_dialog: Option<BulkInstallDialog>,
+    // 正在查看完整说明的插件
+    description_dialog: Option<Plugin>,
+    // 用户点击了"前往启动盘设置"，由主程序切换页面
+    settings_requested: bool,
+    // 下载完成提示及其显示时间
+    toast: Arc<RwLock<Option<(String, Instant)>>>,
+    // 插件列表加载任务，页面销毁时中止，避免旧任务写入即将被替换的管理器
+    fetch_handle: Option<JoinHandle<()>>,
+    // 上次查看市场后新增的插件 ID
+    new_plugins: HashSet<String>,
+    // 上次运行时中断的批量安装，等待用户选择继续或放弃
+    resume_prompt: Option<PendingBatch>,
+    // 设置改为保存在程序所在目录，首次显示页面时提示
+    portable_notice_pending: bool,
 }
 
 impl PluginsMarketPage {
@@ -43,152 +252,1572 @@ impl PluginsMarketPage {
         plugin_manager: Arc<RwLock<PluginManager>>,
         config: Arc<RwLock<AppConfig>>,
         runtime: Arc<Runtime>,
+        download_slots: Arc<Semaphore>,
         boot_drive_manager: Arc<RwLock<BootDriveManager>>,
         mode: PluginMode,
     ) -> Self {
-        let plugin_manager_clone = plugin_manager.clone();
-        let runtime_clone = runtime.clone();
-        let mode_clone = mode.clone();
-        
-        let page = Self {
+        let mut page = Self {
             plugin_manager: plugin_manager.clone(),
             config,
             runtime: runtime.clone(),
+            download_slots,
             boot_drive_manager,
             search_text: String::new(),
             selected_category: "推荐".to_string(),
             last_selected_category: "推荐".to_string(),
             downloading_tasks: Arc::new(RwLock::new(HashMap::new())),
+            failed_tasks: Arc::new(RwLock::new(HashMap::new())),
             is_loading: true,
             show_search_category: false,
             mode,
+            dependency_dialog: None,
+            pages_loaded: Arc::new(AtomicUsize::new(0)),
+            partial_categories: Arc::default(),
+            partial_shown: Arc::default(),
+            partial_plugin_count: 0,
+            fetch_result: Arc::new(RwLock::new(None)),
+            fetched_channel: ReleaseChannel::Stable,
+            fetched_offline: false,
+            system_drive_prompt: None,
+            system_drive_confirmed: None,
+            compatibility_prompt: None,
+            search_pages: 1,
+            search_paged_text: String::new(),
+            downgrade_prompt: None,
+            error_dialog: None,
+            bulk_dialog: None,
+            description_dialog: None,
+            settings_requested: false,
+            toast: Arc::new(RwLock::new(None)),
+            fetch_handle: None,
+            new_plugins: HashSet::new(),
+            resume_prompt: None,
+            portable_notice_pending: config::portable_data_dir().is_some(),
         };
         
-        runtime_clone.spawn(async move {
-            match PluginManager::fetch_plugins_async(mode_clone).await {
+        page.start_fetch(true);
+        page
+    }
+    
+    // use_cache 为 true 时优先使用足够新的缓存列表，手动重新加载时总是重新获取
+    fn start_fetch(&mut self, use_cache: bool) {
+        if let Some(handle) = self.fetch_handle.take() {
+            handle.abort();
+        }
+        
+        self.is_loading = true;
+        self.pages_loaded.store(0, Ordering::Relaxed);
+        self.partial_categories = Arc::default();
+        self.partial_shown = Arc::default();
+        self.partial_plugin_count = 0;
+        *self.fetch_result.write() = None;
+        
+        let plugin_manager = self.plugin_manager.clone();
+        let pages_loaded = self.pages_loaded.clone();
+        let partial = self.partial_categories.clone();
+        let partial_shown = self.partial_shown.clone();
+        let fetch_result = self.fetch_result.clone();
+        let toast = self.toast.clone();
+        let mode = self.mode;
+        let channel = self.config.read().channel;
+        let prefetch = self.config.read().prefetch_plugin_lists;
+        self.fetched_channel = channel;
+        self.fetched_offline = is_offline_mode();
+        
+        self.fetch_handle = Some(self.runtime.spawn(async move {
+            // 预取的列表足够新时直接使用，切换插件源时不必等待
+            if prefetch && use_cache {
+                if let Some(cache) = config::load_plugin_list_cache(mode, channel).filter(|cache| cache.is_fresh()) {
+                    plugin_manager.write().categories = cache.categories;
+                    *fetch_result.write() = Some(Ok(()));
+                    tokio::spawn(prefetch_other_modes(mode, channel));
+                    return;
+                }
+            }
+            
+            let result = PluginManager::fetch_plugins_async(mode, channel, pages_loaded, partial).await;
+            let fetched = result.is_ok();
+            
+            // 无法获取时以缓存的列表作为后备
+            let result = match result {
+                Ok(categories) => {
+                    if prefetch {
+                        let _ = config::save_plugin_list_cache(mode, channel, &categories);
+                    }
+                    Ok(categories)
+                }
+                Err(e) => {
+                    crate::app_log::log(format!("获取插件列表失败: {}", e));
+                    match config::load_plugin_list_cache(mode, channel).filter(|_| prefetch) {
+                        Some(cache) => {
+                            *toast.write() = Some((
+                                format!("{}，当前显示的是 {} 缓存的插件列表", e, cache.fetched_label()),
+                                Instant::now(),
+                            ));
+                            Ok(cache.categories)
+                        }
+                        None => Err(e),
+                    }
+                }
+            };
+            
+            // 与界面显示部分列表使用同一把锁，写入结果后界面不会再用部分列表覆盖
+            let mut manager = plugin_manager.write();
+            let result = match result {
                 Ok(categories) => {
-                    plugin_manager_clone.write().categories = categories;
+                    manager.categories = categories;
+                    Ok(())
+                }
+                Err(e) => {
+                    // 中途失败时清空已显示的部分列表，显示错误和重新加载按钮
+                    if partial_shown.load(Ordering::Relaxed) {
+                        manager.categories.clear();
+                    }
+                    Err(e.to_string())
+                }
+            };
+            *fetch_result.write() = Some(result);
+            drop(manager);
+            
+            if prefetch && fetched {
+                tokio::spawn(prefetch_other_modes(mode, channel));
+            }
+        }));
+    }
+    
+    pub fn show(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let channel = self.config.read().channel;
+        let offline = is_offline_mode();
+        if channel != self.fetched_channel || (self.fetched_offline && !offline) {
+            self.start_fetch(false);
+        }
+        
+        if std::mem::take(&mut self.portable_notice_pending) {
+            if let Some(dir) = config::portable_data_dir() {
+                *self.toast.write() = Some((format!("无法写入用户配置目录，设置将保存在 {}", dir.display()), Instant::now()));
+            }
+        }
+        
+        // 以加载任务结束为准，服务器返回空列表时也不会一直显示加载中
+        if self.is_loading && self.fetch_result.read().is_some() {
+            self.is_loading = false;
+            self.update_new_plugins();
+            if !offline {
+                self.resume_prompt = config::load_pending_batch(self.mode);
+            }
+            self.ensure_category_selected();
+        } else {
+            self.show_partial_categories();
+        }
+        
+        ui.horizontal(|ui| {
+            ui.heading(self.mode.get_plugin_market_name());
+            if channel == ReleaseChannel::Testing {
+                ui.label(
+                    egui::RichText::new(" 测试版 ")
+                        .color(egui::Color32::WHITE)
+                        .background_color(egui::Color32::from_rgb(255, 160, 0)),
+                )
+                .on_hover_text("当前使用测试频道，插件可能不稳定，可在设置中切换回正式版");
+            }
+            ui.add_space(20.0);
+            
+            ui.label("搜索：");
+            let response = ui.text_edit_singleline(&mut self.search_text);
+            
+            if response.changed() {
+                self.update_search_category();
+            }
+            
+            // 按回车或离开输入框时记录本次搜索
+            if response.lost_focus() {
+                self.record_search();
+            }
+            
+            if !self.search_text.is_empty() && ui.small_button("✕").on_hover_text("清空搜索").clicked() {
+                self.search_text.clear();
+                self.update_search_category();
+            }
+            
+            let history = self.config.read().search_history.clone();
+            if !history.is_empty() {
+                ui.menu_button("🕘", |ui| {
+                    for query in &history {
+                        if ui.button(query).clicked() {
+                            self.search_text = query.clone();
+                            self.update_search_category();
+                            self.record_search();
+                            ui.close_menu();
+                        }
+                    }
+                    
+                    ui.separator();
+                    if ui.button("清除搜索记录").clicked() {
+                        let mut config = self.config.write();
+                        config.search_history.clear();
+                        let _ = config.save();
+                        ui.close_menu();
+                    }
+                })
+                .response
+                .on_hover_text("最近搜索");
+            }
+            
+            let has_boot_drive = self.boot_drive_manager.read().get_current_drive().is_some();
+            if !self.is_loading && self.selected_category != "搜索" {
+                if has_boot_drive && ui.add_enabled(!offline, egui::Button::new("全部安装"))
+                    .on_hover_text("安装当前分类中的所有插件")
+                    .on_disabled_hover_text("离线模式下无法安装")
+                    .clicked()
+                {
+                    self.open_bulk_dialog();
+                }
+                
+                if ui.add_enabled(!offline, egui::Button::new("全部下载"))
+                    .on_hover_text("把当前分类的所有插件下载到以分类命名的文件夹，已存在的文件会跳过")
+                    .on_disabled_hover_text("离线模式下无法下载")
+                    .clicked()
+                {
+                    self.start_category_mirror();
+                }
+            }
+        });
+        
+        ui.separator();
+        
+        if self.boot_drive_manager.read().get_current_drive().is_none() {
+            self.show_no_boot_drive_banner(ui);
+        }
+        
+        // 关键词变化后重新从第一批结果开始显示
+        if self.search_text != self.search_paged_text {
+            self.search_paged_text = self.search_text.clone();
+            self.search_pages = 1;
+        }
+        
+        // 搜索结果在标签计数和列表中共用，每帧只计算一次
+        let search_limit = self.config.read().search_page_size.max(1) * self.search_pages;
+        let (search_results, search_total) = if self.list_ready() && self.show_search_category && !self.search_text.is_empty() {
+            self.plugin_manager.read().search_plugins(&self.search_text, search_limit)
+        } else {
+            (Vec::new(), 0)
+        };
+        
+        if self.list_ready() {
+            let categories = self.plugin_manager.read().get_categories().clone();
+            if !categories.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    if self.show_search_category {
+                        let search_label = if self.search_text.is_empty() {
+                            "搜索".to_string()
+                        } else {
+                            format!("搜索 ({})", search_total)
+                        };
+                        if ui.selectable_label(self.selected_category == "搜索", search_label).clicked() {
+                            self.selected_category = "搜索".to_string();
+                        }
+                    }
+                    
+                    for category in &categories {
+                        let label = format!("{} ({})", category.class, category.list.len());
+                        if ui.selectable_label(self.selected_category == category.class, label).clicked() {
+                            self.selected_category = category.class.clone();
+                            if !self.show_search_category || self.selected_category != "搜索" {
+                                self.last_selected_category = category.class.clone();
+                            }
+                        }
+                    }
+                });
+                ui.separator();
+            }
+        }
+        
+        egui::ScrollArea::vertical()
+            .id_salt("plugin_scroll")
+            .show(ui, |ui| {
+                if !self.list_ready() {
+                    ui.centered_and_justified(|ui| {
+                        ui.spinner();
+                        ui.label(self.loading_text());
+                    });
+                } else if self.plugin_manager.read().get_categories().is_empty() {
+                    self.show_empty_market(ui);
+                } else {
+                    // 已显示部分列表时，其余分类仍在加载
+                    if self.is_loading {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.weak(self.loading_text());
+                        });
+                    }
+                    
+                    let showing_search = self.selected_category == "搜索" && !self.search_text.is_empty();
+                    let plugins = if showing_search {
+                        search_results
+                    } else if self.selected_category != "搜索" {
+                        self.get_category_plugins()
+                    } else {
+                        Vec::new()
+                    };
+                    
+                    if plugins.is_empty() {
+                        ui.centered_and_justified(|ui| {
+                            if self.selected_category == "搜索" {
+                                let not_found_text = match self.mode {
+                                    PluginMode::HotPE => "未找到相关模块",
+                                    _ => "未找到相关插件",
+                                };
+                                ui.label(not_found_text);
+                            } else {
+                                let empty_text = match self.mode {
+                                    PluginMode::HotPE => "该分类暂无模块",
+                                    _ => "该分类暂无插件",
+                                };
+                                ui.label(empty_text);
+                            }
+                        });
+                    } else {
+                        // 与插件列表加载时使用相同的去重键
+                        let mut seen = HashSet::new();
+                        let shown = plugins.len();
+                        for plugin in plugins {
+                            if seen.insert(plugin.get_unique_key()) {
+                                self.show_plugin_card(ui, &plugin);
+                            }
+                        }
+                        
+                        if showing_search && shown < search_total {
+                            ui.add_space(5.0);
+                            ui.horizontal(|ui| {
+                                ui.weak(format!("已显示 {} / {} 个结果", shown, search_total));
+                                if ui.button("显示更多").clicked() {
+                                    self.search_pages += 1;
+                                }
+                            });
+                        }
+                    }
+                }
+            });
+        
+        self.show_dependency_dialog(ctx);
+        self.show_description_dialog(ctx);
+        self.show_system_drive_dialog(ctx);
+        self.show_compatibility_dialog(ctx);
+        self.show_downgrade_dialog(ctx);
+        self.show_error_dialog(ctx);
+        self.show_bulk_dialog(ctx);
+        self.show_resume_dialog(ctx);
+        self.show_toast(ctx);
+        
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    }
+    
+    // 列表加载完成，或已有部分分类可以显示
+    fn list_ready(&self) -> bool {
+        !self.is_loading || self.partial_plugin_count > 0
+    }
+    
+    fn loading_text(&self) -> String {
+        let loading_text = match self.mode {
+            PluginMode::HotPE => "正在加载模块列表",
+            _ => "正在加载插件列表",
+        };
+        let pages = self.pages_loaded.load(Ordering::Relaxed);
+        if pages > 0 {
+            format!("{}（已加载 {} 页）...", loading_text, pages)
+        } else {
+            format!("{}...", loading_text)
+        }
+    }
+    
+    // 加载过程中先显示已解析完成的分类，不必等整个列表加载结束
+    fn show_partial_categories(&mut self) {
+        if !self.is_loading {
+            return;
+        }
+        
+        let categories = {
+            let partial = self.partial_categories.read();
+            let count: usize = partial.iter().map(|c| c.list.len()).sum();
+            if count == 0 || count == self.partial_plugin_count {
+                return;
+            }
+            self.partial_plugin_count = count;
+            partial.clone()
+        };
+        
+        {
+            let mut manager = self.plugin_manager.write();
+            // 加载任务在同一把锁内写入完整列表和结果，结果已出现时不能再覆盖
+            if self.fetch_result.read().is_some() {
+                return;
+            }
+            manager.categories = categories;
+            self.partial_shown.store(true, Ordering::Relaxed);
+        }
+        
+        self.ensure_category_selected();
+    }
+    
+    // 之前选择的分类不在列表中时（例如服务器没有"推荐"分类）切换到第一个分类
+    fn ensure_category_selected(&mut self) {
+        let manager = self.plugin_manager.read();
+        let categories = manager.get_categories();
+        if categories.iter().any(|c| c.class == self.last_selected_category) {
+            return;
+        }
+        
+        if let Some(first_category) = categories.first() {
+            if self.selected_category != "搜索" {
+                self.selected_category = first_category.class.clone();
+            }
+            self.last_selected_category = first_category.class.clone();
+        }
+    }
+    
+    // 根据搜索框内容切换到搜索分类，清空后回到之前的分类
+    fn update_search_category(&mut self) {
+        if !self.search_text.is_empty() {
+            if !self.show_search_category {
+                self.show_search_category = true;
+                if self.selected_category != "搜索" {
+                    self.last_selected_category = self.selected_category.clone();
+                }
+                self.selected_category = "搜索".to_string();
+            }
+        } else {
+            if self.show_search_category {
+                self.show_search_category = false;
+                self.selected_category = self.last_selected_category.clone();
+            }
+        }
+    }
+    
+    // 把当前关键词放到搜索记录最前面，重复的只保留一条
+    fn record_search(&mut self) {
+        let query = self.search_text.trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        
+        let mut config = self.config.write();
+        if config.search_history.first() == Some(&query) {
+            return;
+        }
+        
+        config.search_history.retain(|existing| existing != &query);
+        config.search_history.insert(0, query);
+        config.search_history.truncate(MAX_SEARCH_HISTORY);
+        let _ = config.save();
+    }
+    
+    // 加载失败或插件源暂时没有内容
+    fn show_empty_market(&mut self, ui: &mut egui::Ui) {
+        let error = self.fetch_result.read().clone().and_then(|result| result.err());
+        let mut retry = false;
+        
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            match &error {
+                Some(e) => {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), "加载失败");
+                    ui.label(egui::RichText::new(e).small());
                 }
-                Err(_) => {
+                None => {
+                    let empty_text = match self.mode {
+                        PluginMode::HotPE => "暂无模块",
+                        _ => "暂无插件",
+                    };
+                    ui.label(empty_text);
                 }
             }
+            
+            ui.add_space(10.0);
+            if ui.add_enabled(!is_offline_mode(), egui::Button::new("重新加载"))
+                .on_disabled_hover_text("离线模式下无法加载插件列表")
+                .clicked()
+            {
+                retry = true;
+            }
+        });
+        
+        if retry {
+            self.start_fetch(false);
+        }
+    }
+    
+    // 与上次保存的插件列表比较，找出新增的插件，并把当前列表保存为新的基准
+    fn update_new_plugins(&mut self) {
+        let current: HashSet<String> = self.plugin_manager.read()
+            .get_categories()
+            .iter()
+            .flat_map(|category| category.list.iter())
+            .map(|plugin| plugin.get_plugin_id())
+            .collect();
+        
+        // 加载失败或列表为空时保留原有基准
+        if current.is_empty() {
+            return;
+        }
+        
+        // 首次运行没有基准，不标记任何插件
+        if let Some(known) = config::load_known_plugins(self.mode) {
+            self.new_plugins = current.difference(&known).cloned().collect();
+        }
+        
+        let _ = config::save_known_plugins(self.mode, &current);
+    }
+    
+    fn show_name_with_badge(&self, ui: &mut egui::Ui, plugin: &Plugin) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(&plugin.name).strong());
+            
+            if self.config.read().show_new_badges && self.new_plugins.contains(&plugin.get_plugin_id()) {
+                ui.label(
+                    egui::RichText::new(" 新 ")
+                        .small()
+                        .color(egui::Color32::WHITE)
+                        .background_color(egui::Color32::from_rgb(220, 60, 60)),
+                );
+            }
+        });
+    }
+    
+    // 主程序每帧调用一次，返回后清除请求
+    pub fn take_settings_request(&mut self) -> bool {
+        std::mem::take(&mut self.settings_requested)
+    }
+    
+    // 未选择启动盘时只能下载，提示用户选择或重新扫描启动盘
+    fn show_no_boot_drive_banner(&mut self, ui: &mut egui::Ui) {
+        egui::Frame::default()
+            .fill(ui.style().visuals.faint_bg_color)
+            .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 160, 0)))
+            .inner_margin(10.0)
+            .outer_margin(5.0)
+            .rounding(5.0)
+            .show(ui, |ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 160, 0), "未选择启动盘");
+                let item_name = match self.mode {
+                    PluginMode::HotPE => "模块",
+                    _ => "插件",
+                };
+                ui.label(format!("安装{}需要先选择启动盘，当前只能下载到本地", item_name));
+                
+                ui.horizontal(|ui| {
+                    if ui.button("前往启动盘设置").clicked() {
+                        self.settings_requested = true;
+                    }
+                    
+                    if ui.button("重新扫描启动盘").clicked() {
+                        let mut boot_drive_manager = self.boot_drive_manager.write();
+                        boot_drive_manager.reload();
+                        
+                        // 只有一个启动盘时直接选中，多个时交给用户选择
+                        let drives = boot_drive_manager.get_all_drives();
+                        match drives.len() {
+                            0 => {}
+                            1 => boot_drive_manager.set_current_drive(drives[0].letter.clone()),
+                            _ => self.settings_requested = true,
+                        }
+                    }
+                });
+            });
+        
+        ui.add_space(5.0);
+    }
+    
+    fn show_toast(&mut self, ctx: &egui::Context) {
+        let mut toast = self.toast.write();
+        let Some((message, shown_at)) = toast.as_ref() else {
+            return;
+        };
+        
+        if shown_at.elapsed() > TOAST_DURATION {
+            *toast = None;
+            return;
+        }
+        
+        egui::Area::new(egui::Id::new("market_toast").with(self.mode))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-20.0, -20.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(message);
+                });
+            });
+    }
+    
+    // 当前启动盘是系统盘且尚未对这个启动盘和插件源确认过
+    fn needs_system_drive_confirmation(&self) -> bool {
+        let Some(drive) = self.boot_drive_manager.read().get_current_drive() else {
+            return false;
+        };
+        is_system_drive(&drive) && self.system_drive_confirmed != Some((drive, self.mode))
+    }
+    
+    fn request_install(&mut self, plugin: &Plugin) {
+        if self.needs_system_drive_confirmation() {
+            self.system_drive_prompt = Some(SystemDrivePrompt::Install(Box::new(plugin.clone())));
+            return;
+        }
+        
+        if self.needs_compatibility_confirmation(plugin, false) {
+            return;
+        }
+        
+        self.proceed_install(plugin);
+    }
+    
+    // 更新、重试更新和确认后的降级安装同样先确认是否写入系统盘，以及是否安装适用平台不符的版本
+    fn request_update(&mut self, plugin: &Plugin) {
+        if self.needs_system_drive_confirmation() {
+            self.system_drive_prompt = Some(SystemDrivePrompt::Update(Box::new(plugin.clone())));
+            return;
+        }
+        
+        if self.needs_compatibility_confirmation(plugin, true) {
+            return;
+        }
+        
+        self.update_plugin(plugin.clone());
+    }
+    
+    // 设置为提示时，插件标注的适用平台与当前插件源不符则弹出确认框并返回 true
+    fn needs_compatibility_confirmation(&mut self, plugin: &Plugin, update: bool) -> bool {
+        if self.config.read().compatibility_check != CompatibilityCheck::Warn {
+            return false;
+        }
+        
+        let Some(message) = plugin.compatibility_error(self.mode) else {
+            return false;
+        };
+        self.compatibility_prompt = Some(CompatibilityPrompt { plugin: plugin.clone(), message, update });
+        true
+    }
+    
+    fn proceed_install(&mut self, plugin: &Plugin) {
+        if plugin.dependencies.is_empty() {
+            self.install_plugin(plugin.clone());
+        } else {
+            self.open_dependency_dialog(plugin, true);
+        }
+    }
+    
+    fn show_compatibility_dialog(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &self.compatibility_prompt else {
+            return;
+        };
+        let (verb, confirm_text) = if prompt.update {
+            ("更新", "仍然更新")
+        } else {
+            ("安装", "仍然安装")
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("插件可能不兼容")
+            .id(egui::Id::new("compatibility_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 160, 0), &prompt.message);
+                ui.label(format!("{}后可能无法正常加载，确定要继续吗？", verb));
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(confirm_text).clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            match self.compatibility_prompt.take() {
+                Some(prompt) if prompt.update => self.update_plugin(prompt.plugin),
+                Some(prompt) => self.proceed_install(&prompt.plugin),
+                None => {}
+            }
+        } else if cancelled {
+            self.compatibility_prompt = None;
+        }
+    }
+    
+    fn show_downgrade_dialog(&mut self, ctx: &egui::Context) {
+        let Some((plugin, local_version)) = &self.downgrade_prompt else {
+            return;
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("降级安装？")
+            .id(egui::Id::new("downgrade_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "启动盘上的 {} 版本为 {}，比市场中的 {} 新。",
+                    plugin.name, local_version, plugin.version,
+                ));
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 160, 0),
+                    "降级安装会用市场中的版本替换启动盘上的版本。",
+                );
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("降级安装").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            if let Some((plugin, _)) = self.downgrade_prompt.take() {
+                self.request_update(&plugin);
+            }
+        } else if cancelled {
+            self.downgrade_prompt = None;
+        }
+    }
+    
+    fn show_error_dialog(&mut self, ctx: &egui::Context) {
+        let Some((title, message)) = &self.error_dialog else {
+            return;
+        };
+        
+        let mut closed = false;
+        
+        egui::Window::new(title.as_str())
+            .id(egui::Id::new("error_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), message);
+                ui.separator();
+                if ui.button("确定").clicked() {
+                    closed = true;
+                }
+            });
+        
+        if closed {
+            self.error_dialog = None;
+        }
+    }
+    
+    // 开始安装或更新前检查启动盘空间，不足时提示并返回 false
+    fn has_free_space_for(&mut self, plugin: &Plugin) -> bool {
+        let Some(drive_letter) = self.boot_drive_manager.read().get_current_drive() else {
+            return true;
+        };
+        
+        match check_free_space(&drive_letter, plugin) {
+            Ok(()) => true,
+            Err(message) => {
+                self.error_dialog = Some(("启动盘空间不足".to_string(), message));
+                false
+            }
+        }
+    }
+    
+    fn show_system_drive_dialog(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &self.system_drive_prompt else {
+            return;
+        };
+        let target = match prompt {
+            SystemDrivePrompt::Install(plugin) | SystemDrivePrompt::Update(plugin) => plugin.name.clone(),
+            SystemDrivePrompt::Bulk => "所选插件".to_string(),
+        };
+        
+        let drive = self.boot_drive_manager.read().get_current_drive().unwrap_or_default();
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        egui::Window::new("安装到系统盘？")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 100, 100),
+                    format!("当前启动盘 {} 是正在运行的系统盘", drive),
+                );
+                ui.label(format!(
+                    "继续安装会在 {} 中写入 {}，这通常不是期望的操作。",
+                    crate::utils::plugin_dir(&drive, self.mode).display(),
+                    target,
+                ));
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("仍然安装").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+        
+        if confirmed {
+            self.system_drive_confirmed = Some((drive, self.mode));
+            match self.system_drive_prompt.take() {
+                Some(SystemDrivePrompt::Install(plugin)) => self.request_install(&plugin),
+                Some(SystemDrivePrompt::Update(plugin)) => self.request_update(&plugin),
+                Some(SystemDrivePrompt::Bulk) => self.request_bulk_install(),
+                None => {}
+            }
+        } else if cancelled {
+            self.system_drive_prompt = None;
+        }
+    }
+    
+    fn show_resume_dialog(&mut self, ctx: &egui::Context) {
+        let Some(batch) = &self.resume_prompt else {
+            return;
+        };
+        
+        let current_drive = self.boot_drive_manager.read().get_current_drive();
+        let on_target_drive = current_drive.as_deref().is_some_and(|drive| drive.eq_ignore_ascii_case(&batch.drive));
+        let busy = self.bulk_dialog.is_some();
+        
+        let mut resume = false;
+        let mut discard = false;
+        let mut later = false;
+        
+        egui::Window::new("继续上次的安装")
+            .id(egui::Id::new("resume_batch_dialog").with(self.mode))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "上次向 {} 批量安装时中断，还有 {} 个插件未安装",
+                    batch.drive,
+                    batch.plugin_ids.len(),
+                ));
+                if !on_target_drive {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 160, 0),
+                        format!("当前启动盘不是 {}，请先切换启动盘再继续", batch.drive),
+                    );
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(on_target_drive && !busy, egui::Button::new("继续安装")).clicked() {
+                        resume = true;
+                    }
+                    if ui.button("稍后").on_hover_text("下次启动时再询问").clicked() {
+                        later = true;
+                    }
+                    if ui.button("放弃").clicked() {
+                        discard = true;
+                    }
+                });
+            });
+        
+        if resume {
+            if let Some(batch) = self.resume_prompt.take() {
+                self.open_resume_bulk_dialog(batch);
+            }
+        } else if discard {
+            self.resume_prompt = None;
+            config::clear_pending_batch(self.mode);
+        } else if later {
+            self.resume_prompt = None;
+        }
+    }
+    
+    // 用记录的插件打开批量安装对话框，市场中已不存在的插件无法继续安装
+    fn open_resume_bulk_dialog(&mut self, batch: PendingBatch) {
+        let plugins: Vec<Plugin> = {
+            let manager = self.plugin_manager.read();
+            batch.plugin_ids.iter()
+                .filter_map(|id| manager.find_market_plugin_by_id(id))
+                .collect()
+        };
+        
+        if plugins.is_empty() {
+            config::clear_pending_batch(self.mode);
+            *self.toast.write() = Some(("上次未安装的插件已不在市场中".to_string(), Instant::now()));
+            return;
+        }
+        
+        let available_space = BootDriveManager::get_free_space(&batch.drive);
+        
+        self.bulk_dialog = Some(BulkInstallDialog {
+            title: "继续上次的安装".to_string(),
+            selected: vec![true; plugins.len()],
+            plugins,
+            available_space,
+            force_reinstall: batch.force_reinstall,
+            summary: None,
+        });
+    }
+    
+    fn open_bulk_dialog(&mut self) {
+        let plugins = self.get_category_plugins();
+        let available_space = self.boot_drive_manager.read()
+            .get_current_drive()
+            .and_then(|drive| BootDriveManager::get_free_space(&drive));
+        
+        self.bulk_dialog = Some(BulkInstallDialog {
+            title: self.selected_category.clone(),
+            selected: vec![true; plugins.len()],
+            plugins,
+            available_space,
+            force_reinstall: false,
+            summary: None,
+        });
+    }
+    
+    fn show_bulk_dialog(&mut self, ctx: &egui::Context) {
+        let plugin_manager = self.plugin_manager.clone();
+        let compatibility_check = self.config.read().compatibility_check;
+        let mode = self.mode;
+        let Some(dialog) = &mut self.bulk_dialog else {
+            return;
+        };
+        
+        let mut start = false;
+        let mut close = false;
+        
+        egui::Window::new(format!("全部安装 - {}", dialog.title))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match &dialog.summary {
+                    None => {
+                        let installed = dialog.plugins.iter()
+                            .filter(|p| plugin_manager.read().get_enabled_plugin_by_id(&p.get_plugin_id()).is_some())
+                            .count();
+                        
+                        ui.label(format!("共 {} 个插件，其中 {} 个已安装", dialog.plugins.len(), installed));
+                        ui.checkbox(&mut dialog.force_reinstall, "强制重新安装")
+                            .on_hover_text("删除并重新下载已安装的插件，可用于修复损坏的文件");
+                        if !dialog.force_reinstall {
+                            ui.label(egui::RichText::new("已安装且为最新版本的插件将被跳过").small());
+                        }
+                        
+                        let incompatible = dialog.plugins.iter()
+                            .filter(|p| p.compatibility_error(mode).is_some())
+                            .count();
+                        if incompatible > 0 && compatibility_check != CompatibilityCheck::Off {
+                            let note = if compatibility_check == CompatibilityCheck::Block {
+                                format!("其中 {} 个插件的适用平台与当前插件源不符，安装时会被拒绝", incompatible)
+                            } else {
+                                format!("其中 {} 个插件的适用平台与当前插件源不符，安装后可能无法正常加载", incompatible)
+                            };
+                            ui.colored_label(egui::Color32::from_rgb(255, 160, 0), note);
+                        }
+                        
+                        // 与单独安装一样自动安装所需的依赖
+                        let (planned, missing) = bulk_install_plan(&plugin_manager.read(), &dialog.plugins, &dialog.selected);
+                        let dependencies: Vec<&Plugin> = planned.iter()
+                            .filter(|p| {
+                                !dialog.plugins.iter()
+                                    .zip(&dialog.selected)
+                                    .any(|(plugin, selected)| *selected && plugin.get_plugin_id() == p.get_plugin_id())
+                            })
+                            .collect();
+                        if !dependencies.is_empty() {
+                            ui.label(format!("另需安装 {} 个依赖插件", dependencies.len()));
+                        }
+                        if !missing.is_empty() {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 160, 0),
+                                format!("市场中找不到以下依赖：{}", missing.join("、")),
+                            );
+                        }
+                        
+                        ui.separator();
+                        
+                        // 只统计实际需要下载的插件，会被跳过的不计入
+                        let mut total_size = 0u64;
+                        let mut unknown_count = 0;
+                        for dependency in &dependencies {
+                            match parse_size_to_bytes(&dependency.size) {
+                                Some(bytes) => total_size += bytes,
+                                None => unknown_count += 1,
+                            }
+                        }
+                        egui::ScrollArea::vertical()
+                            .id_salt("bulk_install_list")
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                for (plugin, selected) in dialog.plugins.iter().zip(dialog.selected.iter_mut()) {
+                                    let size = parse_size_to_bytes(&plugin.size);
+                                    let label = format!("{} ({})", plugin.name, plugin.size);
+                                    ui.checkbox(selected, label);
+                                    
+                                    let skipped = !dialog.force_reinstall && is_current_version(&plugin_manager, plugin);
+                                    if *selected && !skipped {
+                                        match size {
+                                            Some(bytes) => total_size += bytes,
+                                            None => unknown_count += 1,
+                                        }
+                                    }
+                                }
+                            });
+                        
+                        ui.separator();
+                        ui.label(format!("预计下载：{}", format_file_size(total_size as i64)));
+                        if unknown_count > 0 {
+                            ui.label(egui::RichText::new(format!("另有 {} 个插件大小未知", unknown_count)).small());
+                        }
+                        
+                        let fits = match dialog.available_space {
+                            Some(available) => {
+                                ui.label(format!("启动盘可用空间：{}", format_file_size(available as i64)));
+                                total_size <= available
+                            }
+                            None => true,
+                        };
+                        if !fits {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 100, 100),
+                                "启动盘空间不足，请取消勾选部分插件",
+                            );
+                        }
+                        
+                        let any_selected = dialog.selected.iter().any(|selected| *selected);
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(fits && any_selected, egui::Button::new("开始安装")).clicked() {
+                                start = true;
+                            }
+                            if ui.button("取消").clicked() {
+                                close = true;
+                            }
+                        });
+                    }
+                    Some(summary) => {
+                        let summary = summary.read().clone();
+                        
+                        if summary.finished {
+                            ui.label(format!(
+                                "安装完成：新安装 {}，更新 {}，重新安装 {}，跳过 {}，失败 {}",
+                                summary.installed, summary.updated, summary.reinstalled, summary.skipped, summary.failed,
+                            ));
+                            show_install_report(ui, &summary.report);
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                if ui.button("关闭").clicked() {
+                                    close = true;
+                                }
+                                if ui.button("写入日志")
+                                    .on_hover_text("把核对结果写入运行日志，可随诊断包一起导出")
+                                    .clicked()
+                                {
+                                    for entry in &summary.report {
+                                        crate::app_log::log(entry.log_line());
+                                    }
+                                }
+                            });
+                        } else {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(format!("正在安装 {}/{}", summary.processed, summary.total));
+                            });
+                        }
+                    }
+                }
+            });
+        
+        if start {
+            self.request_bulk_install();
+        } else if close {
+            self.bulk_dialog = None;
+        }
+    }
+    
+    // 与单独安装一样，安装到系统盘前先确认
+    fn request_bulk_install(&mut self) {
+        if self.needs_system_drive_confirmation() {
+            self.system_drive_prompt = Some(SystemDrivePrompt::Bulk);
+            return;
+        }
+        
+        self.start_bulk_install();
+    }
+    
+    fn start_bulk_install(&mut self) {
+        let drive_letter = match self.boot_drive_manager.read().get_current_drive() {
+            Some(drive) => drive,
+            None => return,
+        };
+        
+        let Some(dialog) = &self.bulk_dialog else {
+            return;
+        };
+        
+        let force_reinstall = dialog.force_reinstall;
+        let safe_install = self.config.read().safe_install;
+        let block_incompatible = self.config.read().compatibility_check == CompatibilityCheck::Block;
+        let (planned, missing) = bulk_install_plan(&self.plugin_manager.read(), &dialog.plugins, &dialog.selected);
+        if !missing.is_empty() {
+            crate::app_log::log(format!("批量安装：市场中找不到以下依赖：{}", missing.join("、")));
+        }
+        let jobs: Vec<(Plugin, String)> = planned.into_iter()
+            .map(|plugin| {
+                let filename = self.generate_plugin_filename(&plugin);
+                (plugin, filename)
+            })
+            .collect();
+        
+        let summary = Arc::new(RwLock::new(BulkSummary {
+            total: jobs.len(),
+            ..Default::default()
+        }));
+        
+        // 记录尚未处理的插件，中途崩溃或重启后可继续
+        let mut pending = PendingBatch {
+            drive: drive_letter.clone(),
+            plugin_ids: jobs.iter().map(|(plugin, _)| plugin.get_plugin_id()).collect(),
+            force_reinstall,
+        };
+        if let Some(dialog) = &mut self.bulk_dialog {
+            dialog.summary = Some(summary.clone());
+        }
+        
+        // 先把全部插件放进下载队列，便于查看和取消排队中的任务。
+        // 已在单独安装的插件不再重复加入
+        let download_threads = self.config.read().download_threads;
+        let download_retries = self.config.read().download_retries;
+        let mut queued = Vec::new();
+        {
+            let mut tasks = self.downloading_tasks.write();
+            for (plugin, filename) in jobs {
+                let task_id = format!("{}_install", plugin.get_plugin_id());
+                if tasks.contains_key(&task_id) {
+                    pending.plugin_ids.retain(|id| *id != plugin.get_plugin_id());
+                    let mut summary = summary.write();
+                    summary.skipped += 1;
+                    summary.processed += 1;
+                    summary.report.push(InstallReportEntry::new(
+                        &plugin,
+                        Ok("正在单独安装，跳过"),
+                        None,
+                        check_local_state(&self.plugin_manager, &plugin),
+                    ));
+                    continue;
+                }
+                
+                let downloader = Arc::new(Downloader::new(download_threads, download_retries));
+                tasks.insert(task_id.clone(), DownloadTask {
+                    plugin_name: plugin.name.clone(),
+                    progress: downloader.progress_handle(),
+                    cancelled: downloader.cancel_flag(),
+                    abort_handle: None,
+                    is_install: true,
+                });
+                queued.push((task_id, plugin, filename, downloader));
+            }
+        }
+        
+        let plugin_manager = self.plugin_manager.clone();
+        let downloading_tasks = self.downloading_tasks.clone();
+        let failed_tasks = self.failed_tasks.clone();
+        let mode = self.mode;
+        let download_slots = self.download_slots.clone();
+        let _ = config::save_pending_batch(mode, &pending);
+        
+        self.runtime.spawn(async move {
+            for (task_id, plugin, filename, downloader) in queued {
+                let plugin_id = plugin.get_plugin_id();
+                
+                // 排队期间已在下载队列中取消
+                if !downloading_tasks.read().contains_key(&task_id) {
+                    mark_batch_processed(mode, &mut pending, &plugin_id);
+                    let local = check_local_state(&plugin_manager, &plugin);
+                    let mut summary = summary.write();
+                    summary.skipped += 1;
+                    summary.processed += 1;
+                    summary.report.push(InstallReportEntry::new(&plugin, Ok("已取消"), None, local));
+                    continue;
+                }
+                
+                let is_current = {
+                    let manager = plugin_manager.read();
+                    manager.get_enabled_plugin_by_id(&plugin_id).map(|local| {
+                        manager.compare_versions(&local.version, &plugin.version) != std::cmp::Ordering::Less
+                    })
+                };
+                
+                if is_current == Some(true) && !force_reinstall {
+                    downloading_tasks.write().remove(&task_id);
+                    mark_batch_processed(mode, &mut pending, &plugin_id);
+                    let local = check_local_state(&plugin_manager, &plugin);
+                    let mut summary = summary.write();
+                    summary.skipped += 1;
+                    summary.processed += 1;
+                    summary.report.push(InstallReportEntry::new(&plugin, Ok("已是最新，跳过"), None, local));
+                    continue;
+                }
+                
+                // 替换已安装的版本时，新文件完整写入启动盘后才删除旧文件
+                let options = DeployOptions {
+                    replace_existing: is_current.is_some(),
+                    safe_install,
+                    block_incompatible,
+                };
+                
+                // 每个插件单独运行，取消时只中止当前插件，批量安装继续处理后面的插件
+                let handle = {
+                    let plugin_manager = plugin_manager.clone();
+                    let drive_letter = drive_letter.clone();
+                    let downloader = downloader.clone();
+                    let plugin = plugin.clone();
+                    let download_slots = download_slots.clone();
+                    tokio::spawn(async move {
+                        let _permit = download_slots.acquire_owned().await;
+                        deploy_plugin(&downloader, &plugin_manager, mode, &drive_letter, &plugin, &filename, options).await
+                    })
+                };
+                
+                let cancelled = match downloading_tasks.write().get_mut(&task_id) {
+                    Some(task) => {
+                        task.abort_handle = Some(handle.abort_handle());
+                        false
+                    }
+                    None => true,
+                };
+                if cancelled {
+                    handle.abort();
+                }
+                
+                let result = handle.await;
+                
+                downloading_tasks.write().remove(&task_id);
+                mark_batch_processed(mode, &mut pending, &plugin_id);
+                
+                let progress = downloader.get_progress();
+                let bytes = (progress.current > 0).then_some((progress.current, progress.total));
+                let local = check_local_state(&plugin_manager, &plugin);
+                
+                let mut summary = summary.write();
+                summary.processed += 1;
+                let entry_result = match result {
+                    Ok(Ok(_)) => {
+                        failed_tasks.write().remove(&task_id);
+                        match is_current {
+                            Some(true) => {
+                                summary.reinstalled += 1;
+                                Ok("重新安装")
+                            }
+                            Some(false) => {
+                                summary.updated += 1;
+                                Ok("更新")
+                            }
+                            None => {
+                                summary.installed += 1;
+                                Ok("新安装")
+                            }
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        failed_tasks.write().insert(task_id, e.clone());
+                        summary.failed += 1;
+                        Err(e)
+                    }
+                    // 用户取消
+                    Err(_) => {
+                        summary.skipped += 1;
+                        Ok("已取消")
+                    }
+                };
+                summary.report.push(InstallReportEntry::new(&plugin, entry_result, bytes, local));
+            }
+            
+            config::clear_pending_batch(mode);
+            summary.write().finished = true;
+        });
+    }
+    
+    // 把当前分类的全部插件下载到所选文件夹中以分类命名的子文件夹，用于离线保存。
+    // 已存在的文件跳过，完成后提示下载结果
+    fn start_category_mirror(&mut self) {
+        use rfd::AsyncFileDialog;
+        
+        let category = self.selected_category.clone();
+        let extension = self.mode.get_enabled_extension();
+        let jobs: Vec<(Plugin, String)> = self.get_category_plugins()
+            .into_iter()
+            .map(|plugin| {
+                let filename = format!("{}.{}", self.generate_plugin_filename(&plugin), extension);
+                (plugin, filename)
+            })
+            .collect();
+        if jobs.is_empty() {
+            return;
+        }
+        
+        let default_download_path = self.config.read().download_path(self.mode);
+        let download_threads = self.config.read().download_threads;
+        let download_retries = self.config.read().download_retries;
+        let downloading_tasks = self.downloading_tasks.clone();
+        let failed_tasks = self.failed_tasks.clone();
+        let toast = self.toast.clone();
+        let download_slots = self.download_slots.clone();
+        
+        self.runtime.spawn(async move {
+            let mut dialog = AsyncFileDialog::new().set_title("选择保存位置");
+            if let Some(path) = &default_download_path {
+                dialog = dialog.set_directory(path);
+            }
+            let Some(handle) = dialog.pick_folder().await else {
+                return;
+            };
+            
+            let folder = handle.path().join(sanitize_windows_filename(&category));
+            if let Err(e) = std::fs::create_dir_all(&folder) {
+                *toast.write() = Some((format!("创建文件夹失败: {}", e), Instant::now()));
+                return;
+            }
+            
+            // 先把需要下载的插件全部放进下载队列，便于查看和取消
+            let mut skipped = 0;
+            let mut queued = Vec::new();
+            {
+                let mut tasks = downloading_tasks.write();
+                for (plugin, filename) in jobs {
+                    let target = folder.join(&filename);
+                    let task_id = format!("{}_mirror", plugin.get_plugin_id());
+                    if std::fs::metadata(&target).is_ok_and(|metadata| metadata.len() > 0) || tasks.contains_key(&task_id) {
+                        skipped += 1;
+                        continue;
+                    }
+                    
+                    let downloader = Arc::new(Downloader::new(download_threads, download_retries));
+                    tasks.insert(task_id.clone(), DownloadTask {
+                        plugin_name: plugin.name.clone(),
+                        progress: downloader.progress_handle(),
+                        cancelled: downloader.cancel_flag(),
+                        abort_handle: None,
+                        is_install: false,
+                    });
+                    queued.push((task_id, plugin, target, downloader));
+                }
+            }
+            
+            let mut handles = Vec::new();
+            
+            for (task_id, plugin, target, downloader) in queued {
+                let Ok(permit) = download_slots.clone().acquire_owned().await else {
+                    break;
+                };
+                
+                // 排队期间已在下载队列中取消
+                if !downloading_tasks.read().contains_key(&task_id) {
+                    skipped += 1;
+                    continue;
+                }
+                
+                // 下载时先写入 .part 文件，中断后不会被当作已下载的文件跳过
+                let handle = {
+                    let downloading_tasks = downloading_tasks.clone();
+                    let task_id = task_id.clone();
+                    tokio::spawn(async move {
+                        let _permit = permit;
+                        let result = downloader.download(&plugin.link, target, plugin.sha256.as_deref()).await
+                            .map_err(|e| e.to_string());
+                        downloading_tasks.write().remove(&task_id);
+                        result
+                    })
+                };
+                
+                match downloading_tasks.write().get_mut(&task_id) {
+                    Some(task) => task.abort_handle = Some(handle.abort_handle()),
+                    None => handle.abort(),
+                }
+                handles.push((task_id, handle));
+            }
+            
+            let mut downloaded = 0;
+            let mut failed = 0;
+            for (task_id, handle) in handles {
+                match handle.await {
+                    Ok(Ok(())) => {
+                        failed_tasks.write().remove(&task_id);
+                        downloaded += 1;
+                    }
+                    Ok(Err(e)) => {
+                        crate::app_log::log(format!("下载 {} 失败: {}", task_id, e));
+                        failed_tasks.write().insert(task_id.clone(), e);
+                        failed += 1;
+                    }
+                    // 用户取消
+                    Err(_) => skipped += 1,
+                }
+            }
+            
+            *toast.write() = Some((
+                format!("{} 下载完成：下载 {}，跳过 {}，失败 {}", category, downloaded, skipped, failed),
+                Instant::now(),
+            ));
+        });
+    }
+    
+    fn open_dependency_dialog(&mut self, plugin: &Plugin, install: bool) {
+        let resolution = self.plugin_manager.read().resolve_dependencies(plugin);
+        self.dependency_dialog = Some(DependencyDialog {
+            plugin: plugin.clone(),
+            resolution,
+            install,
+        });
+    }
+    
+    fn show_copy_info_menu(&self, ui: &mut egui::Ui, plugin: &Plugin) {
+        ui.menu_button("复制插件信息", |ui| {
+            let copied = if ui.button("纯文本").clicked() {
+                Some(plugin.share_text())
+            } else if ui.button("Markdown").clicked() {
+                Some(plugin.share_markdown())
+            } else {
+                None
+            };
+            
+            if let Some(text) = copied {
+                ui.ctx().copy_text(text);
+                *self.toast.write() = Some(("已复制插件信息".to_string(), Instant::now()));
+                ui.close_menu();
+            }
         });
-        
-        page
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        if self.is_loading && !self.plugin_manager.read().get_categories().is_empty() {
-            self.is_loading = false;
-            if !self.plugin_manager.read().get_categories().iter().any(|c| c.class == "推荐") {
-                if let Some(first_category) = self.plugin_manager.read().get_categories().first() {
-                    self.selected_category = first_category.class.clone();
-                    self.last_selected_category = first_category.class.clone();
-                }
-            }
+    // Markdown 格式的说明在卡片上只显示第一行，完整内容在说明窗口中查看
+    fn show_description(&mut self, ui: &mut egui::Ui, plugin: &Plugin) {
+        if !looks_like_markdown(&plugin.describe) {
+            ui.label(&plugin.describe);
+            return;
         }
         
-        ui.horizontal(|ui| {
-            ui.heading(self.mode.get_plugin_market_name());
-            ui.add_space(20.0);
-            
-            ui.label("搜索：");
-            let response = ui.text_edit_singleline(&mut self.search_text);
-            
-            if response.changed() {
-                if !self.search_text.is_empty() {
-                    if !self.show_search_category {
-                        self.show_search_category = true;
-                        if self.selected_category != "搜索" {
-                            self.last_selected_category = self.selected_category.clone();
-                        }
-                        self.selected_category = "搜索".to_string();
-                    }
-                } else {
-                    if self.show_search_category {
-                        self.show_search_category = false;
-                        self.selected_category = self.last_selected_category.clone();
-                    }
-                }
+        ui.horizontal_wrapped(|ui| {
+            ui.label(summary_line(&plugin.describe));
+            if ui.small_button("查看说明").clicked() {
+                self.description_dialog = Some(plugin.clone());
             }
         });
+    }
+    
+    fn show_description_dialog(&mut self, ctx: &egui::Context) {
+        let Some(plugin) = &self.description_dialog else {
+            return;
+        };
         
-        ui.separator();
+        let mut open = true;
+        let mut close = false;
         
-        if !self.is_loading {
-            let categories = self.plugin_manager.read().get_categories().clone();
-            if !categories.is_empty() {
-                ui.horizontal_wrapped(|ui| {
-                    if self.show_search_category {
-                        if ui.selectable_label(self.selected_category == "搜索", "搜索").clicked() {
-                            self.selected_category = "搜索".to_string();
-                        }
-                    }
-                    
-                    for category in &categories {
-                        if ui.selectable_label(self.selected_category == category.class, &category.class).clicked() {
-                            self.selected_category = category.class.clone();
-                            if !self.show_search_category || self.selected_category != "搜索" {
-                                self.last_selected_category = category.class.clone();
-                            }
-                        }
-                    }
-                });
+        egui::Window::new(format!("{} 的说明", plugin.name))
+            .id(egui::Id::new("plugin_description").with(self.mode))
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("plugin_description_scroll")
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        show_markdown(ui, &plugin.describe);
+                    });
+                
                 ui.separator();
-            }
+                if ui.button("关闭").clicked() {
+                    close = true;
+                }
+            });
+        
+        if !open || close {
+            self.description_dialog = None;
         }
+    }
+    
+    fn show_dependency_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &self.dependency_dialog else {
+            return;
+        };
         
-        egui::ScrollArea::vertical()
-            .id_salt("plugin_scroll")
-            .show(ui, |ui| {
-                if self.is_loading {
-                    ui.centered_and_justified(|ui| {
-                        ui.spinner();
-                        let loading_text = match self.mode {
-                            PluginMode::HotPE => "正在加载模块列表...",
-                            _ => "正在加载插件列表...",
-                        };
-                        ui.label(loading_text);
-                    });
+        let mut open = true;
+        let mut install_all = false;
+        let mut install_only = false;
+        let mut close = false;
+        
+        egui::Window::new(format!("{} 的依赖", dialog.plugin.name))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let resolution = &dialog.resolution;
+                
+                if resolution.to_install.is_empty() {
+                    ui.label("所有依赖均已安装");
                 } else {
-                    let plugins = if self.selected_category == "搜索" && !self.search_text.is_empty() {
-                        self.plugin_manager.read().search_plugins(&self.search_text)
-                    } else if self.selected_category != "搜索" {
-                        self.get_category_plugins()
-                    } else {
-                        Vec::new()
-                    };
-                    
-                    if plugins.is_empty() {
-                        ui.centered_and_justified(|ui| {
-                            if self.selected_category == "搜索" {
-                                let not_found_text = match self.mode {
-                                    PluginMode::HotPE => "未找到相关模块",
-                                    _ => "未找到相关插件",
-                                };
-                                ui.label(not_found_text);
-                            } else {
-                                let empty_text = match self.mode {
-                                    PluginMode::HotPE => "该分类暂无模块",
-                                    _ => "该分类暂无插件",
-                                };
-                                ui.label(empty_text);
-                            }
-                        });
-                    } else {
-                        let mut seen = HashSet::new();
-                        for plugin in plugins {
-                            let key = format!("{}_{}_{}_{}", 
-                                plugin.name, plugin.version, plugin.author, plugin.size);
-                            if seen.insert(key) {
-                                self.show_plugin_card(ui, &plugin);
-                            }
-                        }
+                    ui.label("将同时安装以下依赖：");
+                    for dependency in &resolution.to_install {
+                        ui.label(format!("• {} ({})", dependency.name, dependency.version));
                     }
                 }
+                
+                if !resolution.missing.is_empty() {
+                    ui.add_space(5.0);
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 100, 100),
+                        format!("市场中找不到以下依赖：{}", resolution.missing.join("、")),
+                    );
+                }
+                
+                if !resolution.cycles.is_empty() {
+                    ui.add_space(5.0);
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 100, 100),
+                        format!("检测到循环依赖：{}", resolution.cycles.join("、")),
+                    );
+                }
+                
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if dialog.install {
+                        if ui.button("全部安装").clicked() {
+                            install_all = true;
+                        }
+                        if ui.button("仅安装此插件").clicked() {
+                            install_only = true;
+                        }
+                        if ui.button("取消").clicked() {
+                            close = true;
+                        }
+                    } else if ui.button("关闭").clicked() {
+                        close = true;
+                    }
+                });
             });
         
-        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        if install_all || install_only {
+            if let Some(dialog) = self.dependency_dialog.take() {
+                if install_all {
+                    for dependency in dialog.resolution.to_install {
+                        self.install_plugin(dependency);
+                    }
+                }
+                self.install_plugin(dialog.plugin);
+            }
+        } else if close || !open {
+            self.dependency_dialog = None;
+        }
     }
     
     fn get_category_plugins(&self) -> Vec<Plugin> {
@@ -212,14 +1841,14 @@ impl PluginsMarketPage {
             .show(ui, |ui| {
                 let available_width = ui.available_width();
                 
-                if available_width > 400.0 {
+                if available_width > WIDE_CARD_MIN_WIDTH {
                     ui.horizontal(|ui| {
                         ui.vertical(|ui| {
-                            ui.set_max_width(available_width - 180.0);
-                            ui.label(egui::RichText::new(&plugin.name).strong());
+                            ui.set_max_width(available_width - CARD_ACTIONS_WIDTH);
+                            self.show_name_with_badge(ui, plugin);
                             
                             if self.mode != PluginMode::Edgeless && !plugin.describe.is_empty() {
-                                ui.label(&plugin.describe);
+                                self.show_description(ui, plugin);
                             }
                             
                             ui.horizontal_wrapped(|ui| {
@@ -228,6 +1857,10 @@ impl PluginsMarketPage {
                                 ui.label(format!("大小: {}", plugin.size));
                                 ui.separator();
                                 ui.label(format!("作者: {}", plugin.author));
+                                ui.separator();
+                                ui.hyperlink_to("报告问题", plugin.get_report_url(self.mode));
+                                ui.separator();
+                                self.show_copy_info_menu(ui, plugin);
                             });
                         });
                         
@@ -235,12 +1868,29 @@ impl PluginsMarketPage {
                             self.show_plugin_actions(ui, plugin);
                         });
                     });
+                } else if available_width < COMPACT_CARD_MAX_WIDTH {
+                    ui.vertical(|ui| {
+                        self.show_name_with_badge(ui, plugin);
+                        
+                        if self.mode != PluginMode::Edgeless && !plugin.describe.is_empty() {
+                            self.show_description(ui, plugin);
+                        }
+                        
+                        ui.label(format!("v{} · {}", plugin.version, plugin.size));
+                        ui.label(&plugin.author);
+                        ui.horizontal_wrapped(|ui| {
+                            ui.hyperlink_to("报告", plugin.get_report_url(self.mode));
+                            self.show_copy_info_menu(ui, plugin);
+                        });
+                        ui.add_space(5.0);
+                        self.show_plugin_actions(ui, plugin);
+                    });
                 } else {
                     ui.vertical(|ui| {
-                        ui.label(egui::RichText::new(&plugin.name).strong());
+                        self.show_name_with_badge(ui, plugin);
                         
                         if self.mode != PluginMode::Edgeless && !plugin.describe.is_empty() {
-                            ui.label(&plugin.describe);
+                            self.show_description(ui, plugin);
                         }
                         
                         ui.horizontal_wrapped(|ui| {
@@ -249,6 +1899,10 @@ impl PluginsMarketPage {
                             ui.label(format!("大小: {}", plugin.size));
                             ui.separator();
                             ui.label(format!("作者: {}", plugin.author));
+                            ui.separator();
+                            ui.hyperlink_to("报告问题", plugin.get_report_url(self.mode));
+                            ui.separator();
+                            self.show_copy_info_menu(ui, plugin);
                         });
                         ui.add_space(5.0);
                         ui.horizontal(|ui| {
@@ -260,57 +1914,136 @@ impl PluginsMarketPage {
     }
     
     fn show_plugin_actions(&mut self, ui: &mut egui::Ui, plugin: &Plugin) {
+        // 离线模式下安装、更新、下载都需要联网，统一禁用
+        let offline = is_offline_mode();
+        if offline {
+            ui.disable();
+        }
+        
         let plugin_id = plugin.get_plugin_id();
         let plugin_id_install = format!("{}_install", plugin_id);
         let plugin_id_update = format!("{}_update", plugin_id);
         let plugin_id_download = format!("{}_download", plugin_id);
         
+        // 复制一份进度，绘制时不持有任务列表的锁
         let tasks = self.downloading_tasks.read();
-        let is_installing = tasks.contains_key(&plugin_id_install);
-        let is_updating = tasks.contains_key(&plugin_id_update);
-        let is_downloading = tasks.contains_key(&plugin_id_download);
+        let task_progress = |task_id: &str| tasks.get(task_id).map(|task| task.progress.read().clone());
+        let install_progress = task_progress(&plugin_id_install);
+        let update_progress = task_progress(&plugin_id_update);
+        let download_progress = task_progress(&plugin_id_download);
         drop(tasks);
         
+        let is_installing = install_progress.is_some();
+        let is_updating = update_progress.is_some();
+        let is_downloading = download_progress.is_some();
+        
+        let failures = self.failed_tasks.read();
+        let install_error = failures.get(&plugin_id_install).cloned();
+        let update_error = failures.get(&plugin_id_update).cloned();
+        let download_error = failures.get(&plugin_id_download).cloned();
+        drop(failures);
+        
         let has_boot_drive = self.boot_drive_manager.read().get_current_drive().is_some();
         
-        ui.horizontal(|ui| {
-            if has_boot_drive {
-                let plugin_status = self.check_plugin_status(plugin);
-                
+        // 卡片较窄时按钮换行显示，不会超出卡片
+        ui.horizontal_wrapped(|ui| {
+            let plugin_status = self.check_plugin_status(plugin);
+            let busy = match plugin_status {
+                PluginStatus::NotInstalled => is_installing,
+                PluginStatus::UpdateAvailable | PluginStatus::LocalNewer => is_updating,
+                PluginStatus::Installed => false,
+            };
+            let install_reason = install_unavailable(has_boot_drive, offline, plugin_status, busy);
+            
+            if !has_boot_drive {
+                // 没有启动盘时仍显示安装按钮，悬停时说明原因
+                explain_unavailable(ui.add_enabled(false, egui::Button::new("安装")), install_reason);
+            } else {
                 match plugin_status {
                     PluginStatus::NotInstalled => {
-                        if is_installing {
-                            ui.spinner();
-                            ui.add_enabled(false, egui::Button::new("安装中..."));
+                        if let Some(progress) = &install_progress {
+                            show_task_progress(ui, progress);
+                            explain_unavailable(ui.add_enabled(false, egui::Button::new("安装中...")), install_reason);
+                            if ui.button("取消").clicked() {
+                                self.cancel_task(&plugin_id_install);
+                            }
                         } else {
-                            if ui.button("安装").clicked() {
-                                self.install_plugin(plugin.clone());
+                            if let Some(error) = &install_error {
+                                if explain_unavailable(retry_button(ui, error), install_reason).clicked() {
+                                    self.request_install(plugin);
+                                }
+                            } else if explain_unavailable(ui.button("安装"), install_reason).clicked() {
+                                self.request_install(plugin);
                             }
                         }
                     }
                     PluginStatus::Installed => {
-                        ui.add_enabled(false, egui::Button::new("已安装"));
+                        explain_unavailable(ui.add_enabled(false, egui::Button::new("已安装")), install_reason);
                     }
                     PluginStatus::UpdateAvailable => {
-                        if is_updating {
-                            ui.spinner();
-                            ui.add_enabled(false, egui::Button::new("更新中..."));
+                        if let Some(progress) = &update_progress {
+                            show_task_progress(ui, progress);
+                            explain_unavailable(ui.add_enabled(false, egui::Button::new("更新中...")), install_reason);
+                            if ui.button("取消").clicked() {
+                                self.cancel_task(&plugin_id_update);
+                            }
+                        } else {
+                            if let Some(error) = &update_error {
+                                if explain_unavailable(retry_button(ui, error), install_reason).clicked() {
+                                    self.request_update(plugin);
+                                }
+                            } else if explain_unavailable(ui.button("更新"), install_reason).clicked() {
+                                self.request_update(plugin);
+                            }
+                        }
+                    }
+                    PluginStatus::LocalNewer => {
+                        let local_version = self.plugin_manager.read()
+                            .get_enabled_plugin_by_id(&plugin.get_plugin_id())
+                            .map(|local| local.version.clone())
+                            .unwrap_or_default();
+                        
+                        if let Some(progress) = &update_progress {
+                            show_task_progress(ui, progress);
+                            explain_unavailable(ui.add_enabled(false, egui::Button::new("降级中...")), install_reason);
+                            if ui.button("取消").clicked() {
+                                self.cancel_task(&plugin_id_update);
+                            }
                         } else {
-                            if ui.button("更新").clicked() {
-                                self.update_plugin(plugin.clone());
+                            ui.colored_label(egui::Color32::from_rgb(100, 150, 255), "本地版本较新")
+                                .on_hover_text(format!("启动盘上为 {}，市场中为 {}", local_version, plugin.version));
+                            
+                            if self.config.read().allow_downgrade {
+                                let clicked = match &update_error {
+                                    Some(error) => explain_unavailable(retry_button(ui, error), install_reason).clicked(),
+                                    None => explain_unavailable(ui.button("降级安装"), install_reason).clicked(),
+                                };
+                                if clicked {
+                                    self.downgrade_prompt = Some((plugin.clone(), local_version));
+                                }
                             }
                         }
                     }
                 }
             }
             
-            if is_downloading {
-                ui.spinner();
-                ui.add_enabled(false, egui::Button::new("下载中..."));
-            } else {
-                if ui.button("下载").clicked() {
+            if !plugin.dependencies.is_empty() && ui.button("查看依赖").clicked() {
+                self.open_dependency_dialog(plugin, false);
+            }
+            
+            let download_reason = download_unavailable(offline, is_downloading);
+            if let Some(progress) = &download_progress {
+                show_task_progress(ui, progress);
+                explain_unavailable(ui.add_enabled(false, egui::Button::new("下载中...")), download_reason);
+                if ui.button("取消").clicked() {
+                    self.cancel_task(&plugin_id_download);
+                }
+            } else if let Some(error) = &download_error {
+                if explain_unavailable(retry_button(ui, error), download_reason).clicked() {
                     self.download_plugin(plugin.clone());
                 }
+            } else if explain_unavailable(ui.button("下载"), download_reason).clicked() {
+                self.download_plugin(plugin.clone());
             }
         });
     }
@@ -325,7 +2058,7 @@ impl PluginsMarketPage {
             match comparison {
                 std::cmp::Ordering::Less => PluginStatus::UpdateAvailable,
                 std::cmp::Ordering::Equal => PluginStatus::Installed,
-                std::cmp::Ordering::Greater => PluginStatus::Installed,
+                std::cmp::Ordering::Greater => PluginStatus::LocalNewer,
             }
         } else {
             PluginStatus::NotInstalled
@@ -336,46 +2069,54 @@ impl PluginsMarketPage {
         let plugin_id = plugin.get_plugin_id();
         let task_id = format!("{}_install", plugin_id);
         
+        if !self.has_free_space_for(&plugin) {
+            return;
+        }
+        
+        let config = self.config.read();
+        let downloader = Arc::new(Downloader::new(config.download_threads, config.download_retries));
+        drop(config);
         let task = DownloadTask {
             plugin_name: plugin.name.clone(),
-            progress: Arc::new(RwLock::new(0.0)),
+            progress: downloader.progress_handle(),
+            cancelled: downloader.cancel_flag(),
+            abort_handle: None,
             is_install: true,
         };
         
         self.downloading_tasks.write().insert(task_id.clone(), task.clone());
         
-        let downloader = Arc::new(Downloader::new(self.config.read().download_threads));
         let boot_drive = self.boot_drive_manager.read().get_current_drive();
         
         if let Some(drive_letter) = boot_drive {
             let filename = self.generate_plugin_filename(&plugin);
-            let _plugin_name = plugin.name.clone();
-            let plugin_url = plugin.link.clone();
             let downloading_tasks = self.downloading_tasks.clone();
+            let failed_tasks = self.failed_tasks.clone();
             let mode = self.mode.clone();
             let plugin_manager = self.plugin_manager.clone();
+            let options = DeployOptions {
+                replace_existing: false,
+                safe_install: self.config.read().safe_install,
+                block_incompatible: self.config.read().compatibility_check == CompatibilityCheck::Block,
+            };
             
-            self.runtime.spawn(async move {
-                let plugin_dir = format!("{}\\{}", drive_letter, mode.get_plugin_folder());
-                
-                if let Err(_) = tokio::fs::create_dir_all(&plugin_dir).await {
-                    downloading_tasks.write().remove(&task_id);
-                    return;
-                }
-                
-                let extension = mode.get_enabled_extension();
-                let install_path = std::path::PathBuf::from(plugin_dir).join(format!("{}.{}", filename, extension));
-                
-                match downloader.download(&plugin_url, install_path.clone()).await {
+            let spawned_task_id = task_id.clone();
+            let download_slots = self.download_slots.clone();
+            let handle = self.runtime.spawn(async move {
+                let task_id = spawned_task_id;
+                let _permit = download_slots.acquire_owned().await;
+                match deploy_plugin(&downloader, &plugin_manager, mode, &drive_letter, &plugin, &filename, options).await {
                     Ok(_) => {
-                        let _ = plugin_manager.write().load_local_plugins(&drive_letter);
+                        failed_tasks.write().remove(&task_id);
                     }
-                    Err(_e) => {
+                    Err(e) => {
+                        failed_tasks.write().insert(task_id.clone(), e);
                     }
                 }
                 
                 downloading_tasks.write().remove(&task_id);
             });
+            self.set_abort_handle(&task_id, handle.abort_handle());
         } else {
             self.downloading_tasks.write().remove(&task_id);
         }
@@ -385,76 +2126,73 @@ impl PluginsMarketPage {
         let plugin_id = plugin.get_plugin_id();
         let task_id = format!("{}_update", plugin_id);
         
+        if !self.has_free_space_for(&plugin) {
+            return;
+        }
+        
+        let config = self.config.read();
+        let downloader = Arc::new(Downloader::new(config.download_threads, config.download_retries));
+        drop(config);
         let task = DownloadTask {
             plugin_name: plugin.name.clone(),
-            progress: Arc::new(RwLock::new(0.0)),
+            progress: downloader.progress_handle(),
+            cancelled: downloader.cancel_flag(),
+            abort_handle: None,
             is_install: true,
         };
         
         self.downloading_tasks.write().insert(task_id.clone(), task.clone());
         
-        let downloader = Arc::new(Downloader::new(self.config.read().download_threads));
         let boot_drive = self.boot_drive_manager.read().get_current_drive();
         
         if let Some(drive_letter) = boot_drive {
             let filename = self.generate_plugin_filename(&plugin);
-            let plugin_url = plugin.link.clone();
             let downloading_tasks = self.downloading_tasks.clone();
+            let failed_tasks = self.failed_tasks.clone();
             let mode = self.mode.clone();
             let plugin_manager = self.plugin_manager.clone();
-            let market_plugin_id = plugin.get_plugin_id();
+            let options = DeployOptions {
+                replace_existing: true,
+                safe_install: self.config.read().safe_install,
+                block_incompatible: self.config.read().compatibility_check == CompatibilityCheck::Block,
+            };
             
-            self.runtime.spawn(async move {
-                let plugin_dir = format!("{}\\{}", drive_letter, mode.get_plugin_folder());
-                
-                if let Err(_) = tokio::fs::create_dir_all(&plugin_dir).await {
-                    downloading_tasks.write().remove(&task_id);
-                    return;
-                }
-                
-                let old_file = {
-                    let manager = plugin_manager.read();
-                    if let Some(local_plugin) = manager.get_enabled_plugin_by_id(&market_plugin_id) {
-                        Some(local_plugin.file.clone())
-                    } else {
-                        None
-                    }
-                };
-                
-                if let Some(old_file_name) = old_file {
-                    if let Err(_) = plugin_manager.read().delete_plugin_file(&drive_letter, &old_file_name) {
-                        downloading_tasks.write().remove(&task_id);
-                        return;
-                    }
-                }
-                
-                let extension = mode.get_enabled_extension();
-                let install_path = std::path::PathBuf::from(plugin_dir).join(format!("{}.{}", filename, extension));
-                
-                match downloader.download(&plugin_url, install_path.clone()).await {
+            let spawned_task_id = task_id.clone();
+            let download_slots = self.download_slots.clone();
+            let handle = self.runtime.spawn(async move {
+                let task_id = spawned_task_id;
+                let _permit = download_slots.acquire_owned().await;
+                match deploy_plugin(&downloader, &plugin_manager, mode, &drive_letter, &plugin, &filename, options).await {
                     Ok(_) => {
-                        let _ = plugin_manager.write().load_local_plugins(&drive_letter);
+                        failed_tasks.write().remove(&task_id);
                     }
-                    Err(_e) => {
+                    Err(e) => {
+                        failed_tasks.write().insert(task_id.clone(), e);
                     }
                 }
                 
                 downloading_tasks.write().remove(&task_id);
             });
+            self.set_abort_handle(&task_id, handle.abort_handle());
         } else {
             self.downloading_tasks.write().remove(&task_id);
         }
     }
     
     fn download_plugin(&mut self, plugin: Plugin) {
-        use rfd::AsyncFileDialog;
+        use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialogResult, MessageLevel};
         
         let plugin_id = plugin.get_plugin_id();
         let task_id = format!("{}_download", plugin_id);
         
+        let config = self.config.read();
+        let downloader = Arc::new(Downloader::new(config.download_threads, config.download_retries));
+        drop(config);
         let task = DownloadTask {
             plugin_name: plugin.name.clone(),
-            progress: Arc::new(RwLock::new(0.0)),
+            progress: downloader.progress_handle(),
+            cancelled: downloader.cancel_flag(),
+            abort_handle: None,
             is_install: false,
         };
         
@@ -462,6 +2200,7 @@ impl PluginsMarketPage {
         
         let config = self.config.clone();
         let downloading_tasks = self.downloading_tasks.clone();
+        let failed_tasks = self.failed_tasks.clone();
         let runtime = self.runtime.clone();
         
         let filename = self.generate_plugin_filename(&plugin);
@@ -469,74 +2208,329 @@ impl PluginsMarketPage {
         let full_filename = format!("{}.{}", filename, extension);
         
         let plugin_url = plugin.link.clone();
+        let plugin_sha256 = plugin.sha256.clone();
+        let plugin_name = plugin.name.clone();
+        let plugin_category = plugin.category.clone();
+        let plugin_size = parse_size_to_bytes(&plugin.size);
+        let toast = self.toast.clone();
         
-        let default_download_path = config.read().default_download_path.clone();
+        let mode = self.mode;
+        let default_download_path = config.read().download_path(mode);
+        let download_slots = self.download_slots.clone();
         
-        runtime.spawn(async move {
-            let download_path = if let Some(path) = default_download_path {
-                path
-            } else {
-                match AsyncFileDialog::new()
-                    .set_title("选择下载位置")
-                    .pick_folder()
-                    .await
-                {
-                    Some(handle) => {
-                        let path = handle.path().to_path_buf();
+        let spawned_task_id = task_id.clone();
+        let handle = runtime.spawn(async move {
+            let task_id = spawned_task_id;
+            let mut candidate = default_download_path;
+            
+            let download_path = loop {
+                let (path, picked) = match candidate.take() {
+                    Some(path) => (path, false),
+                    None => match AsyncFileDialog::new()
+                        .set_title("选择下载位置")
+                        .pick_folder()
+                        .await
+                    {
+                        Some(handle) => (handle.path().to_path_buf(), true),
+                        None => {
+                            downloading_tasks.write().remove(&task_id);
+                            return;
+                        }
+                    },
+                };
+                
+                // 下载前先确认目录可写，避免下载完成后才写入失败
+                if is_dir_writable(&path) {
+                    let free_memory = ram_disk_free_memory(&path);
+                    if let (Some(size), Some(free_memory)) = (plugin_size, free_memory) {
+                        if size.saturating_add(RAM_DISK_RESERVE) > free_memory {
+                            let choice = AsyncMessageDialog::new()
+                                .set_level(MessageLevel::Warning)
+                                .set_title("内存可能不足")
+                                .set_description(format!(
+                                    "{} 位于 PE 内存盘，下载的 {} 会直接占用内存，当前剩余内存 {}，可能导致 PE 崩溃。\n\n\
+                                     是：选择其他位置（如 U 盘或硬盘）\n否：仍然下载到此处\n取消：取消下载",
+                                    path.display(),
+                                    format_file_size(size as i64),
+                                    format_file_size(free_memory as i64),
+                                ))
+                                .set_buttons(MessageButtons::YesNoCancel)
+                                .show()
+                                .await;
+                            
+                            match choice {
+                                MessageDialogResult::Yes => continue,
+                                MessageDialogResult::No => {}
+                                _ => {
+                                    downloading_tasks.write().remove(&task_id);
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    
+                    if picked {
                         let mut config_write = config.write();
-                        config_write.default_download_path = Some(path.clone());
+                        config_write.set_download_path(mode, path.clone());
                         let _ = config_write.save();
-                        path
-                    }
-                    None => {
-                        downloading_tasks.write().remove(&task_id);
-                        return;
                     }
+                    break path;
+                }
+                
+                AsyncMessageDialog::new()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("无法写入下载位置")
+                    .set_description(format!("无法写入文件夹 {}，请选择其他下载位置", path.display()))
+                    .set_buttons(MessageButtons::Ok)
+                    .show()
+                    .await;
+            };
+            
+            // 子文件夹只影响本次下载位置，记住的默认下载路径仍是用户选择的文件夹
+            let download_path = if config.read().category_subfolder && !plugin_category.is_empty() {
+                let subfolder = download_path.join(sanitize_windows_filename(&plugin_category));
+                if let Err(e) = std::fs::create_dir_all(&subfolder) {
+                    failed_tasks.write().insert(task_id.clone(), format!("创建分类文件夹失败: {}", e));
+                    downloading_tasks.write().remove(&task_id);
+                    return;
                 }
+                subfolder
+            } else {
+                download_path
+            };
+            
+            let generated_path = download_path.join(&full_filename);
+            let use_server_filename = config.read().use_server_filename;
+            
+            // 选择下载位置时不占用名额
+            let _permit = download_slots.acquire_owned().await;
+            
+            // 使用服务器文件名时要等响应返回才知道最终文件名，下载完成后再把 .part 文件重命名
+            let download_result = if use_server_filename {
+                downloader.download_part(&plugin_url, &generated_path, plugin_sha256.as_deref()).await
+            } else {
+                downloader.download(&plugin_url, generated_path.clone(), plugin_sha256.as_deref()).await
+                    .map(|_| generated_path.clone())
             };
             
-            let downloader = Arc::new(Downloader::new(config.read().download_threads));
-            let file_path = download_path.join(full_filename);
+            let result = match download_result {
+                Ok(download_target) if use_server_filename => {
+                    let final_path = downloader.get_progress()
+                        .server_filename
+                        .map(|name| sanitize_windows_filename(&name))
+                        .filter(|name| !name.is_empty())
+                        .map(|name| download_path.join(name))
+                        .unwrap_or(generated_path);
+                    // 不覆盖文件夹中已有的同名文件
+                    let final_path = unused_path(final_path);
+                    
+                    std::fs::rename(&download_target, &final_path)
+                        .map(|_| final_path)
+                        .map_err(|e| format!("重命名下载文件失败: {}", e))
+                }
+                Ok(file_path) => Ok(file_path),
+                Err(e) => Err(e.to_string()),
+            };
             
-            match downloader.download(&plugin_url, file_path).await {
-                Ok(_) => {
+            match result {
+                Ok(file_path) => {
+                    failed_tasks.write().remove(&task_id);
+                    
+                    match config.read().post_download_action {
+                        PostDownloadAction::None => {}
+                        PostDownloadAction::Toast => {
+                            *toast.write() = Some((format!("{} 下载完成", plugin_name), Instant::now()));
+                        }
+                        PostDownloadAction::OpenFolder => open_in_explorer(&download_path),
+                        PostDownloadAction::OpenFile => open_in_explorer(&file_path),
+                    }
                 }
-                Err(_) => {
+                Err(e) => {
+                    crate::app_log::log(format!("下载 {} 失败: {}", plugin_name, e));
+                    failed_tasks.write().insert(task_id.clone(), e);
                 }
             }
             
             downloading_tasks.write().remove(&task_id);
         });
+        self.set_abort_handle(&task_id, handle.abort_handle());
+    }
+    
+    // 任务可能在设置前就已完成并移除，此时无需记录
+    fn set_abort_handle(&self, task_id: &str, handle: AbortHandle) {
+        if let Some(task) = self.downloading_tasks.write().get_mut(task_id) {
+            task.abort_handle = Some(handle);
+        }
+    }
+    
+    // 供下载队列显示的全部任务，按名称排序以保持顺序稳定
+    pub fn queue_entries(&self) -> Vec<QueueEntry> {
+        let mut entries: Vec<QueueEntry> = self.downloading_tasks.read()
+            .iter()
+            .map(|(task_id, task)| QueueEntry {
+                task_id: task_id.clone(),
+                plugin_name: task.plugin_name.clone(),
+                progress: task.progress.read().clone(),
+                waiting: task.abort_handle.is_none(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.plugin_name.cmp(&b.plugin_name).then_with(|| a.task_id.cmp(&b.task_id)));
+        entries
+    }
+    
+    // 查询进行中的任务数，窗口隐藏到托盘后界面不再刷新，由托盘菜单直接查询
+    pub fn task_counter(&self) -> Box<dyn Fn() -> usize> {
+        let tasks = self.downloading_tasks.clone();
+        Box::new(move || tasks.read().len())
+    }
+    
+    // 取消正在下载的任务；排队中的任务直接移出队列，批量安装会跳过它
+    pub fn cancel_task(&mut self, task_id: &str) {
+        if let Some(task) = self.downloading_tasks.write().remove(task_id) {
+            task.cancelled.store(true, Ordering::Relaxed);
+            if let Some(handle) = task.abort_handle {
+                handle.abort();
+            }
+        }
     }
     
     fn generate_plugin_filename(&self, plugin: &Plugin) -> String {
-        let safe_describe = plugin.describe
-            .replace(' ', "_")
-            .replace('/', "_")
-            .replace('\\', "_")
-            .replace(':', "_")
-            .replace('*', "_")
-            .replace('?', "_")
-            .replace('"', "_")
-            .replace('<', "_")
-            .replace('>', "_")
-            .replace('|', "_");
-        
-        match self.mode {
-            PluginMode::CloudPE => {
-                format!("{}_{}_{}_{}", plugin.name, plugin.version, plugin.author, safe_describe)
-            }
-            PluginMode::HotPE => {
-                if safe_describe.is_empty() {
-                    format!("{}_{}_{}_{}", plugin.name, plugin.author, plugin.version, plugin.name)
-                } else {
-                    format!("{}_{}_{}_{}", plugin.name, plugin.author, plugin.version, safe_describe)
-                }
+        plugin_naming::encode(plugin, self.mode)
+    }
+}
+
+impl Drop for PluginsMarketPage {
+    fn drop(&mut self) {
+        if let Some(handle) = self.fetch_handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+// 插件处理完毕（无论成功、失败或取消）后从未完成记录中移除
+fn mark_batch_processed(mode: PluginMode, pending: &mut PendingBatch, plugin_id: &str) {
+    pending.plugin_ids.retain(|id| id != plugin_id);
+    let _ = config::save_pending_batch(mode, pending);
+}
+
+// 在后台依次获取其他插件源的插件列表并写入缓存，缓存仍然足够新的跳过。
+// 离线模式或按流量计费的网络下不预取
+async fn prefetch_other_modes(mode: PluginMode, channel: ReleaseChannel) {
+    for other in [PluginMode::CloudPE, PluginMode::HotPE, PluginMode::Edgeless] {
+        if is_offline_mode() || crate::network::is_metered_connection() {
+            return;
+        }
+        
+        if other == mode || config::load_plugin_list_cache(other, channel).is_some_and(|cache| cache.is_fresh()) {
+            continue;
+        }
+        
+        match PluginManager::fetch_plugins_async(other, channel, Arc::default(), Arc::default()).await {
+            Ok(categories) => {
+                let _ = config::save_plugin_list_cache(other, channel, &categories);
+            }
+            Err(e) => crate::app_log::log(format!("预取{}插件列表失败: {}", other.get_server_name(), e)),
+        }
+    }
+}
+
+// 批量安装完成后的核对报告，有问题的插件排在前面
+fn show_install_report(ui: &mut egui::Ui, report: &[InstallReportEntry]) {
+    let problems = report.iter().filter(|entry| !entry.is_ok()).count();
+    if problems == 0 {
+        ui.colored_label(egui::Color32::from_rgb(0, 160, 0), format!("核对通过：{} 个插件均已在启动盘上启用", report.len()));
+    } else {
+        ui.colored_label(egui::Color32::from_rgb(255, 160, 0), format!("核对发现 {} 个插件需要检查", problems));
+    }
+    if report.iter().any(|entry| entry.bytes.is_some() && !entry.checksum) {
+        ui.label(egui::RichText::new("部分插件未提供校验值，这些插件的下载完整性按文件大小核对").small());
+    }
+    
+    let mut entries: Vec<&InstallReportEntry> = report.iter().collect();
+    entries.sort_by_key(|entry| entry.is_ok());
+    
+    egui::ScrollArea::vertical()
+        .id_salt("bulk_install_report")
+        .max_height(260.0)
+        .show(ui, |ui| {
+            egui::Grid::new("bulk_install_report_grid")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("插件");
+                    ui.strong("结果");
+                    ui.strong("下载");
+                    ui.strong("启动盘上");
+                    ui.end_row();
+                    
+                    for entry in entries {
+                        let name = format!("{} {} {}", if entry.is_ok() { "✔" } else { "⚠" }, entry.name, entry.version);
+                        if entry.is_ok() {
+                            ui.label(name);
+                        } else {
+                            ui.colored_label(egui::Color32::from_rgb(255, 160, 0), name);
+                        }
+                        ui.label(entry.result_text());
+                        ui.label(entry.bytes_text());
+                        ui.label(entry.local_text());
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+// 批量安装实际处理的插件：与单独安装一样，未安装且未勾选的依赖排在依赖它的插件之前。
+// 同时返回市场中找不到的依赖
+fn bulk_install_plan(plugin_manager: &PluginManager, plugins: &[Plugin], selected: &[bool]) -> (Vec<Plugin>, Vec<String>) {
+    let mut planned = Vec::new();
+    let mut planned_ids = HashSet::new();
+    let mut missing = Vec::new();
+    
+    for (plugin, _) in plugins.iter().zip(selected).filter(|(_, selected)| **selected) {
+        let resolution = plugin_manager.resolve_dependencies(plugin);
+        for id in resolution.missing {
+            if !missing.contains(&id) {
+                missing.push(id);
             }
-            PluginMode::Edgeless => {
-                format!("{}_{}_{}", plugin.name, plugin.version, plugin.author)
+        }
+        for dependency in resolution.to_install {
+            if planned_ids.insert(dependency.get_plugin_id()) {
+                planned.push(dependency);
             }
-            _ => String::new()
+        }
+        if planned_ids.insert(plugin.get_plugin_id()) {
+            planned.push(plugin.clone());
         }
     }
+    
+    (planned, missing)
+}
+
+// 文件已存在时在文件名后加上 (1)、(2) 等序号，返回第一个不存在的路径
+fn unused_path(path: std::path::PathBuf) -> std::path::PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|index| path.with_file_name(format!("{} ({}){}", stem, index, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+// 已安装且版本不低于市场版本，批量安装时会跳过
+fn is_current_version(plugin_manager: &Arc<RwLock<PluginManager>>, plugin: &Plugin) -> bool {
+    let manager = plugin_manager.read();
+    manager.get_enabled_plugin_by_id(&plugin.get_plugin_id())
+        .is_some_and(|local| manager.compare_versions(&local.version, &plugin.version) != std::cmp::Ordering::Less)
 }
+
+// 失败后显示的重试按钮，悬停可查看错误信息
+fn retry_button(ui: &mut egui::Ui, error: &str) -> egui::Response {
+    ui.add(egui::Button::new(
+        egui::RichText::new("重试").color(egui::Color32::from_rgb(255, 100, 100)),
+    ))
+    .on_hover_text(format!("上次操作失败：{}", error))
+}
\ No newline at end of file
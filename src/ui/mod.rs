@@ -1,7 +1,11 @@
+mod action_hint;
+mod download_queue;
+mod markdown;
 mod market_page;
 mod manage_page;
 mod settings_page;
 
+pub use download_queue::{show_download_queue, QueueEntry};
 pub use market_page::PluginsMarketPage;
 pub use manage_page::PluginsManagePage;
 pub use settings_page::SettingsPage;
\ No newline at end of file
@@ -1,21 +1,40 @@
-use crate::config::{AppConfig, ColorMode};
-use crate::utils::BootDriveManager;
+use crate::app::{MAX_FONT_SCALE, MIN_FONT_SCALE};
+use crate::config::{self, AppConfig, ColorMode, CompatibilityCheck, ConnectTestStrategy, SourceAuth, PostDownloadAction, ReleaseChannel};
+use crate::utils::{is_pe_environment, BootDrive, BootDriveManager};
 use crate::mode::PluginMode;
+use crate::plugins::format_file_size;
 use eframe::egui;
 use std::sync::Arc;
 use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-#[cfg(target_os = "windows")]
-use winapi::um::dwmapi::DwmSetWindowAttribute;
-#[cfg(target_os = "windows")]
-use winapi::um::winuser::GetActiveWindow;
-#[cfg(target_os = "windows")]
-use std::mem;
+// 超过此线程数时提示可能适得其反
+const HIGH_THREAD_COUNT: u32 = 8;
+
+// 启动盘可用空间的刷新间隔
+const FREE_SPACE_REFRESH: Duration = Duration::from_secs(10);
 
 pub struct SettingsPage {
     config: Arc<RwLock<AppConfig>>,
     boot_drive_manager: Arc<RwLock<BootDriveManager>>,
     mode: PluginMode,
+    // 拖动滑块时暂存的缩放比例，松开后才应用
+    font_scale: f32,
+    raw_config_editing: bool,
+    raw_config_text: String,
+    raw_config_error: Option<String>,
+    // 下一帧展开启动盘设置
+    focus_boot_drive: bool,
+    diagnostics_copied: bool,
+    // 上次导出诊断包的结果，成功时为保存位置
+    bundle_result: Option<Result<std::path::PathBuf, String>>,
+    // 正在编辑的代理地址，点击应用并通过校验后才保存
+    proxy_text: String,
+    proxy_error: Option<String>,
+    // 各启动盘的可用空间，以及查询时的当前启动盘和时间。切换启动盘或超过刷新间隔后才重新查询
+    free_space: HashMap<String, Option<u64>>,
+    free_space_checked: Option<(Option<String>, Instant)>,
 }
 
 impl SettingsPage {
@@ -24,13 +43,31 @@ impl SettingsPage {
         boot_drive_manager: Arc<RwLock<BootDriveManager>>,
         mode: PluginMode,
     ) -> Self {
+        let font_scale = config.read().font_scale;
+        let proxy_text = config.read().proxy_url.clone().unwrap_or_default();
+        
         Self {
             config,
             boot_drive_manager,
             mode,
+            font_scale,
+            raw_config_editing: false,
+            raw_config_text: String::new(),
+            raw_config_error: None,
+            focus_boot_drive: false,
+            diagnostics_copied: false,
+            bundle_result: None,
+            proxy_text,
+            proxy_error: None,
+            free_space: HashMap::new(),
+            free_space_checked: None,
         }
     }
     
+    pub fn focus_boot_drive_settings(&mut self) {
+        self.focus_boot_drive = true;
+    }
+    
     pub fn show(&mut self, ui: &mut egui::Ui, _ctx: &egui::Context) {
         ui.heading("设置");
         ui.separator();
@@ -40,14 +77,28 @@ impl SettingsPage {
                 self.show_basic_settings(ui);
             });
             
-            ui.collapsing("启动盘设置", |ui| {
-                self.show_boot_drive_settings(ui);
-            });
+            let open = std::mem::take(&mut self.focus_boot_drive).then_some(true);
+            let response = egui::CollapsingHeader::new("启动盘设置")
+                .open(open)
+                .show(ui, |ui| {
+                    self.show_boot_drive_settings(ui);
+                });
+            if open.is_some() {
+                response.header_response.scroll_to_me(Some(egui::Align::TOP));
+            }
             
             ui.collapsing("下载设置", |ui| {
                 self.show_download_settings(ui);
             });
             
+            ui.collapsing("网络设置", |ui| {
+                self.show_network_settings(ui);
+            });
+            
+            ui.collapsing("高级", |ui| {
+                self.show_advanced_settings(ui);
+            });
+            
             ui.collapsing("关于", |ui| {
                 self.show_about(ui);
             });
@@ -74,16 +125,102 @@ impl SettingsPage {
                 });
             
             if current_mode != config.color_mode {
+                // 窗口标题栏主题由主程序在下一帧更新
                 config.color_mode = current_mode.clone();
                 let _ = config.save();
-                
-                // 更新窗口标题栏主题
-                #[cfg(target_os = "windows")]
-                unsafe {
-                    set_dwm_theme(&current_mode);
-                }
             }
         });
+        
+        let mut config = self.config.write();
+        let mut mode_accent = config.mode_accent;
+        if ui.checkbox(&mut mode_accent, "使用插件源主题色").changed() {
+            config.mode_accent = mode_accent;
+            let _ = config.save();
+        }
+        
+        let mut high_contrast = config.high_contrast;
+        if ui.checkbox(&mut high_contrast, "高对比度").changed() {
+            config.high_contrast = high_contrast;
+            let _ = config.save();
+        }
+        
+        let mut confirm_destructive = config.confirm_destructive;
+        if ui.checkbox(&mut confirm_destructive, "执行禁用、删除、恢复备份等操作前确认").changed() {
+            config.confirm_destructive = confirm_destructive;
+            let _ = config.save();
+        }
+        
+        let mut minimize_to_tray = config.minimize_to_tray;
+        if ui.checkbox(&mut minimize_to_tray, "关闭窗口时隐藏到托盘")
+            .on_hover_text("在系统托盘显示图标，关闭窗口后下载在后台继续，可从托盘菜单退出。部分 PE 没有系统托盘，此时关闭窗口仍会退出")
+            .changed()
+        {
+            config.minimize_to_tray = minimize_to_tray;
+            let _ = config.save();
+        }
+        
+        ui.horizontal(|ui| {
+            let mut show_new_badges = config.show_new_badges;
+            if ui.checkbox(&mut show_new_badges, "标记新增插件").changed() {
+                config.show_new_badges = show_new_badges;
+                let _ = config.save();
+            }
+            
+            if ui.button("重置").on_hover_text("下次启动时以当前插件列表为基准重新标记").clicked() {
+                config::reset_known_plugins();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("界面缩放：");
+            let response = ui.add(
+                egui::Slider::new(&mut self.font_scale, MIN_FONT_SCALE..=MAX_FONT_SCALE)
+                    .step_by(0.1)
+                    .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+            );
+            
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                config.font_scale = self.font_scale;
+                let _ = config.save();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("每次显示的搜索结果：");
+            
+            let mut page_size = config.search_page_size;
+            egui::ComboBox::from_id_salt("search_page_size")
+                .selected_text(format!("{} 个", page_size))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut page_size, 20, "20 个");
+                    ui.selectable_value(&mut page_size, 50, "50 个（默认）");
+                    ui.selectable_value(&mut page_size, 100, "100 个");
+                    ui.selectable_value(&mut page_size, 200, "200 个");
+                })
+                .response
+                .on_hover_text("搜索结果较多时只显示前面的部分，可点击\"显示更多\"继续查看");
+            
+            if page_size != config.search_page_size {
+                config.search_page_size = page_size;
+                let _ = config.save();
+            }
+        });
+    }
+    
+    // 查询可用空间需要访问磁盘，不在每帧都查询
+    fn refresh_free_space(&mut self, drives: &[BootDrive], current_drive: &Option<String>) {
+        let stale = match &self.free_space_checked {
+            Some((drive, checked)) => drive != current_drive || checked.elapsed() > FREE_SPACE_REFRESH,
+            None => true,
+        };
+        if !stale && drives.iter().all(|drive| self.free_space.contains_key(&drive.letter)) {
+            return;
+        }
+        
+        self.free_space = drives.iter()
+            .map(|drive| (drive.letter.clone(), BootDriveManager::get_free_space(&drive.letter)))
+            .collect();
+        self.free_space_checked = Some((current_drive.clone(), Instant::now()));
     }
     
     fn show_boot_drive_settings(&mut self, ui: &mut egui::Ui) {
@@ -96,25 +233,35 @@ impl SettingsPage {
                 self.boot_drive_manager.write().reload();
             }
         } else {
+            let current_drive = self.boot_drive_manager.read().get_current_drive();
+            self.refresh_free_space(&boot_drives, &current_drive);
+            
             ui.horizontal(|ui| {
                 ui.label("当前启动盘：");
                 
-                let current_drive = self.boot_drive_manager.read().get_current_drive();
                 let mut selected_drive = current_drive.clone().unwrap_or_default();
                 
+                let selected_text = boot_drives.iter()
+                    .find(|drive| drive.letter == selected_drive)
+                    .map(|drive| drive.display_name())
+                    .unwrap_or_else(|| selected_drive.clone());
+                
                 egui::ComboBox::from_label("")
-                    .selected_text(&selected_drive)
+                    .selected_text(selected_text)
                     .show_ui(ui, |ui| {
                         for drive in &boot_drives {
-                            // 只显示盘符，不显示版本
-                            ui.selectable_value(
-                                &mut selected_drive,
-                                drive.letter.clone(),
-                                &drive.letter,
-                            );
+                            let label = match self.free_space.get(&drive.letter).copied().flatten() {
+                                Some(free) => format!("{}（可用 {}）", drive.display_name(), format_file_size(free as i64)),
+                                None => drive.display_name(),
+                            };
+                            ui.selectable_value(&mut selected_drive, drive.letter.clone(), label);
                         }
                     });
                 
+                if let Some(free) = current_drive.as_ref().and_then(|drive| self.free_space.get(drive).copied().flatten()) {
+                    ui.label(format!("可用空间：{}", format_file_size(free as i64)));
+                }
+                
                 if Some(&selected_drive) != current_drive.as_ref() && !selected_drive.is_empty() {
                     self.boot_drive_manager.write().set_current_drive(selected_drive.clone());
                     
@@ -126,8 +273,22 @@ impl SettingsPage {
             
             if ui.button("重新扫描启动盘").clicked() {
                 self.boot_drive_manager.write().reload();
+                self.free_space_checked = None;
             }
         }
+        
+        let mut config = self.config.write();
+        let mut auto_select = config.auto_select_recent_drive;
+        if ui.checkbox(&mut auto_select, "检测到多个启动盘时自动选择最近修改的启动盘").changed() {
+            config.auto_select_recent_drive = auto_select;
+            let _ = config.save();
+        }
+        
+        let mut prefer_native = config.prefer_native_drive;
+        if ui.checkbox(&mut prefer_native, "检测到多个启动盘时优先选择唯一的原生启动盘（不含兼容模式识别的 Cloud-PE 启动盘）").changed() {
+            config.prefer_native_drive = prefer_native;
+            let _ = config.save();
+        }
     }
     
     fn show_download_settings(&mut self, ui: &mut egui::Ui) {
@@ -152,10 +313,156 @@ impl SettingsPage {
         });
         
         ui.horizontal(|ui| {
-            ui.label("默认下载路径：");
+            ui.label("下载出错时重试：");
+            
+            let mut config = self.config.write();
+            let mut retries = config.download_retries;
+            
+            egui::ComboBox::from_id_salt("download_retries")
+                .selected_text(if retries == 0 { "不重试".to_string() } else { format!("{} 次", retries) })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut retries, 0, "不重试");
+                    ui.selectable_value(&mut retries, 1, "1 次");
+                    ui.selectable_value(&mut retries, 3, "3 次");
+                    ui.selectable_value(&mut retries, 5, "5 次");
+                })
+                .response
+                .on_hover_text("网络中断或服务器暂时不可用时，分别等待 1、2、4 秒……后重试，安装插件时从中断处继续下载");
+            
+            if retries != config.download_retries {
+                config.download_retries = retries;
+                let _ = config.save();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("下载超时：");
+            
+            let mut config = self.config.write();
+            let mut timeout = config.download_timeout_secs;
+            
+            egui::ComboBox::from_id_salt("download_timeout")
+                .selected_text(format!("{} 秒", timeout))
+                .show_ui(ui, |ui| {
+                    for secs in [15, 30, 60, 120] {
+                        ui.selectable_value(&mut timeout, secs, format!("{} 秒", secs));
+                    }
+                })
+                .response
+                .on_hover_text("连接服务器或等待数据超过这个时间即视为下载失败，之后按重试设置重试");
+            
+            if timeout != config.download_timeout_secs {
+                config.download_timeout_secs = timeout;
+                config.apply_network_settings();
+                let _ = config.save();
+            }
+        });
+        
+        self.show_proxy_settings(ui);
+        
+        if self.config.read().download_threads > HIGH_THREAD_COUNT {
+            ui.label(
+                egui::RichText::new("线程数较高时，小文件或较慢的网络下载反而可能变慢。下载时会按文件大小自动减少分段，约每 4 MB 一段")
+                    .small()
+                    .weak(),
+            );
+        }
+        
+        {
+            let mut config = self.config.write();
+            let mut safe_install = config.safe_install;
+            if ui.checkbox(&mut safe_install, "安全安装模式")
+                .on_hover_text("先下载到本地临时目录并校验，再复制到启动盘，减少 U 盘上出现不完整插件的可能")
+                .changed()
+            {
+                config.safe_install = safe_install;
+                let _ = config.save();
+            }
+            
+            let mut allow_downgrade = config.allow_downgrade;
+            if ui.checkbox(&mut allow_downgrade, "允许降级安装")
+                .on_hover_text("启动盘上的插件比市场中的版本新时，显示“降级安装”按钮，用市场中的版本替换")
+                .changed()
+            {
+                config.allow_downgrade = allow_downgrade;
+                let _ = config.save();
+            }
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("平台兼容性检查：");
+            
+            let mut config = self.config.write();
+            let mut check = config.compatibility_check;
+            
+            egui::ComboBox::from_id_salt("compatibility_check")
+                .selected_text(compatibility_check_label(check))
+                .show_ui(ui, |ui| {
+                    for option in [CompatibilityCheck::Warn, CompatibilityCheck::Block, CompatibilityCheck::Off] {
+                        ui.selectable_value(&mut check, option, compatibility_check_label(option));
+                    }
+                })
+                .response
+                .on_hover_text("插件标注的适用平台与当前插件源不符时的处理方式，未标注平台的插件不受影响");
+            
+            if check != config.compatibility_check {
+                config.compatibility_check = check;
+                let _ = config.save();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("插件频道：");
+            
+            let mut config = self.config.write();
+            let mut channel = config.channel;
+            
+            egui::ComboBox::from_id_salt("release_channel")
+                .selected_text(match channel {
+                    ReleaseChannel::Stable => "正式版",
+                    ReleaseChannel::Testing => "测试版",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut channel, ReleaseChannel::Stable, "正式版（默认）");
+                    ui.selectable_value(&mut channel, ReleaseChannel::Testing, "测试版");
+                });
+            
+            if channel != config.channel {
+                config.channel = channel;
+                let _ = config.save();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("下载完成后：");
+            
+            let mut config = self.config.write();
+            let mut action = config.post_download_action;
+            
+            egui::ComboBox::from_id_salt("post_download_action")
+                .selected_text(post_download_action_label(action))
+                .show_ui(ui, |ui| {
+                    for option in [
+                        PostDownloadAction::Toast,
+                        PostDownloadAction::OpenFolder,
+                        PostDownloadAction::OpenFile,
+                        PostDownloadAction::None,
+                    ] {
+                        ui.selectable_value(&mut action, option, post_download_action_label(option));
+                    }
+                });
+            
+            if action != config.post_download_action {
+                config.post_download_action = action;
+                let _ = config.save();
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label(format!("默认下载路径（{}）：", self.mode.get_server_name()));
             
             let config = self.config.read();
-            if let Some(path) = &config.default_download_path {
+            if let Some(path) = config.download_path(self.mode) {
                 ui.label(path.display().to_string());
             } else {
                 ui.label("未设置");
@@ -170,11 +477,247 @@ impl SettingsPage {
                 {
                     drop(config);
                     let mut config = self.config.write();
-                    config.default_download_path = Some(path);
+                    config.set_download_path(self.mode, path);
                     let _ = config.save();
                 }
             }
         });
+        
+        let mut config = self.config.write();
+        let mut use_server_filename = config.use_server_filename;
+        if ui.checkbox(&mut use_server_filename, "使用服务器文件名")
+            .on_hover_text("下载到本地文件夹时，优先使用服务器提供的文件名；安装到启动盘时仍按插件信息命名")
+            .changed()
+        {
+            config.use_server_filename = use_server_filename;
+            let _ = config.save();
+        }
+        
+        let mut category_subfolder = config.category_subfolder;
+        if ui.checkbox(&mut category_subfolder, "按分类建立子文件夹")
+            .on_hover_text("下载到本地文件夹时，放入以插件分类命名的子文件夹中")
+            .changed()
+        {
+            config.category_subfolder = category_subfolder;
+            let _ = config.save();
+        }
+    }
+    
+    fn show_proxy_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("代理服务器：");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.proxy_text)
+                    .hint_text("http://代理地址:端口，留空则直接连接")
+                    .desired_width(240.0),
+            )
+            .on_hover_text("插件列表、连通性检测和下载都通过此代理访问网络。需要认证时可写为 http://用户名:密码@代理地址:端口");
+            
+            let saved = self.config.read().proxy_url.clone().unwrap_or_default();
+            let text = self.proxy_text.trim().to_string();
+            if ui.add_enabled(text != saved, egui::Button::new("应用")).clicked() {
+                let result = if text.is_empty() {
+                    Ok(())
+                } else {
+                    crate::network::validate_proxy_url(&text).map(|_| ())
+                };
+                
+                match result {
+                    Ok(()) => {
+                        let mut config = self.config.write();
+                        config.proxy_url = (!text.is_empty()).then_some(text);
+                        config.apply_network_settings();
+                        let _ = config.save();
+                        self.proxy_error = None;
+                    }
+                    Err(e) => self.proxy_error = Some(e),
+                }
+            }
+        });
+        
+        // 配置文件中的代理地址也可能无效（如手动修改过），此时所有网络请求都会被拒绝
+        if let Some(error) = self.proxy_error.clone().or_else(crate::network::proxy_problem) {
+            ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+        }
+    }
+    
+    fn show_network_settings(&mut self, ui: &mut egui::Ui) {
+        let mut config = self.config.write();
+        let mut changed = false;
+        
+        let mut offline_mode = config.offline_mode;
+        if ui.checkbox(&mut offline_mode, "离线模式")
+            .on_hover_text("禁止所有网络请求，只管理启动盘上已有的插件")
+            .changed()
+        {
+            config.offline_mode = offline_mode;
+            changed = true;
+        }
+        
+        let mut prefetch = config.prefetch_plugin_lists;
+        if ui.checkbox(&mut prefetch, "在后台预取其他插件源的插件列表")
+            .on_hover_text("切换插件源时无需等待加载，无法联网时显示缓存的列表。离线模式或按流量计费的网络下不会预取")
+            .changed()
+        {
+            config.prefetch_plugin_lists = prefetch;
+            changed = true;
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("连通性检测：");
+            
+            let mut strategy = config.connect_test;
+            egui::ComboBox::from_id_salt("connect_test")
+                .selected_text(connect_test_label(strategy))
+                .show_ui(ui, |ui| {
+                    for option in [ConnectTestStrategy::Head, ConnectTestStrategy::Full] {
+                        ui.selectable_value(&mut strategy, option, connect_test_label(option));
+                    }
+                });
+            
+            if strategy != config.connect_test {
+                config.connect_test = strategy;
+                changed = true;
+            }
+        });
+        
+        let mut tls_pinning = config.tls_pinning;
+        if ui.checkbox(&mut tls_pinning, "启用证书固定（仅信任指定证书）").changed() {
+            config.tls_pinning = tls_pinning;
+            changed = true;
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label("固定证书：");
+            match &config.pinned_certificate_path {
+                Some(path) => ui.label(path.display().to_string()),
+                None => ui.label("未设置"),
+            };
+            
+            if ui.button("浏览").clicked() {
+                use rfd::FileDialog;
+                
+                if let Some(path) = FileDialog::new()
+                    .set_title("选择固定证书")
+                    .add_filter("证书", &["pem", "crt", "cer", "der"])
+                    .pick_file()
+                {
+                    config.pinned_certificate_path = Some(path);
+                    changed = true;
+                }
+            }
+        });
+        
+        if config.tls_pinning {
+            ui.label(egui::RichText::new("固定的是证书公钥，服务器更换密钥后需要更新固定证书，否则将无法连接服务器").small());
+            
+            if let Some(problem) = crate::network::pinning_problem() {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("{}，所有 HTTPS 连接都会被拒绝", problem));
+            }
+        }
+        
+        ui.separator();
+        ui.label("认证请求头：")
+            .on_hover_text("访问需要令牌的私有插件源或镜像时，按主机名附加请求头");
+        
+        let mut removed = None;
+        for (index, entry) in config.source_auth.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::TextEdit::singleline(&mut entry.host).hint_text("主机名").desired_width(140.0)).changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut entry.header_name).hint_text("请求头").desired_width(110.0)).changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut entry.header_value).hint_text("值").password(true).desired_width(140.0)).changed();
+                if ui.small_button("删除").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            config.source_auth.remove(index);
+            changed = true;
+        }
+        if ui.button("添加").clicked() {
+            config.source_auth.push(SourceAuth::default());
+            changed = true;
+        }
+        
+        if changed {
+            config.apply_network_settings();
+            let _ = config.save();
+        }
+    }
+    
+    fn show_advanced_settings(&mut self, ui: &mut egui::Ui) {
+        if !self.raw_config_editing {
+            // 代理地址、认证请求头等隐私只显示为占位，编辑时保留占位即保持原值
+            let current = serde_json::to_string_pretty(&crate::support_bundle::redacted_config(&self.config.read()))
+                .unwrap_or_default();
+            
+            ui.label("当前配置：");
+            ui.add(
+                egui::TextEdit::multiline(&mut current.as_str())
+                    .code_editor()
+                    .desired_width(f32::INFINITY),
+            );
+            
+            if ui.button("编辑配置").clicked() {
+                self.raw_config_text = current;
+                self.raw_config_error = None;
+                self.raw_config_editing = true;
+            }
+            return;
+        }
+        
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 160, 0),
+            "直接修改配置可能导致程序行为异常，请确认了解各项含义后再应用",
+        );
+        
+        ui.add(
+            egui::TextEdit::multiline(&mut self.raw_config_text)
+                .code_editor()
+                .desired_width(f32::INFINITY),
+        );
+        
+        ui.label(egui::RichText::new("显示为 <已隐藏> 的值保持不变，需要修改时直接替换").small());
+        
+        if let Some(error) = &self.raw_config_error {
+            ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+        }
+        
+        ui.horizontal(|ui| {
+            if ui.button("应用").clicked() {
+                match self.parse_raw_config() {
+                    Ok(new_config) => {
+                        let mut config = self.config.write();
+                        *config = new_config;
+                        config.apply_network_settings();
+                        self.font_scale = config.font_scale;
+                        
+                        match config.save() {
+                            Ok(_) => {
+                                self.raw_config_error = None;
+                                self.raw_config_editing = false;
+                            }
+                            Err(e) => self.raw_config_error = Some(format!("保存失败: {}", e)),
+                        }
+                    }
+                    Err(e) => self.raw_config_error = Some(e),
+                }
+            }
+            
+            if ui.button("取消").clicked() {
+                self.raw_config_editing = false;
+                self.raw_config_error = None;
+            }
+        });
+    }
+    
+    // 解析编辑后的配置，仍为占位的隐私项换回当前的值
+    fn parse_raw_config(&self) -> Result<AppConfig, String> {
+        let mut value = serde_json::from_str::<serde_json::Value>(&self.raw_config_text)
+            .map_err(|e| format!("配置格式错误: {}", e))?;
+        crate::support_bundle::restore_redacted(&mut value, &self.config.read())?;
+        serde_json::from_value(value).map_err(|e| format!("配置格式错误: {}", e))
     }
     
     fn show_about(&mut self, ui: &mut egui::Ui) {
@@ -207,27 +750,144 @@ impl SettingsPage {
             }
             _ => {}
         }
+        
+        ui.separator();
+        
+        ui.horizontal(|ui| {
+            if ui.button("复制诊断信息")
+                .on_hover_text("复制版本、启动盘和设置等信息，反馈问题时可直接粘贴")
+                .clicked()
+            {
+                ui.ctx().copy_text(self.diagnostic_report());
+                self.diagnostics_copied = true;
+            }
+            if self.diagnostics_copied {
+                ui.weak("已复制到剪贴板");
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            if ui.button("导出日志并打包")
+                .on_hover_text("将诊断信息、设置（已隐去路径和认证信息）和运行日志打包为 ZIP 文件，反馈问题时可作为附件")
+                .clicked()
+            {
+                self.export_support_bundle();
+            }
+            match &self.bundle_result {
+                Some(Ok(path)) => {
+                    ui.weak(format!("已保存到 {}", path.display()));
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("导出失败: {}", e));
+                }
+                None => {}
+            }
+        });
     }
-}
-
-#[cfg(target_os = "windows")]
-unsafe fn set_dwm_theme(mode: &ColorMode) {
-    let hwnd = GetActiveWindow();
-    if !hwnd.is_null() {
-        let dark_mode = match mode {
-            ColorMode::System => {
-                // 跟随系统模式
-                if dark_light::detect() == dark_light::Mode::Dark { 1i32 } else { 0i32 }
-            }
-            ColorMode::Light => 0i32,
-            ColorMode::Dark => 1i32,
+    
+    fn export_support_bundle(&mut self) {
+        use rfd::FileDialog;
+        
+        let file_name = format!("CloudPE-诊断-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let Some(path) = FileDialog::new()
+            .set_title("保存诊断包")
+            .set_file_name(file_name)
+            .add_filter("ZIP 压缩包", &["zip"])
+            .save_file()
+        else {
+            return;
         };
         
-        DwmSetWindowAttribute(
-            hwnd as _,
-            20, // DWMWA_USE_IMMERSIVE_DARK_MODE
-            &dark_mode as *const _ as *mut _,
-            mem::size_of::<i32>() as u32,
-        );
+        let config = self.config.read().clone();
+        let result = crate::support_bundle::export(&path, &self.diagnostic_report(), &config);
+        crate::app_log::log(match &result {
+            Ok(()) => format!("已导出诊断包: {}", path.display()),
+            Err(e) => format!("导出诊断包失败: {}", e),
+        });
+        self.bundle_result = Some(result.map(|()| path).map_err(|e| e.to_string()));
+    }
+    
+    // 汇总反馈问题所需的信息。路径和搜索记录可能包含用户名等隐私，只记录是否设置
+    fn diagnostic_report(&self) -> String {
+        let mut lines = vec![
+            format!("版本: {}", env!("CARGO_PKG_VERSION")),
+            format!("模式: {}", self.mode.get_title()),
+            format!("系统: {} {}", std::env::consts::OS, std::env::consts::ARCH),
+            format!("PE 环境: {}", if is_pe_environment() { "是" } else { "否" }),
+            format!("配置目录: {}", if config::portable_data_dir().is_some() { "程序所在目录" } else { "用户配置目录" }),
+            format!("离线模式: {}", if crate::network::is_offline_mode() { "是" } else { "否" }),
+            format!("连接检测: {}", match crate::network::last_connection_test() {
+                Some(true) => "成功",
+                Some(false) => "失败",
+                None => "未检测",
+            }),
+        ];
+        
+        let manager = self.boot_drive_manager.read();
+        lines.push(format!("当前启动盘: {}", manager.get_current_drive().unwrap_or_else(|| "未选择".to_string())));
+        let drives = manager.get_all_drives();
+        if drives.is_empty() {
+            lines.push("检测到的启动盘: 无".to_string());
+        } else {
+            lines.push("检测到的启动盘:".to_string());
+            lines.extend(drives.iter().map(|drive| format!("  {}", drive.display_name())));
+        }
+        drop(manager);
+        
+        let config = self.config.read();
+        let is_set = |value: bool| if value { "已设置" } else { "未设置" };
+        lines.push("设置:".to_string());
+        lines.extend([
+            format!("  颜色模式: {:?}", config.color_mode),
+            format!("  下载线程数: {}", config.download_threads),
+            format!("  下载重试次数: {}", config.download_retries),
+            format!("  下载超时: {} 秒", config.download_timeout_secs),
+            format!("  代理服务器: {}", is_set(config.proxy_url.is_some())),
+            format!("  默认启动盘: {}", config.default_boot_drive.as_deref().unwrap_or("未设置")),
+            format!("  默认下载路径: 当前插件源{}，共 {} 个插件源已设置", is_set(config.download_path(self.mode).is_some()), config.download_paths.len()),
+            format!("  自动选择最近使用的启动盘: {}", config.auto_select_recent_drive),
+            format!("  优先选择原生启动盘: {}", config.prefer_native_drive),
+            format!("  界面缩放: {}", config.font_scale),
+            format!("  每次显示的搜索结果: {}", config.search_page_size),
+            format!("  高对比度: {}", config.high_contrast),
+            format!("  隐藏到托盘: {}", config.minimize_to_tray),
+            format!("  证书固定: {} (证书路径{})", config.tls_pinning, is_set(config.pinned_certificate_path.is_some())),
+            format!("  安全安装: {}", config.safe_install),
+            format!("  允许降级安装: {}", config.allow_downgrade),
+            format!("  下载完成后: {}", post_download_action_label(config.post_download_action)),
+            format!("  更新通道: {:?}", config.channel),
+            format!("  连接检测方式: {}", connect_test_label(config.connect_test)),
+            format!("  预取插件列表: {}", config.prefetch_plugin_lists),
+            format!("  使用服务器文件名: {}", config.use_server_filename),
+            format!("  按分类建立子文件夹: {}", config.category_subfolder),
+            format!("  平台兼容性检查: {}", compatibility_check_label(config.compatibility_check)),
+            format!("  认证请求头: {} 条", config.source_auth.len()),
+        ]);
+        
+        lines.join("\n")
+    }
+}
+
+fn compatibility_check_label(check: CompatibilityCheck) -> &'static str {
+    match check {
+        CompatibilityCheck::Warn => "安装前提示（默认）",
+        CompatibilityCheck::Block => "拒绝安装",
+        CompatibilityCheck::Off => "不检查",
+    }
+}
+
+fn connect_test_label(strategy: ConnectTestStrategy) -> &'static str {
+    match strategy {
+        ConnectTestStrategy::Head => "仅检测能否访问（默认）",
+        ConnectTestStrategy::Full => "完整请求测试地址",
+    }
+}
+
+fn post_download_action_label(action: PostDownloadAction) -> &'static str {
+    match action {
+        PostDownloadAction::None => "不做任何操作",
+        PostDownloadAction::Toast => "显示提示（默认）",
+        PostDownloadAction::OpenFolder => "打开所在文件夹",
+        PostDownloadAction::OpenFile => "打开文件",
     }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use crate::mode::PluginMode;
@@ -8,8 +8,22 @@ use crate::mode::PluginMode;
 pub struct BootDrive {
     pub letter: String,
     pub version: String,
+    // false 表示以兼容模式识别的 Cloud-PE 启动盘
+    #[serde(default)]
+    pub native: bool,
 }
 
+impl BootDrive {
+    // 下拉框等处显示的名称，带上检测到的版本便于确认启动盘
+    pub fn display_name(&self) -> String {
+        format!("{} ({})", self.letter, self.version)
+    }
+}
+
+// Edgeless 启动盘在 Edgeless\version.txt 中记录版本，内容形如 Edgeless_Beta_4.1.0，Edgeless Hub 也从这里读取。
+// HotPE 没有公开说明的版本文件，不猜测路径，只显示 HotPE
+const EDGELESS_VERSION_FILE: &str = "Edgeless\\version.txt";
+
 pub struct BootDriveManager {
     boot_drives: Vec<BootDrive>,
     current_drive: Option<String>,
@@ -38,13 +52,20 @@ impl BootDriveManager {
                     let config_path = format!("{}\\cloud-pe\\config.json", drive_letter);
                     let iso_path = format!("{}\\Cloud-PE.iso", drive_letter);
                     
+                    // 有 ISO 即可确认是 Cloud-PE 启动盘。config.json 损坏或被占用时仍然列出，版本显示为未知
                     if Path::new(&config_path).exists() && Path::new(&iso_path).exists() {
-                        if let Ok(version) = self.read_cloudpe_version(&drive_letter) {
-                            drives.push(BootDrive {
-                                letter: drive_letter,
-                                version,
-                            });
-                        }
+                        let version = match self.read_cloudpe_version(&drive_letter) {
+                            Ok(version) => version,
+                            Err(e) => {
+                                crate::app_log::log(format!("读取 {} 的 config.json 失败: {}", drive_letter, e));
+                                "未知".to_string()
+                            }
+                        };
+                        drives.push(BootDrive {
+                            letter: drive_letter,
+                            version,
+                            native: true,
+                        });
                     }
                 }
                 PluginMode::HotPE => {
@@ -55,6 +76,7 @@ impl BootDriveManager {
                         drives.push(BootDrive {
                             letter: drive_letter.clone(),
                             version: "HotPE".to_string(),
+                            native: true,
                         });
                     } else {
                         // 如果没有，检查是否是Cloud-PE启动盘
@@ -64,8 +86,9 @@ impl BootDriveManager {
                         if Path::new(&config_path).exists() && Path::new(&iso_path).exists() {
                             // 是Cloud-PE启动盘，也算作HotPE启动盘
                             drives.push(BootDrive {
+                                version: self.compatible_cloudpe_label(&drive_letter, "HotPE兼容"),
                                 letter: drive_letter,
-                                version: "Cloud-PE (HotPE兼容)".to_string(),
+                                native: false,
                             });
                         }
                     }
@@ -75,9 +98,14 @@ impl BootDriveManager {
                     
                     // 先检查是否有Edgeless\Resource文件夹
                     if Path::new(&edgeless_resource_path).exists() {
+                        let version = match read_version_file(&drive_letter, EDGELESS_VERSION_FILE) {
+                            Ok(version) => format!("Edgeless {}", version),
+                            Err(_) => "Edgeless".to_string(),
+                        };
                         drives.push(BootDrive {
                             letter: drive_letter.clone(),
-                            version: "Edgeless".to_string(),
+                            version,
+                            native: true,
                         });
                     } else {
                         // 如果没有，检查是否是Cloud-PE启动盘
@@ -87,8 +115,9 @@ impl BootDriveManager {
                         if Path::new(&config_path).exists() && Path::new(&iso_path).exists() {
                             // 是Cloud-PE启动盘，也算作Edgeless启动盘
                             drives.push(BootDrive {
+                                version: self.compatible_cloudpe_label(&drive_letter, "Edgeless兼容"),
                                 letter: drive_letter,
-                                version: "Cloud-PE (Edgeless兼容)".to_string(),
+                                native: false,
                             });
                         }
                     }
@@ -97,6 +126,7 @@ impl BootDriveManager {
             }
         }
         
+        sort_drives(&mut drives);
         drives
     }
     
@@ -112,6 +142,39 @@ impl BootDriveManager {
             .ok_or_else(|| anyhow::anyhow!("无法读取版本信息"))
     }
     
+    // 兼容模式下的 Cloud-PE 启动盘，能读到版本时一并显示
+    fn compatible_cloudpe_label(&self, drive_letter: &str, compat: &str) -> String {
+        match self.read_cloudpe_version(drive_letter) {
+            Ok(version) => format!("Cloud-PE {} ({})", version, compat),
+            Err(_) => format!("Cloud-PE ({})", compat),
+        }
+    }
+    
+    // 选出插件目录最近修改过的启动盘，插件目录不存在时以盘符根目录的修改时间为准
+    pub fn most_recent_drive(&self) -> Option<BootDrive> {
+        self.boot_drives
+            .iter()
+            .filter_map(|drive| {
+                let modified = fs::metadata(plugin_dir(&drive.letter, self.mode))
+                    .or_else(|_| fs::metadata(drive_path(&drive.letter, "")))
+                    .and_then(|m| m.modified())
+                    .ok()?;
+                Some((modified, drive))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, drive)| drive.clone())
+    }
+    
+    // 只有一个原生启动盘、其余都是兼容模式识别的启动盘时返回该原生启动盘
+    pub fn only_native_drive(&self) -> Option<BootDrive> {
+        let mut native = self.boot_drives.iter().filter(|drive| drive.native);
+        let drive = native.next()?;
+        if native.next().is_some() {
+            return None;
+        }
+        Some(drive.clone())
+    }
+    
     pub fn get_all_drives(&self) -> Vec<BootDrive> {
         self.boot_drives.clone()
     }
@@ -120,11 +183,236 @@ impl BootDriveManager {
         self.current_drive.clone()
     }
     
+    // 当前启动盘的完整信息，含检测到的版本
+    pub fn get_current_boot_drive(&self) -> Option<BootDrive> {
+        let current = self.current_drive.as_ref()?;
+        self.boot_drives.iter().find(|drive| drive.letter.eq_ignore_ascii_case(current)).cloned()
+    }
+    
+    // 盘符统一为 "X:" 的形式，无法识别时原样保存
     pub fn set_current_drive(&mut self, drive: String) {
-        self.current_drive = Some(drive);
+        self.current_drive = Some(normalize_drive_letter(&drive).unwrap_or(drive));
     }
     
     pub fn reload(&mut self) {
         self.boot_drives = self.scan_boot_drives();
     }
+    
+    // 在后台扫描完成后替换启动盘列表，扫描期间不必持有写锁
+    pub fn replace_drives(&mut self, drives: Vec<BootDrive>) {
+        self.boot_drives = drives;
+    }
+    
+    // 启动盘的可用空间（字节），无法获取时返回 None
+    pub fn get_free_space(drive: &str) -> Option<u64> {
+        available_space(&normalize_drive_letter(drive)?)
+    }
+}
+// 把 "X"、"x:"、"X:\\"、"X:/" 等写法统一为大写的 "X:"，不是单个盘符时返回 None
+pub fn normalize_drive_letter(drive: &str) -> Option<String> {
+    let drive = drive.trim().trim_end_matches(['\\', '/']);
+    let drive = drive.strip_suffix(':').unwrap_or(drive);
+    
+    let mut chars = drive.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Some(format!("{}:", letter.to_ascii_uppercase())),
+        _ => None,
+    }
+}
+
+// 启动盘上的路径，relative 为相对盘符根目录的路径，为空时返回根目录。
+// 所有启动盘上的路径都经由这里拼接，盘符带不带冒号或末尾分隔符都能得到正确的路径
+pub fn drive_path(drive: &str, relative: &str) -> PathBuf {
+    let root = normalize_drive_letter(drive)
+        .unwrap_or_else(|| drive.trim_end_matches(['\\', '/']).to_string());
+    PathBuf::from(format!("{}\\{}", root, relative.trim_start_matches(['\\', '/'])))
+}
+
+// 启动盘上当前插件源的插件目录
+pub fn plugin_dir(drive: &str, mode: PluginMode) -> PathBuf {
+    drive_path(drive, mode.get_plugin_folder())
+}
+
+// 读取版本文件，从第一行非空内容中取出版本号。
+// 版本文件中带有前缀，如 "Edgeless_Beta_4.1.0"，只保留数字部分才能与插件要求的版本比较
+fn read_version_file(drive_letter: &str, relative: &str) -> Result<String> {
+    let content = fs::read_to_string(format!("{}\\{}", drive_letter, relative))?;
+    let line = content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("版本文件为空"))?;
+    
+    extract_version_number(line).ok_or_else(|| anyhow::anyhow!("版本文件中没有版本号: {}", line))
+}
+
+// 取出文本中第一段由数字和点组成的内容，如 "Edgeless_Beta_4.1.0" 中的 "4.1.0"
+fn extract_version_number(text: &str) -> Option<String> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let rest = &text[start..];
+    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+    Some(rest[..end].trim_end_matches('.').to_string())
+}
+
+// 把任意文本转换为合法的 Windows 文件名：替换保留字符和控制字符，
+// 去掉末尾的点和空格，并避开 CON、NUL 等设备名
+pub fn sanitize_windows_filename(name: &str) -> String {
+    const RESERVED_NAMES: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL",
+        "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+        "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches(['.', ' ']).to_string();
+    
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+        format!("_{}", sanitized)
+    } else {
+        sanitized
+    }
+}
+
+// 按盘符排序并去重，保证下拉框和启动盘选择对话框中的顺序在每次刷新后保持一致
+fn sort_drives(drives: &mut Vec<BootDrive>) {
+    drives.sort_by_key(|drive| drive.letter.to_ascii_uppercase());
+    drives.dedup_by(|a, b| a.letter.eq_ignore_ascii_case(&b.letter));
+}
+
+// 检测是否在 PE 环境
+pub fn is_pe_environment() -> bool {
+    // PE 环境通常有这些特征
+    std::env::var("X:").is_ok() || 
+    std::env::var("WINPE").is_ok() ||
+    std::env::current_dir()
+        .ok()
+        .and_then(|p| p.to_str().map(|s| s.starts_with("X:")))
+        .unwrap_or(false)
+}
+
+// 非 PE 环境下判断盘符是否为正在运行的系统盘
+pub fn is_system_drive(drive_letter: &str) -> bool {
+    if is_pe_environment() {
+        return false;
+    }
+    
+    std::env::var("SystemDrive")
+        .map(|system_drive| system_drive.trim_end_matches('\\').eq_ignore_ascii_case(drive_letter.trim_end_matches('\\')))
+        .unwrap_or(false)
+}
+
+// 通过创建并删除临时文件检测目录是否可写
+pub fn is_dir_writable(dir: &Path) -> bool {
+    if !dir.is_dir() {
+        return false;
+    }
+    
+    let probe_path = dir.join(format!(".cloudpe_write_test_{}", std::process::id()));
+    match fs::File::create(&probe_path) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe_path);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// 查询盘符的可用空间（字节），无法获取时返回 None
+#[cfg(target_os = "windows")]
+pub fn available_space(drive_letter: &str) -> Option<u64> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    
+    let root: Vec<u16> = format!("{}\\", drive_letter.trim_end_matches('\\'))
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    let mut available = 0u64;
+    
+    unsafe {
+        GetDiskFreeSpaceExW(PCWSTR(root.as_ptr()), Some(&mut available as *mut u64), None, None).ok()?;
+    }
+    
+    Some(available)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn available_space(_drive_letter: &str) -> Option<u64> {
+    None
+}
+
+// 查询剩余的物理内存（字节），无法获取时返回 None
+#[cfg(target_os = "windows")]
+pub fn available_memory() -> Option<u64> {
+    use windows::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+    
+    let mut status = MEMORYSTATUSEX {
+        dwLength: std::mem::size_of::<MEMORYSTATUSEX>() as u32,
+        ..Default::default()
+    };
+    
+    unsafe {
+        GlobalMemoryStatusEx(&mut status).ok()?;
+    }
+    
+    Some(status.ullAvailPhys)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn available_memory() -> Option<u64> {
+    None
+}
+
+// PE 的 X: 盘是内存盘，写入的文件直接占用内存。
+// 路径位于内存盘时返回剩余内存，否则返回 None
+pub fn ram_disk_free_memory(path: &Path) -> Option<u64> {
+    let on_ram_disk = path.to_str()
+        .and_then(|path| path.get(..2))
+        .is_some_and(|drive| drive.eq_ignore_ascii_case("X:"));
+    
+    if !on_ram_disk || !is_pe_environment() {
+        return None;
+    }
+    
+    available_memory()
+}
+
+// 用资源管理器打开文件夹，或用关联程序打开文件
+pub fn open_in_explorer(path: &Path) {
+    let _ = std::process::Command::new("explorer").arg(path).spawn();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn drive_letter_variants_normalize() {
+        for drive in ["X", "X:", "X:\\", "x:", "x:/", " X: "] {
+            assert_eq!(normalize_drive_letter(drive).as_deref(), Some("X:"), "{:?}", drive);
+        }
+    }
+    
+    #[test]
+    fn non_drive_letters_are_rejected() {
+        for drive in ["", ":", "XY:", "1:", "\\\\server\\share"] {
+            assert_eq!(normalize_drive_letter(drive), None, "{:?}", drive);
+        }
+    }
+    
+    #[test]
+    fn drive_paths_join_once() {
+        assert_eq!(drive_path("x", "cloud-pe\\config.json"), PathBuf::from("X:\\cloud-pe\\config.json"));
+        assert_eq!(drive_path("X:\\", "\\ce-apps"), PathBuf::from("X:\\ce-apps"));
+    }
 }
\ No newline at end of file